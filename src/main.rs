@@ -1,5 +1,5 @@
 mod navigation;
-use navigation::{database, aircraft::{self, Aircraft}, airport::Airport, flightplan::NavBranch, geographics::{calculate_distance_and_course, NavPoint}};
+use navigation::{database, aircraft::{self, Aircraft}, airport::Airport, coordinates::{self, CoordinateFormat}, import::{self, AirportImportFilter}, search::{self, AirportFilter}, spatial, user_waypoint::{UserWaypoint, UserWaypointType}, flightplan::{NavBranch, Wind}, geographics::{calculate_distance_and_course, EarthModel, NavPoint}, plan::{PlanDocument, RecentPlan}, route::{self, RoutePoint}, error::PlanError};
 /*
 #[tokio::main]
 async fn main() {
@@ -49,9 +49,220 @@ slint::include_modules!();
 use std::rc::Rc;
 use std::cell::RefCell;
 use slint::{ModelRc, SharedString, VecModel};
+use chrono::NaiveDateTime;
 
 
+const USAGE: &str = "Usage:
+    flight_planner import-airports <airports.csv> [--country FR] [--continent EU] [--type small_airport]
+    flight_planner import-runways <runways.csv>
+    flight_planner import-frequencies <airport-frequencies.csv>
+    flight_planner import-navaids <navaids.csv>
+    flight_planner import-fuels <airport-fuels.csv>";
+
+/// Runs an import subcommand on the OurAirports CSV files
+fn import_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let (Some(command), Some(csv_path)) = (args.first(), args.get(1)) else {
+        return Err(USAGE.into());
+    };
+    match command.as_str() {
+        "import-airports" => {
+            let mut filter = AirportImportFilter::default();
+            let mut options = args[2..].iter();
+            while let Some(option) = options.next() {
+                let value = options.next().ok_or(USAGE)?.clone();
+                match option.as_str() {
+                    "--country" => filter.countries.push(value),
+                    "--continent" => filter.continents.push(value),
+                    "--type" => filter.types.push(value),
+                    _ => return Err(USAGE.into()),
+                }
+            }
+            let report = import::import_airports("../../data/airports.db", csv_path, &filter)?;
+            println!(
+                "Airports: {} inserted, {} updated ({} closed), {} unchanged",
+                report.inserted, report.updated, report.closed, report.unchanged
+            );
+        }
+        "import-runways" => println!("Runways: {} imported", import::import_runways("../../data/airports.db", csv_path)?),
+        "import-frequencies" => println!("Frequencies: {} imported", import::import_frequencies("../../data/airports.db", csv_path)?),
+        "import-navaids" => println!("Navaids: {} imported", import::import_navaids("../../data/airports.db", csv_path)?),
+        "import-fuels" => println!("Airport fuels: {} imported", import::import_fuels("../../data/airports.db", csv_path)?),
+        _ => return Err(USAGE.into()),
+    }
+    Ok(())
+}
+
+/// Reads the waypoint edited in the GUI
+fn user_waypoint_from_view(view: &WaypointView) -> Result<UserWaypoint, Box<dyn std::error::Error>> {
+    if view.name.trim().is_empty() {
+        return Err("Name is missing".into());
+    }
+    let (latitude, longitude) = coordinates::parse(&view.position)?;
+    let airport = Some(view.airport.trim().to_uppercase()).filter(|airport| !airport.is_empty());
+    Ok(UserWaypoint::new(view.name.trim().to_string(), latitude, longitude, UserWaypointType::from_db_type(&view.waypoint_type), airport, view.notes.to_string()))
+}
+
+/// Reloads the user waypoints and shows them in the waypoint list
+fn refresh_waypoints(main_window: &MainWindow, waypoints: &RefCell<Vec<UserWaypoint>>) {
+    *waypoints.borrow_mut() = UserWaypoint::list("../../data/airports.db").unwrap_or_default();
+    let names: Vec<SharedString> = waypoints
+        .borrow()
+        .iter()
+        .map(|w| SharedString::from(match &w.airport {
+            Some(airport) => format!("{} ({})", w.name, airport),
+            None => w.name.clone(),
+        }))
+        .collect();
+    main_window.set_waypoint_list(ModelRc::new(VecModel::from(names)));
+    main_window.set_selected_waypoint(-1);
+}
+
+/// Altitude field of the plan view, in ft, empty for none
+fn parse_altitude(text: &str) -> Result<Option<f64>, Box<dyn std::error::Error>> {
+    Ok(Some(text.trim()).filter(|a| !a.is_empty()).map(str::parse::<f64>).transpose()?)
+}
+
+/// Wind field of the plan view, direction/speed, empty for calm
+fn parse_wind(text: &str) -> Result<Wind, Box<dyn std::error::Error>> {
+    match text.trim().split_once('/') {
+        Some((direction, speed)) => Ok(Wind { direction: direction.trim().parse()?, speed: speed.trim().parse()? }),
+        None if text.trim().is_empty() => Ok(Wind { direction: 0.0, speed: 0.0 }),
+        None => Err("Wind must be direction/speed, e.g. 270/15".into()),
+    }
+}
+
+/// Builds a plan from the fields of the plan view, flown by the selected aircraft.
+/// With the plan last opened or saved, only the fields edited since it was shown are applied to it,
+/// so that what the view does not show (altitude and wind of the other legs, leg types, loading) is kept.
+fn plan_from_view(current: Option<&PlanDocument>, view: &PlanView, registration: &str) -> Result<PlanDocument, Box<dyn std::error::Error>> {
+    let altitude = parse_altitude(&view.altitude)?;
+    let wind = parse_wind(&view.wind)?;
+    let route_plan = || -> Result<PlanDocument, Box<dyn std::error::Error>> {
+        let points: Vec<RoutePoint> = route::resolve_route("../../data/airports.db", &view.route)?
+            .into_iter()
+            .map(|point| RoutePoint { point, altitude })
+            .collect();
+        let navigation = route::navigation_from_route(&points, wind, EarthModel::spherical).ok_or("The route needs at least two points")?;
+        Ok(PlanDocument::new(String::new(), &navigation, None))
+    };
+    let (mut plan, mut legs_changed) = match current {
+        Some(plan) => (plan.clone(), false),
+        None => (route_plan()?, true),
+    };
+    let shown = plan_view(&plan);
+    let edited = |field: fn(&PlanView) -> &SharedString| field(&shown).trim() != field(view).trim();
+
+    if edited(|v| &v.route) {
+        let routed = route_plan()?;
+        plan.departure = routed.departure;
+        plan.legs = routed.legs;
+        legs_changed = true;
+    }
+    if edited(|v| &v.altitude) {
+        plan.legs.iter_mut().for_each(|leg| leg.altitude = altitude);
+        legs_changed = true;
+    }
+    if edited(|v| &v.wind) {
+        plan.legs.iter_mut().for_each(|leg| leg.wind = wind);
+        legs_changed = true;
+    }
+    if edited(|v| &v.off_block) {
+        plan.off_block = match view.off_block.trim() {
+            "" => None,
+            off_block => Some(NaiveDateTime::parse_from_str(off_block, "%Y-%m-%d %H:%M")?.and_utc()),
+        };
+    }
+
+    let aircraft_changed = plan.aircraft.as_deref() != Some(registration);
+    let loaded = if aircraft_changed { None } else { plan.aircraft()? };
+    let mut aircraft = match loaded {
+        Some(aircraft) => aircraft,
+        None => Aircraft::import(registration)?,
+    };
+    if edited(|v| &v.fuel) || aircraft_changed {
+        let fuel = view.fuel.trim();
+        if !fuel.is_empty() {
+            aircraft.load_fuel(fuel.parse()?)?;
+        }
+    }
+    let mut navigation = plan.navigation();
+    if legs_changed || aircraft_changed {
+        for branch in navigation.branches.iter_mut() {
+            branch.estimate_time(aircraft.cruise_speed);
+        }
+    }
+    Ok(PlanDocument::new(view.name.trim().to_string(), &navigation, Some(&aircraft)))
+}
+
+/// Fields of the plan view showing a plan
+fn plan_view(plan: &PlanDocument) -> PlanView {
+    let mut route = vec![plan.departure.route_name()];
+    route.extend(plan.legs.iter().map(|leg| leg.to.route_name()));
+    let first_leg = plan.legs.first();
+    PlanView {
+        name: plan.name.clone().into(),
+        route: route.join(" ").into(),
+        altitude: first_leg.and_then(|leg| leg.altitude).map_or(String::new(), |a| format!("{:.0}", a)).into(),
+        wind: first_leg.map_or(String::new(), |leg| format!("{:03.0}/{:.0}", leg.wind.direction, leg.wind.speed)).into(),
+        off_block: plan.off_block.map_or(String::new(), |t| t.format("%Y-%m-%d %H:%M").to_string()).into(),
+        fuel: plan.fuel.as_ref().map_or(String::new(), |f| format!("{:.0}", f.on_board)).into(),
+    }
+}
+
+/// Shows a plan in the plan view, with its nav log, and selects its aircraft
+fn show_plan(main_window: &MainWindow, plan: &PlanDocument, path: &str) {
+    main_window.set_plan(plan_view(plan));
+    main_window.set_plan_path(path.into());
+    let nav_log: Vec<SharedString> = plan.navigation().nav_log().into_iter().map(SharedString::from).collect();
+    main_window.set_nav_log(ModelRc::new(VecModel::from(nav_log)));
+    if let Some(registration) = &plan.aircraft {
+        main_window.set_selected_aircraft(registration.into());
+    }
+}
+
+/// Reloads the recently opened plans and shows them in the recent list
+fn refresh_recent_plans(main_window: &MainWindow, recent: &RefCell<Vec<RecentPlan>>) {
+    *recent.borrow_mut() = RecentPlan::list("../../data/airports.db", 10).unwrap_or_default();
+    let names: Vec<SharedString> = recent
+        .borrow()
+        .iter()
+        .map(|p| SharedString::from(if p.name.is_empty() { p.path.clone() } else { format!("{} ({})", p.name, p.path) }))
+        .collect();
+    main_window.set_recent_plans(ModelRc::new(VecModel::from(names)));
+}
+
+/// Opens a plan file, keeps it as the current plan and remembers it; files that cannot be read any more leave the recent list
+fn open_plan(main_window: &MainWindow, recent: &RefCell<Vec<RecentPlan>>, current: &RefCell<Option<PlanDocument>>, path: &str) {
+    match PlanDocument::load(path) {
+        Ok(plan) => {
+            show_plan(main_window, &plan, path);
+            main_window.set_plan_status(format!("{} opened", path).into());
+            let _ = RecentPlan::touch("../../data/airports.db", path, &plan.name);
+            *current.borrow_mut() = Some(plan);
+        }
+        Err(e) => {
+            if let PlanError::io(_) = e {
+                let _ = RecentPlan::remove("../../data/airports.db", path);
+            }
+            main_window.set_plan_status(e.to_string().into());
+        }
+    }
+    refresh_recent_plans(main_window, recent);
+}
+
 fn main () {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(e) = import_command(&args) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Err(e) = Aircraft::create_tables("../../data/airports.db") {
+        eprintln!("Aircraft tables: {}", e);
+    }
     let main_window = MainWindow::new().unwrap();
     let list_aircraft = database::list_entries("../../data/airports.db", "aircrafts", "immat").unwrap();
     let aircraft_shrd: Vec<SharedString> = list_aircraft.into_iter().map(SharedString::from).collect();
@@ -80,7 +291,160 @@ fn main () {
     });
 
 
-    
+    if let Err(e) = search::index_airports("../../data/airports.db") {
+        eprintln!("Airport search index: {}", e);
+    }
+    if let Err(e) = spatial::ensure_index("../../data/airports.db") {
+        eprintln!("Airport spatial index: {}", e);
+    }
+    let main_window_weak = main_window.as_weak();
+    main_window.on_search_airport(move |query| {
+        // One letter matches most of the database, wait for a second one
+        if query.trim().chars().count() < 2 {
+            main_window_weak.unwrap().set_airport_results(ModelRc::default());
+            return;
+        }
+        let filter = AirportFilter { limit: 10, ..AirportFilter::default() };
+        let results: Vec<SharedString> = Airport::search("../../data/airports.db", &query, &filter)
+            .unwrap_or_default()
+            .iter()
+            .map(|a| SharedString::from(format!("{} - {} ({})", a.oaci_code, a.name, a.municipality)))
+            .collect();
+        main_window_weak.unwrap().set_airport_results(ModelRc::new(VecModel::from(results)));
+    });
+
+    let waypoints = Rc::new(RefCell::new(Vec::new()));
+    match UserWaypoint::create_table("../../data/airports.db") {
+        Ok(()) => refresh_waypoints(&main_window, &waypoints),
+        Err(e) => main_window.set_waypoint_status(format!("User waypoints unavailable: {}", e).into()),
+    }
+
+    let main_window_weak = main_window.as_weak();
+    let waypoints_list = waypoints.clone();
+    main_window.on_waypoint_selected(move |index| {
+        let main_window = main_window_weak.unwrap();
+        if let Some(w) = waypoints_list.borrow().get(index as usize) {
+            main_window.set_waypoint(WaypointView {
+                name: w.name.clone().into(),
+                position: coordinates::format(w.latitude, w.longitude, CoordinateFormat::dms).into(),
+                waypoint_type: w.waypoint_type.db_type().into(),
+                airport: w.airport.clone().unwrap_or_default().into(),
+                notes: w.notes.clone().into(),
+            });
+        }
+    });
+
+    let main_window_weak = main_window.as_weak();
+    let waypoints_list = waypoints.clone();
+    main_window.on_add_waypoint(move |view| {
+        let main_window = main_window_weak.unwrap();
+        let result = user_waypoint_from_view(&view).and_then(|mut w| Ok(w.insert("../../data/airports.db")?));
+        match result {
+            Ok(_) => {
+                main_window.set_waypoint_status(format!("{} added", view.name).into());
+                refresh_waypoints(&main_window, &waypoints_list);
+            }
+            Err(e) => main_window.set_waypoint_status(e.to_string().into()),
+        }
+    });
+
+    let main_window_weak = main_window.as_weak();
+    let waypoints_list = waypoints.clone();
+    main_window.on_update_waypoint(move |index, view| {
+        let main_window = main_window_weak.unwrap();
+        let id = waypoints_list.borrow().get(index as usize).and_then(|w| w.id);
+        let result = user_waypoint_from_view(&view).and_then(|mut w| {
+            w.id = id;
+            Ok(w.update("../../data/airports.db")?)
+        });
+        match result {
+            Ok(_) => {
+                main_window.set_waypoint_status(format!("{} saved", view.name).into());
+                refresh_waypoints(&main_window, &waypoints_list);
+            }
+            Err(e) => main_window.set_waypoint_status(e.to_string().into()),
+        }
+    });
+
+    let main_window_weak = main_window.as_weak();
+    let waypoints_list = waypoints.clone();
+    main_window.on_delete_waypoint(move |index| {
+        let main_window = main_window_weak.unwrap();
+        let id = waypoints_list.borrow().get(index as usize).and_then(|w| w.id);
+        if let Some(id) = id {
+            match UserWaypoint::delete("../../data/airports.db", id) {
+                Ok(_) => main_window.set_waypoint_status("Waypoint deleted".into()),
+                Err(e) => main_window.set_waypoint_status(e.to_string().into()),
+            }
+            refresh_waypoints(&main_window, &waypoints_list);
+        }
+    });
+
+    let main_window_weak = main_window.as_weak();
+    let waypoints_list = waypoints.clone();
+    main_window.on_import_waypoints(move |path| {
+        let main_window = main_window_weak.unwrap();
+        match UserWaypoint::import_csv("../../data/airports.db", &path) {
+            Ok(count) => main_window.set_waypoint_status(format!("{} waypoints imported", count).into()),
+            Err(e) => main_window.set_waypoint_status(e.to_string().into()),
+        }
+        refresh_waypoints(&main_window, &waypoints_list);
+    });
+
+    let main_window_weak = main_window.as_weak();
+    main_window.on_export_waypoints(move |path| {
+        let main_window = main_window_weak.unwrap();
+        match UserWaypoint::export_csv("../../data/airports.db", &path) {
+            Ok(count) => main_window.set_waypoint_status(format!("{} waypoints exported", count).into()),
+            Err(e) => main_window.set_waypoint_status(e.to_string().into()),
+        }
+    });
+
+    let recent_plans = Rc::new(RefCell::new(Vec::new()));
+    let current_plan: Rc<RefCell<Option<PlanDocument>>> = Rc::new(RefCell::new(None));
+    match RecentPlan::create_table("../../data/airports.db") {
+        Ok(()) => refresh_recent_plans(&main_window, &recent_plans),
+        Err(e) => main_window.set_plan_status(format!("Recent plans unavailable: {}", e).into()),
+    }
+
+    let main_window_weak = main_window.as_weak();
+    let recent = recent_plans.clone();
+    let current = current_plan.clone();
+    main_window.on_open_plan(move |path| {
+        open_plan(&main_window_weak.unwrap(), &recent, &current, &path);
+    });
+
+    let main_window_weak = main_window.as_weak();
+    let recent = recent_plans.clone();
+    let current = current_plan.clone();
+    main_window.on_open_recent(move |index| {
+        let path = recent.borrow().get(index as usize).map(|p| p.path.clone());
+        if let Some(path) = path {
+            open_plan(&main_window_weak.unwrap(), &recent, &current, &path);
+        }
+    });
+
+    let main_window_weak = main_window.as_weak();
+    let recent = recent_plans.clone();
+    let current = current_plan.clone();
+    main_window.on_save_plan(move |path, view| {
+        let main_window = main_window_weak.unwrap();
+        let result = plan_from_view(current.borrow().as_ref(), &view, &main_window.get_selected_aircraft()).and_then(|mut plan| {
+            plan.save(&path)?;
+            Ok(plan)
+        });
+        match result {
+            Ok(plan) => {
+                show_plan(&main_window, &plan, &path);
+                main_window.set_plan_status(format!("{} saved", path).into());
+                let _ = RecentPlan::touch("../../data/airports.db", &path, &plan.name);
+                refresh_recent_plans(&main_window, &recent);
+                *current.borrow_mut() = Some(plan);
+            }
+            Err(e) => main_window.set_plan_status(e.to_string().into()),
+        }
+    });
+
 /*
     main_window.on_select_aircraft(move || {
         let main_window = main_window_weak.unwrap();
@@ -1,7 +1,28 @@
 pub mod aircraft;
 pub mod airport;
+pub mod coordinates;
+pub mod diversion;
 pub mod error;
 pub mod flightplan;
+pub mod fpl;
 pub mod fuel;
+pub mod garmin;
 pub mod geographics;
+pub mod gpx;
+pub mod import;
+pub mod kml;
+pub mod levels;
+pub mod map;
+pub mod navaid;
+pub mod plan;
+pub mod profile;
+pub mod route;
+pub mod runway;
+pub mod search;
+pub mod spatial;
+pub mod sun;
+pub mod terrain;
+pub mod timezone;
+pub mod user_waypoint;
+pub mod waypoint;
 pub mod database;
\ No newline at end of file
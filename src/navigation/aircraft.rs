@@ -1,10 +1,35 @@
+use chrono::Duration;
 use std::{
     collections::HashMap, error::Error, path
 };
+use rusqlite::Connection;
 use super::{
     database::{self, parse_database}, error::BalanceError, fuel::{self, Fuel}
 };
 
+const CREATE_PERFORMANCE: &str = r#"CREATE TABLE IF NOT EXISTS "performance" (
+	"aircraft"	TEXT UNIQUE,
+	"climb_rate"	NUMERIC,
+	"climb_speed"	NUMERIC,
+	"climb_fuel_flow"	NUMERIC,
+	"descent_rate"	NUMERIC,
+	"descent_speed"	NUMERIC,
+	"descent_fuel_flow"	NUMERIC,
+	FOREIGN KEY("aircraft") REFERENCES "aircrafts"("immat"),
+	PRIMARY KEY("aircraft")
+);"#;
+
+const CREATE_FLIGHT_PLAN_DATA: &str = r#"CREATE TABLE IF NOT EXISTS "flight_plan_data" (
+	"aircraft"	TEXT UNIQUE,
+	"type_designator"	TEXT,
+	"wake_category"	TEXT,
+	"equipment"	TEXT,
+	"surveillance"	TEXT,
+	"colour"	TEXT,
+	FOREIGN KEY("aircraft") REFERENCES "aircrafts"("immat"),
+	PRIMARY KEY("aircraft")
+);"#;
+
 
 #[derive(Debug, Clone)]
 pub struct Balance {
@@ -26,7 +51,103 @@ pub struct Aircraft {
     pub empty_weight: f64,
     pub balance_chart: BalanceChart,
     pub loading: BalanceSheet,
+    pub performance: Performance,
+    pub max_crosswind: Option<f64>, // kt, maximum demonstrated crosswind
+    pub flight_plan: FlightPlanData,
+}
+
+/// Performance of the aircraft in climb or descent
+#[derive(Debug, Clone)]
+pub struct PhasePerformance {
+    pub rate: f64,      // ft/min
+    pub speed: f64,     // kt
+    pub fuel_flow: f64, // L/h
+}
+
+impl PhasePerformance {
+    pub fn new() -> PhasePerformance {
+        PhasePerformance {
+            rate: 0.0,
+            speed: 0.0,
+            fuel_flow: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Performance {
+    pub climb: PhasePerformance,
+    pub descent: PhasePerformance,
+}
+
+impl Performance {
+    pub fn new() -> Performance {
+        Performance {
+            climb: PhasePerformance::new(),
+            descent: PhasePerformance::new(),
+        }
+    }
+
+    fn performance_mapper(row: &rusqlite::Row) -> rusqlite::Result<Performance> {
+        Ok(Performance {
+            climb: PhasePerformance {
+                rate: row.get(0)?,
+                speed: row.get(1)?,
+                fuel_flow: row.get(2)?,
+            },
+            descent: PhasePerformance {
+                rate: row.get(3)?,
+                speed: row.get(4)?,
+                fuel_flow: row.get(5)?,
+            },
+        })
+    }
+
+    pub fn from_database(database: &str, immatriculation: &str) -> Result<Vec<Performance>, rusqlite::Error> {
+        parse_database(database, "performance", "aircraft", immatriculation,
+        "climb_rate, climb_speed, climb_fuel_flow, descent_rate, descent_speed, descent_fuel_flow",
+        Self::performance_mapper)
+    }
+}
+
+/// Aircraft details filed in ICAO flight plans
+#[derive(Debug, Clone)]
+pub struct FlightPlanData {
+    pub type_designator: String, // ICAO Doc 8643, e.g. "DR40", or "ZZZZ"
+    pub wake_category: char,     // L, M, H or J
+    pub equipment: String,       // item 10a, e.g. "SY"
+    pub surveillance: String,    // item 10b, e.g. "S"
+    pub colour: String,          // colour and markings, item 19 A/
 }
+
+impl FlightPlanData {
+    pub fn new() -> FlightPlanData {
+        FlightPlanData {
+            type_designator: "ZZZZ".to_string(),
+            wake_category: 'L',
+            equipment: "N".to_string(),
+            surveillance: "N".to_string(),
+            colour: String::new(),
+        }
+    }
+
+    fn flight_plan_mapper(row: &rusqlite::Row) -> rusqlite::Result<FlightPlanData> {
+        Ok(FlightPlanData {
+            type_designator: row.get(0)?,
+            wake_category: row.get::<_, String>(1)?.chars().next().unwrap_or('L'),
+            equipment: row.get(2)?,
+            surveillance: row.get(3)?,
+            colour: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+        })
+    }
+
+    pub fn from_database(database: &str, immatriculation: &str) -> Result<Vec<FlightPlanData>, rusqlite::Error> {
+        parse_database(database, "flight_plan_data", "aircraft", immatriculation,
+        "type_designator, wake_category, equipment, surveillance, colour",
+        Self::flight_plan_mapper)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BalanceCat {
     pub front_limit: f64,
@@ -174,7 +295,7 @@ use plotters::{prelude::*, style::full_palette::{LIGHTBLUE, ORANGE_400}};
 
 
 impl Aircraft {
-    pub fn new(immatriculation: String, aircraft_type: String, horse_power: i32, cruise_speed: f64, fuel: Fuel, consomatation: f64, nb_tank: i32, total_tank: i32, empty_weight: f64, balance_chart: BalanceChart, loading: BalanceSheet) -> Aircraft {
+    pub fn new(immatriculation: String, aircraft_type: String, horse_power: i32, cruise_speed: f64, fuel: Fuel, consomatation: f64, nb_tank: i32, total_tank: i32, empty_weight: f64, balance_chart: BalanceChart, loading: BalanceSheet, performance: Performance, max_crosswind: Option<f64>, flight_plan: FlightPlanData) -> Aircraft {
         Aircraft {
             immatriculation,
             aircraft_type,
@@ -187,13 +308,34 @@ impl Aircraft {
             empty_weight,
             balance_chart,
             loading,
+            performance,
+            max_crosswind,
+            flight_plan,
         }
     }
 
+    /// Creates the tables added to the aircraft data since the first databases, if they do not exist yet
+    pub fn create_tables(db_path: &str) -> Result<(), rusqlite::Error> {
+        let conn = Connection::open(db_path)?;
+        conn.execute_batch(CREATE_PERFORMANCE)?;
+        conn.execute_batch(CREATE_FLIGHT_PLAN_DATA)?;
+        let has_crosswind: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('aircrafts') WHERE name = 'max_crosswind'",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_crosswind {
+            conn.execute_batch("ALTER TABLE aircrafts ADD COLUMN max_crosswind NUMERIC")?;
+        }
+        Ok(())
+    }
+
     pub fn import(immatriculation: &str) -> Result<Aircraft, rusqlite::Error> {
         let mut plane = Aircraft::from_database("../../data/airports.db", immatriculation)?.pop().ok_or(rusqlite::Error::QueryReturnedNoRows)?;
         let mut balance = BalanceChart::from_database("../../data/airports.db", immatriculation)?;
         let mut weight = BalanceSheet::from_database("../../data/airports.db", immatriculation)?;
+        let mut performance = Performance::from_database("../../data/airports.db", immatriculation)?;
+        let mut flight_plan = FlightPlanData::from_database("../../data/airports.db", immatriculation)?;
         if !weight.is_empty() {
             plane.loading = weight.pop().unwrap();
             plane.loading.load("Empty".to_string(), plane.empty_weight).unwrap();
@@ -202,6 +344,12 @@ impl Aircraft {
         if !balance.is_empty() {
             plane.balance_chart = balance.pop().unwrap();
         }
+        if !performance.is_empty() {
+            plane.performance = performance.pop().unwrap();
+        }
+        if !flight_plan.is_empty() {
+            plane.flight_plan = flight_plan.pop().unwrap();
+        }
         Ok(plane)
     }
 
@@ -213,17 +361,7 @@ impl Aircraft {
             aircraft_type: row.get(1)?,
             horse_power: row.get(2)?,
             cruise_speed: row.get(3)?,
-            fuel: match row.get::<_, String>(4)?.as_str() {
-                "AVGAS100LL" => Fuel::AVGAS100LL,
-                "AVGAS100" => Fuel::AVGAS100,
-                "AVGAS82" => Fuel::AVGAS82,
-                "AVGAS80" => Fuel::AVGAS80,
-                "JET_A" => Fuel::JET_A,
-                "JET_A1" => Fuel::JET_A1,
-                "JET_B" => Fuel::JET_B,
-                "MOGAS" => Fuel::MOGAS,
-                _ => Fuel::AVGAS100LL,
-            },
+            fuel: Fuel::from_db_type(&row.get::<_, String>(4)?).unwrap_or(Fuel::AVGAS100LL),
             consomatation: row.get(5)?,
             nb_tank: row.get(6)?,
             total_tank: row.get(7)?,
@@ -234,12 +372,15 @@ impl Aircraft {
                 catA: BalanceCat::new(),
             },
             loading: BalanceSheet::new(),
+            performance: Performance::new(),
+            max_crosswind: row.get(9)?,
+            flight_plan: FlightPlanData::new(),
         })
     }
 
     pub fn from_database(database: &str, immatriculation: &str) -> Result<Vec<Aircraft>, rusqlite::Error> {
         parse_database(&database, "aircrafts", "immat", &immatriculation, 
-        "immat, type, horse_power, cruise_speed, fuel, conso, nb_tank, total_tank, empty_weight", Self::aircraft_mapper)
+        "immat, type, horse_power, cruise_speed, fuel, conso, nb_tank, total_tank, empty_weight, max_crosswind", Self::aircraft_mapper)
     }
 
     pub fn load_fuel(&mut self, quantity: f64) -> Result<(), BalanceError> {
@@ -251,6 +392,19 @@ impl Aircraft {
         Ok(())
     }
 
+    /// Fuel loaded in the tanks, in L
+    pub fn fuel_on_board(&self) -> f64 {
+        self.loading.elements.get("tank").map_or(0.0, |tank| tank.weight / self.fuel.properties().density)
+    }
+
+    /// Flight time with the fuel on board at cruise consumption
+    pub fn endurance(&self) -> Duration {
+        if self.consomatation <= 0.0 {
+            return Duration::zero();
+        }
+        Duration::seconds((self.fuel_on_board() / self.consomatation * 3600.0) as i64)
+    }
+
     pub fn load_crew(&mut self, weight: f64) -> Result<(), BalanceError> {
         self.loading.load("PIL".to_string(), weight)?;
         Ok(())
@@ -340,4 +494,28 @@ impl Aircraft {
     
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrades_older_databases() {
+        let db_path = std::env::temp_dir().join("flight_planner_old_aircraft.db");
+        let db_path = db_path.to_str().unwrap();
+        std::fs::copy("data/airports.db", db_path).unwrap();
+        Connection::open(db_path)
+            .unwrap()
+            .execute_batch("ALTER TABLE aircrafts DROP COLUMN max_crosswind; DROP TABLE performance; DROP TABLE flight_plan_data;")
+            .unwrap();
+        assert!(Aircraft::from_database(db_path, "F-HFCG").is_err());
+
+        Aircraft::create_tables(db_path).unwrap();
+        Aircraft::create_tables(db_path).unwrap();
+        let aircraft = Aircraft::from_database(db_path, "F-HFCG").unwrap().pop().unwrap();
+        assert_eq!(aircraft.max_crosswind, None);
+        assert!(Performance::from_database(db_path, "F-HFCG").unwrap().is_empty());
+        assert!(FlightPlanData::from_database(db_path, "F-HFCG").unwrap().is_empty());
+        std::fs::remove_file(db_path).unwrap();
+    }
+}
@@ -1,10 +1,21 @@
 use super::error::ParseError;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use rusqlite::types::Value;
 use rusqlite::Connection;
 use super::database;
+use super::search::{self, AirportFilter};
+use super::fuel::Fuel;
+use super::runway::{Frequency, Runway};
+use super::spatial;
+use super::geographics::NavPoint;
+use super::waypoint::WaypointKind;
+use super::sun::SunTimes;
+use super::timezone;
+use chrono::NaiveDate;
+use chrono_tz::Tz;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AirportType {
     small_airport,
     medium_airport,
@@ -14,6 +25,36 @@ pub enum AirportType {
     unknown,
 }
 
+impl AirportType {
+    pub fn from_db_type(airport_type: &str) -> AirportType {
+        match airport_type {
+            "small_airport" => AirportType::small_airport,
+            "medium_airport" => AirportType::medium_airport,
+            "large_airport" => AirportType::large_airport,
+            "helliport" | "heliport" => AirportType::helliport,
+            "closed" => AirportType::closed,
+            _ => AirportType::unknown,
+        }
+    }
+
+    /// Value of the `airports.type` column, as in OurAirports
+    pub fn db_type(&self) -> &'static str {
+        match self {
+            AirportType::small_airport => "small_airport",
+            AirportType::medium_airport => "medium_airport",
+            AirportType::large_airport => "large_airport",
+            AirportType::helliport => "heliport",
+            AirportType::closed => "closed",
+            AirportType::unknown => "unknown",
+        }
+    }
+}
+
+/// Columns of the `airports` table read by `Airport::airport_mapper`
+pub const AIRPORT_COLUMNS: &str = "ident, name, type, latitude_deg, longitude_deg, iso_country,
+    elevation_ft, continent, country_name, region_name, iso_region, municipality,
+    gps_code, iata_code, local_code, keywords";
+
 #[derive(Debug)]
 pub struct Airport {
     pub oaci_code: String,
@@ -21,15 +62,28 @@ pub struct Airport {
     pub airport_type: AirportType,
     pub latitude: f64,
     pub longitude: f64,
+    pub iso_country: String,
+    pub elevation: Option<f64>, // ft
+    pub continent: String,
+    pub country_name: String,
+    pub region_name: String,
+    pub iso_region: String,
+    pub municipality: String,
+    pub gps_code: String,
+    pub iata_code: String,
+    pub local_code: String,
+    pub keywords: String,
 }
 
 impl Airport {
+    /// Creates an airport from its main fields, the others are left empty
     pub fn new(
         oaci_code: String,
         name: String,
         airport_type: AirportType,
         latitude: f64,
         longitude: f64,
+        iso_country: String,
     ) -> Airport {
         Airport {
             oaci_code,
@@ -37,6 +91,17 @@ impl Airport {
             airport_type,
             latitude,
             longitude,
+            iso_country,
+            elevation: None,
+            continent: String::new(),
+            country_name: String::new(),
+            region_name: String::new(),
+            iso_region: String::new(),
+            municipality: String::new(),
+            gps_code: String::new(),
+            iata_code: String::new(),
+            local_code: String::new(),
+            keywords: String::new(),
         }
     }
 
@@ -45,24 +110,89 @@ impl Airport {
             .pop().ok_or(rusqlite::Error::QueryReturnedNoRows)
     }
 
-    fn airport_mapper(row: &rusqlite::Row) -> rusqlite::Result<Airport> {
+    pub fn airport_mapper(row: &rusqlite::Row) -> rusqlite::Result<Airport> {
         Ok(Airport {
             oaci_code: row.get(0)?,
             name: row.get(1)?,
-            airport_type: match row.get::<_, String>(2)?.as_str() {
-                "small_airport" => AirportType::small_airport,
-                "medium_airport" => AirportType::medium_airport,
-                "large_airport" => AirportType::large_airport,
-                "helliport" => AirportType::helliport,
-                "closed" => AirportType::closed,
-                _ => AirportType::unknown,
-            },
+            airport_type: AirportType::from_db_type(&row.get::<_, String>(2)?),
             latitude: row.get(3)?,
             longitude: row.get(4)?,
+            iso_country: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+            elevation: match row.get::<_, Value>(6)? {
+                Value::Integer(elevation) => Some(elevation as f64),
+                Value::Real(elevation) => Some(elevation),
+                _ => None, // empty in OurAirports when unknown
+            },
+            continent: row.get::<_, Option<String>>(7)?.unwrap_or_default(),
+            country_name: row.get::<_, Option<String>>(8)?.unwrap_or_default(),
+            region_name: row.get::<_, Option<String>>(9)?.unwrap_or_default(),
+            iso_region: row.get::<_, Option<String>>(10)?.unwrap_or_default(),
+            municipality: row.get::<_, Option<String>>(11)?.unwrap_or_default(),
+            gps_code: row.get::<_, Option<String>>(12)?.unwrap_or_default(),
+            iata_code: row.get::<_, Option<String>>(13)?.unwrap_or_default(),
+            local_code: row.get::<_, Option<String>>(14)?.unwrap_or_default(),
+            keywords: row.get::<_, Option<String>>(15)?.unwrap_or_default(),
         })
     }
 
+    /// Navigation point at the airport reference point, named after its ICAO code
+    pub fn to_nav_point(&self) -> NavPoint {
+        let mut point = NavPoint::new(self.oaci_code.clone(), self.latitude, self.longitude);
+        point.kind = WaypointKind::airport(self.oaci_code.clone());
+        point
+    }
+
+    /// Time zone of the airport, used to show local times on the nav log
+    pub fn timezone(&self) -> Tz {
+        timezone::from_country(&self.iso_country, self.longitude)
+    }
+
+    /// Sunrise, sunset and civil twilight at the airport on `date`
+    pub fn sun_times(&self, date: NaiveDate) -> SunTimes {
+        SunTimes::compute(self.latitude, self.longitude, date)
+    }
+
+    /// Runways of the airport, closed ones included
+    pub fn runways(&self, db_path: &str) -> Result<Vec<Runway>, rusqlite::Error> {
+        Runway::from_database(db_path, &self.oaci_code)
+    }
+
+    /// Longest runway still open, in ft
+    pub fn longest_runway(&self, conn: &Connection) -> Result<Option<f64>, rusqlite::Error> {
+        Ok(Runway::from_connection(conn, &self.oaci_code)?
+            .iter()
+            .filter(|runway| !runway.closed)
+            .filter_map(|runway| runway.length)
+            .fold(None, |longest: Option<f64>, length| Some(longest.map_or(length, |l| l.max(length)))))
+    }
+
+    /// Radio frequencies of the airport
+    pub fn frequencies(&self, db_path: &str) -> Result<Vec<Frequency>, rusqlite::Error> {
+        Frequency::from_database(db_path, &self.oaci_code)
+    }
+
+    /// Fuels sold at the airport, `None` when unknown
+    pub fn fuels(&self, conn: &Connection) -> Result<Option<Vec<Fuel>>, rusqlite::Error> {
+        let mut stmt = conn.prepare("SELECT fuel FROM airport_fuels WHERE airport_ident = ?1")?;
+        let fuels: Vec<String> = stmt.query_map([&self.oaci_code], |row| row.get(0))?.collect::<Result<_, _>>()?;
+        if fuels.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(fuels.iter().filter_map(|fuel| Fuel::from_db_type(fuel)).collect()))
+    }
+
     pub fn from_db(db_path: &str, ident: &str) -> Result<Vec<Airport>, rusqlite::Error> {
-        database::parse_database(db_path, "airports", "ident", ident, "ident, name, type, latitude_deg, longitude_deg", Self::airport_mapper)
+        database::parse_database(db_path, "airports", "ident", ident, AIRPORT_COLUMNS, Self::airport_mapper)
+    }
+
+    /// Airports inside a latitude/longitude box, in degrees
+    pub fn in_area(db_path: &str, min_latitude: f64, max_latitude: f64, min_longitude: f64, max_longitude: f64) -> Result<Vec<Airport>, rusqlite::Error> {
+        let conn = Connection::open(db_path)?;
+        spatial::airports_in_box(&conn, min_latitude, max_latitude, min_longitude, max_longitude)
     }
-}
\ No newline at end of file
+
+    /// Airports matching a free text query on codes, name, municipality or keywords, best matches first
+    pub fn search(db_path: &str, query: &str, filter: &AirportFilter) -> Result<Vec<Airport>, rusqlite::Error> {
+        search::search_airports(db_path, query, filter)
+    }
+}
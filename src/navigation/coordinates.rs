@@ -0,0 +1,308 @@
+use super::error::CoordinateError;
+
+/// Ways of writing a position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoordinateFormat {
+    /// `48.753333, 2.105833`
+    decimal,
+    /// `48°45'12"N 002°06'21"E`
+    dms,
+    /// `N48 45.20 E002 06.35`
+    ddm,
+    /// `4845N00206E`, as in ICAO flight plans
+    icao,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Axis {
+    latitude,
+    longitude,
+}
+
+impl Axis {
+    fn limit(self) -> f64 {
+        match self {
+            Axis::latitude => 90.0,
+            Axis::longitude => 180.0,
+        }
+    }
+}
+
+fn hemisphere(letter: char) -> Option<(Axis, f64)> {
+    match letter {
+        'N' => Some((Axis::latitude, 1.0)),
+        'S' => Some((Axis::latitude, -1.0)),
+        'E' => Some((Axis::longitude, 1.0)),
+        'W' => Some((Axis::longitude, -1.0)),
+        _ => None,
+    }
+}
+
+/// Converts degrees, minutes and seconds into decimal degrees, checking their range.
+/// `sign` is given by the hemisphere letter when there is one, otherwise the degrees may be negative.
+fn to_decimal(parts: &[f64], axis: Axis, sign: Option<f64>, text: &str) -> Result<f64, CoordinateError> {
+    let invalid = || CoordinateError::invalid_format(text.to_string());
+    let (degrees, minutes, seconds) = match parts {
+        [degrees] => (*degrees, 0.0, 0.0),
+        [degrees, minutes] => (*degrees, *minutes, 0.0),
+        [degrees, minutes, seconds] => (*degrees, *minutes, *seconds),
+        _ => return Err(invalid()),
+    };
+    if parts.len() > 1 && (degrees.fract() != 0.0 || !(0.0..60.0).contains(&minutes)) {
+        return Err(invalid());
+    }
+    if parts.len() > 2 && (minutes.fract() != 0.0 || !(0.0..60.0).contains(&seconds)) {
+        return Err(invalid());
+    }
+    if degrees < 0.0 && sign.is_some() {
+        return Err(CoordinateError::wrong_hemisphere(text.to_string()));
+    }
+    let value = degrees.signum() * (degrees.abs() + minutes / 60.0 + seconds / 3600.0) * sign.unwrap_or(1.0);
+    if value.abs() > axis.limit() {
+        return Err(CoordinateError::out_of_range(text.to_string()));
+    }
+    Ok(value)
+}
+
+/// Parses the compact ICAO forms: `48N002E`, `4845N00206E` or `484512N0020621E`
+fn parse_icao(text: &str) -> Option<Result<(f64, f64), CoordinateError>> {
+    let lat_end = text.find(['N', 'S'])?;
+    let (lat, rest) = text.split_at(lat_end);
+    let (lat_hemisphere, lon) = rest.split_at(1);
+    let lon_hemisphere = lon.chars().last()?;
+    let lon = &lon[..lon.len() - 1];
+    if !lat.chars().chain(lon.chars()).all(|c| c.is_ascii_digit()) || !matches!(lon_hemisphere, 'E' | 'W') {
+        return None;
+    }
+    let split = |digits: &str, degree_digits: usize| -> Option<Vec<f64>> {
+        if digits.len() < degree_digits || !(digits.len() - degree_digits).is_multiple_of(2) || digits.len() > degree_digits + 4 {
+            return None;
+        }
+        let mut parts = vec![digits[..degree_digits].parse().ok()?];
+        let mut rest = &digits[degree_digits..];
+        while !rest.is_empty() {
+            parts.push(rest[..2].parse().ok()?);
+            rest = &rest[2..];
+        }
+        Some(parts)
+    };
+    let lat_parts = split(lat, 2)?;
+    let lon_parts = split(lon, 3)?;
+    let (_, lat_sign) = hemisphere(lat_hemisphere.chars().next()?)?;
+    let (_, lon_sign) = hemisphere(lon_hemisphere)?;
+    Some(
+        to_decimal(&lat_parts, Axis::latitude, Some(lat_sign), text)
+            .and_then(|lat| Ok((lat, to_decimal(&lon_parts, Axis::longitude, Some(lon_sign), text)?))),
+    )
+}
+
+/// Parses a position into decimal (latitude, longitude) degrees.
+///
+/// Accepted forms:
+/// - `48°45'12"N 001°45'30"E` (DMS, hemisphere before or after)
+/// - `N48 45.2 E001 45.5` or `48°45.2'N 1°45.5'E` (degrees and decimal minutes)
+/// - `48.7533 2.1058`, `48.7533, -1.5` or `48.7533N 1.5W` (decimal degrees)
+/// - `4845N00145E`, `484512N0014530E` (ICAO compact)
+pub fn parse(text: &str) -> Result<(f64, f64), CoordinateError> {
+    // Only the compact ICAO form has no separators: stripping them from `48°45'N 1°45'E` would read 145°E
+    let trimmed = text.trim().to_uppercase();
+    if trimmed.chars().all(|c| c.is_ascii_alphanumeric()) {
+        if let Some(result) = parse_icao(&trimmed) {
+            return result;
+        }
+    }
+
+    let normalized: String = text
+        .trim()
+        .to_uppercase()
+        .chars()
+        .map(|c| match c {
+            '°' | '\'' | '"' | '′' | '″' | ',' | ';' | '/' => ' ',
+            _ => c,
+        })
+        .collect();
+
+    // Split into numbers and hemisphere letters
+    let mut tokens: Vec<Result<f64, char>> = Vec::new();
+    let mut number = String::new();
+    for c in normalized.chars().chain(std::iter::once(' ')) {
+        if c.is_ascii_digit() || c == '.' || (c == '-' && number.is_empty()) {
+            number.push(c);
+            continue;
+        }
+        if !number.is_empty() {
+            let value = number.parse().map_err(|_| CoordinateError::invalid_format(text.to_string()))?;
+            tokens.push(Ok(value));
+            number.clear();
+        }
+        if c.is_whitespace() {
+            continue;
+        }
+        if hemisphere(c).is_none() {
+            return Err(CoordinateError::invalid_format(text.to_string()));
+        }
+        tokens.push(Err(c));
+    }
+
+    // Group the numbers with their hemisphere, written before or after them
+    let mut groups: Vec<(Vec<f64>, Option<char>)> = Vec::new();
+    let prefix = matches!(tokens.first(), Some(Err(_)));
+    let mut current = (Vec::new(), None);
+    for token in tokens {
+        match token {
+            Ok(value) => current.0.push(value),
+            Err(letter) if prefix => {
+                if current.1.is_some() || !current.0.is_empty() {
+                    groups.push(current);
+                }
+                current = (Vec::new(), Some(letter));
+            }
+            Err(letter) => {
+                current.1 = Some(letter);
+                groups.push(current);
+                current = (Vec::new(), None);
+            }
+        }
+    }
+    if !current.0.is_empty() || current.1.is_some() {
+        groups.push(current);
+    }
+    // Without hemisphere, the numbers are split evenly between latitude and longitude
+    if groups.len() == 1 && groups[0].1.is_none() && groups[0].0.len().is_multiple_of(2) {
+        let mut numbers = groups.remove(0).0;
+        let longitude = numbers.split_off(numbers.len() / 2);
+        groups = vec![(numbers, None), (longitude, None)];
+    }
+
+    let [first, second] = groups.as_slice() else {
+        return Err(CoordinateError::invalid_format(text.to_string()));
+    };
+    let mut latitude = None;
+    let mut longitude = None;
+    for (index, (parts, letter)) in [first, second].into_iter().enumerate() {
+        let (axis, sign) = match letter {
+            Some(letter) => hemisphere(*letter)
+                .map(|(axis, sign)| (axis, Some(sign)))
+                .ok_or_else(|| CoordinateError::invalid_format(text.to_string()))?,
+            None if index == 0 => (Axis::latitude, None),
+            None => (Axis::longitude, None),
+        };
+        let value = to_decimal(parts, axis, sign, text)?;
+        let slot = match axis {
+            Axis::latitude => &mut latitude,
+            Axis::longitude => &mut longitude,
+        };
+        if slot.replace(value).is_some() {
+            return Err(CoordinateError::wrong_hemisphere(text.to_string()));
+        }
+    }
+    match (latitude, longitude) {
+        (Some(latitude), Some(longitude)) => Ok((latitude, longitude)),
+        _ => Err(CoordinateError::wrong_hemisphere(text.to_string())),
+    }
+}
+
+/// Splits an angle into whole degrees, minutes and the remaining seconds, rounded to `precision` seconds
+fn split_angle(value: f64, precision: f64) -> (u32, u32, f64) {
+    let seconds = (value.abs() * 3600.0 / precision).round() * precision;
+    let degrees = (seconds / 3600.0).floor();
+    let minutes = ((seconds - degrees * 3600.0) / 60.0).floor();
+    (degrees as u32, minutes as u32, seconds - degrees * 3600.0 - minutes * 60.0)
+}
+
+fn format_axis(value: f64, axis: Axis, format: CoordinateFormat) -> String {
+    let (positive, negative, width) = match axis {
+        Axis::latitude => ('N', 'S', 2),
+        Axis::longitude => ('E', 'W', 3),
+    };
+    let letter = if value < 0.0 { negative } else { positive };
+    match format {
+        CoordinateFormat::decimal => format!("{:.6}", value),
+        CoordinateFormat::dms => {
+            let (degrees, minutes, seconds) = split_angle(value, 1.0);
+            format!("{:0width$}°{:02}'{:02.0}\"{}", degrees, minutes, seconds, letter, width = width)
+        }
+        CoordinateFormat::ddm => {
+            let (degrees, minutes, seconds) = split_angle(value, 0.6);
+            format!("{}{:0width$} {:05.2}", letter, degrees, minutes as f64 + seconds / 60.0, width = width)
+        }
+        CoordinateFormat::icao => {
+            let (degrees, minutes, _) = split_angle(value, 60.0);
+            format!("{:0width$}{:02}{}", degrees, minutes, letter, width = width)
+        }
+    }
+}
+
+/// Formats a position given in decimal degrees
+pub fn format(latitude: f64, longitude: f64, format: CoordinateFormat) -> String {
+    let latitude_text = format_axis(latitude, Axis::latitude, format);
+    let longitude_text = format_axis(longitude, Axis::longitude, format);
+    match format {
+        CoordinateFormat::decimal => format!("{}, {}", latitude_text, longitude_text),
+        CoordinateFormat::icao => format!("{}{}", latitude_text, longitude_text),
+        CoordinateFormat::dms | CoordinateFormat::ddm => format!("{} {}", latitude_text, longitude_text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_position(text: &str, latitude: f64, longitude: f64) {
+        let (lat, lon) = parse(text).unwrap_or_else(|e| panic!("{}: {}", text, e));
+        assert!((lat - latitude).abs() < 1e-6 && (lon - longitude).abs() < 1e-6, "{} read as {}, {}", text, lat, lon);
+    }
+
+    const LAT_DMS: f64 = 48.0 + 45.0 / 60.0 + 12.0 / 3600.0;
+    const LON_DMS: f64 = 1.0 + 45.0 / 60.0 + 30.0 / 3600.0;
+
+    #[test]
+    fn dms() {
+        assert_position("48°45'12\"N 001°45'30\"E", LAT_DMS, LON_DMS);
+        assert_position("48°45'12\"N 1°45'30\"E", LAT_DMS, LON_DMS);
+        assert_position("N48°45'12\" E1°45'30\"", LAT_DMS, LON_DMS);
+        assert_position("48°45'12\"S 1°45'30\"W", -LAT_DMS, -LON_DMS);
+        assert_position("48°45'N 1°45'E", 48.75, 1.75);
+        assert_position("48°45′12″N 1°45′30″E", LAT_DMS, LON_DMS);
+    }
+
+    #[test]
+    fn degrees_decimal_minutes() {
+        assert_position("N48 45.2 E001 45.5", 48.0 + 45.2 / 60.0, 1.0 + 45.5 / 60.0);
+        assert_position("N48 45.2 E1 45.5", 48.0 + 45.2 / 60.0, 1.0 + 45.5 / 60.0);
+        assert_position("48°45.2'N 1°45.5'E", 48.0 + 45.2 / 60.0, 1.0 + 45.5 / 60.0);
+    }
+
+    #[test]
+    fn decimal_degrees() {
+        assert_position("48.7533 2.1058", 48.7533, 2.1058);
+        assert_position("48.7533, -1.5", 48.7533, -1.5);
+        assert_position("48.7533N 1.5W", 48.7533, -1.5);
+    }
+
+    #[test]
+    fn icao_compact() {
+        assert_position("4845N00145E", 48.75, 1.75);
+        assert_position("484512N0014530E", LAT_DMS, LON_DMS);
+        assert_position("48N002E", 48.0, 2.0);
+        assert_position(" 4845s00145w ", -48.75, -1.75);
+    }
+
+    #[test]
+    fn invalid_positions() {
+        assert!(matches!(parse("91N 002E"), Err(CoordinateError::out_of_range(_))));
+        assert!(matches!(parse("48N 49N"), Err(CoordinateError::wrong_hemisphere(_))));
+        assert!(matches!(parse("48°75'N 1°45'E"), Err(CoordinateError::invalid_format(_))));
+        assert!(parse("NORD").is_err());
+    }
+
+    #[test]
+    fn format_parses_back() {
+        for format in [CoordinateFormat::decimal, CoordinateFormat::dms, CoordinateFormat::ddm] {
+            let text = super::format(LAT_DMS, -LON_DMS, format);
+            let (lat, lon) = parse(&text).unwrap();
+            assert!((lat - LAT_DMS).abs() < 1e-3 && (lon + LON_DMS).abs() < 1e-3, "{}", text);
+        }
+        assert_eq!(super::format(48.75, 1.75, CoordinateFormat::icao), "4845N00145E");
+    }
+}
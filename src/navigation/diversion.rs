@@ -0,0 +1,172 @@
+use chrono::Duration;
+use rusqlite::Connection;
+
+use super::aircraft::Aircraft;
+use super::airport::{Airport, AirportType};
+use super::flightplan::{NavBranch, Navigation, RoutePosition};
+use super::fuel::Fuel;
+use super::geographics::{EarthModel, KM_TO_NM};
+use super::spatial;
+
+/// An airfield reachable from a point of the route
+#[derive(Debug)]
+pub struct Diversion {
+    pub airport: Airport,
+    pub course: f64,   // degrees true
+    pub distance: f64, // km
+    pub time: Duration,
+    pub fuel: f64, // L
+}
+
+/// Which airfields are proposed as diversions
+#[derive(Debug, Clone)]
+pub struct DiversionCriteria {
+    pub radius: f64, // km around the point
+    pub count: usize,
+    pub min_runway_length: Option<f64>, // ft, airports without a long enough open runway are left out
+    pub require_fuel: Option<Fuel>,     // airports known not to sell this fuel are left out
+}
+
+impl DiversionCriteria {
+    pub fn new() -> DiversionCriteria {
+        DiversionCriteria {
+            radius: 50.0,
+            count: 3,
+            min_runway_length: None,
+            require_fuel: None,
+        }
+    }
+}
+
+/// Closed airports, heliports and other kinds of fields (balloonports, seaplane bases) are not proposed,
+/// nor airports whose runways are too short or unknown when a minimum length is set,
+/// nor airports known not to sell the required fuel (airports without fuel data are kept)
+fn is_suitable(airport: &Airport, conn: &Connection, criteria: &DiversionCriteria) -> Result<bool, rusqlite::Error> {
+    if !matches!(
+        airport.airport_type,
+        AirportType::small_airport | AirportType::medium_airport | AirportType::large_airport
+    ) {
+        return Ok(false);
+    }
+    if let Some(min_length) = criteria.min_runway_length {
+        if !airport.longest_runway(conn)?.is_some_and(|length| length >= min_length) {
+            return Ok(false);
+        }
+    }
+    if let Some(fuel) = criteria.require_fuel {
+        if airport.fuels(conn)?.is_some_and(|fuels| !fuels.contains(&fuel)) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Lists the airfields suitable for a diversion from a point along the route, nearest in time first.
+/// Course, time and fuel account for the wind of the leg the point lies on.
+/// Returns an empty list if the point cannot be located on the route.
+pub fn diversions(navigation: &Navigation, aircraft: &Aircraft, at: &RoutePosition, db_path: &str, criteria: &DiversionCriteria) -> Result<Vec<Diversion>, rusqlite::Error> {
+    let Some((point, leg)) = navigation.locate(at) else {
+        return Ok(Vec::new());
+    };
+    let wind = navigation.branches[leg].wind;
+
+    let conn = Connection::open(db_path)?;
+    let mut diversions = Vec::new();
+    for (airport, _) in spatial::airports_within_radius(db_path, &point, criteria.radius)? {
+        if !is_suitable(&airport, &conn, criteria)? {
+            continue;
+        }
+        let mut branch = NavBranch::between(point.clone(), airport.to_nav_point(), wind, None, EarthModel::spherical);
+        branch.estimate_time(aircraft.cruise_speed);
+        let (Some(time), Some(course), Some(distance)) = (branch.time, branch.course, branch.distance) else {
+            continue;
+        };
+        diversions.push(Diversion {
+            course: course.rem_euclid(360.0),
+            distance,
+            fuel: time.num_seconds() as f64 / 3600.0 * aircraft.consomatation,
+            time,
+            airport,
+        });
+    }
+    diversions.sort_by_key(|d| d.time);
+    diversions.truncate(criteria.count);
+    Ok(diversions)
+}
+
+/// Diversion table for the briefing pack: the diversions every `interval` of flight time along the route
+pub fn diversion_table(navigation: &Navigation, aircraft: &Aircraft, interval: Duration, db_path: &str, criteria: &DiversionCriteria) -> Result<Vec<String>, rusqlite::Error> {
+    let mut lines = Vec::new();
+    let Some(flight_time) = navigation.flight_time() else {
+        return Ok(lines);
+    };
+    let mut time = interval;
+    while time < flight_time {
+        let entries: Vec<String> = diversions(navigation, aircraft, &RoutePosition::time(time), db_path, criteria)?
+            .iter()
+            .map(|d| {
+                format!(
+                    "{} {:03.0}° {:.0} NM {}' {:.1} L",
+                    d.airport.oaci_code,
+                    d.course,
+                    d.distance * KM_TO_NM,
+                    (d.time.num_seconds() as f64 / 60.0).round(),
+                    d.fuel
+                )
+            })
+            .collect();
+        lines.push(format!("T+{:>3}' | {}", time.num_minutes(), entries.join(" | ")));
+        time += interval;
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuel_criterion_keeps_airports_without_fuel_data() {
+        let db_path = std::env::temp_dir().join("flight_planner_diversion_fuels.db");
+        let db_path = db_path.to_str().unwrap();
+        std::fs::copy("data/airports.db", db_path).unwrap();
+        let conn = Connection::open(db_path).unwrap();
+        conn.execute("INSERT OR REPLACE INTO airport_fuels (airport_ident, fuel) VALUES ('LFPN', 'JET_A1')", [])
+            .unwrap();
+        let toussus = Airport::from_db(db_path, "LFPN").unwrap().remove(0);
+        let beauvais = Airport::from_db(db_path, "LFOB").unwrap().remove(0);
+
+        let mut criteria = DiversionCriteria::new();
+        criteria.require_fuel = Some(Fuel::AVGAS100LL);
+        assert!(!is_suitable(&toussus, &conn, &criteria).unwrap());
+        assert!(is_suitable(&beauvais, &conn, &criteria).unwrap());
+        criteria.require_fuel = Some(Fuel::JET_A1);
+        assert!(is_suitable(&toussus, &conn, &criteria).unwrap());
+        std::fs::remove_file(db_path).unwrap();
+    }
+
+    #[test]
+    fn runway_length_criterion_ignores_closed_runways() {
+        let db_path = std::env::temp_dir().join("flight_planner_diversion_runways.db");
+        let db_path = db_path.to_str().unwrap();
+        std::fs::copy("data/airports.db", db_path).unwrap();
+        let conn = Connection::open(db_path).unwrap();
+        conn.execute_batch(
+            "INSERT INTO runways (airport_ident, length_ft, closed, le_ident, he_ident) VALUES ('LFPN', 3600, 0, '07', '25');
+             INSERT INTO runways (airport_ident, length_ft, closed, le_ident, he_ident) VALUES ('LFOB', 5000, 1, '04', '22');
+             INSERT INTO runways (airport_ident, length_ft, closed, le_ident, he_ident) VALUES ('LFOB', 2000, 0, '13', '31');",
+        )
+        .unwrap();
+        let toussus = Airport::from_db(db_path, "LFPN").unwrap().remove(0);
+        let beauvais = Airport::from_db(db_path, "LFOB").unwrap().remove(0);
+        let pontoise = Airport::from_db(db_path, "LFPT").unwrap().remove(0);
+
+        let mut criteria = DiversionCriteria::new();
+        criteria.min_runway_length = Some(3000.0);
+        assert!(is_suitable(&toussus, &conn, &criteria).unwrap());
+        assert!(!is_suitable(&beauvais, &conn, &criteria).unwrap());
+        // Unknown runways do not meet a minimum length
+        assert!(!is_suitable(&pontoise, &conn, &criteria).unwrap());
+        std::fs::remove_file(db_path).unwrap();
+    }
+}
@@ -18,4 +18,140 @@ pub enum BalanceError {
     tank_capacity_exceeded(i32),
     #[error("Unknown error")]
     unknown_error,
-}
\ No newline at end of file
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum DaylightError {
+    #[error("Takeoff at {0} is outside legal day, from {1} to {2}")]
+    takeoff_outside_day(String, String, String),
+    #[error("Landing at {0} is outside legal day, from {1} to {2}")]
+    landing_outside_day(String, String, String),
+    #[error("No legal day at {0} on that date")]
+    no_daylight(String),
+    #[error("Flight plan has no off-block time or leg times")]
+    missing_times,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum LevelError {
+    #[error("Invalid level {0}")]
+    invalid_level(String),
+    #[error("Leg {0}: {1} does not follow the semicircular rule, nearest level is {2}")]
+    wrong_semicircle(String, String, String),
+    #[error("Leg {0} has no course")]
+    missing_course(String),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TerrainError {
+    #[error("Cannot read terrain file: {0}")]
+    io(#[from] std::io::Error),
+    #[error("Wrong elevation tile size for {0}")]
+    wrong_tile_size(String),
+    #[error("Wrong obstacle file format: {0}")]
+    wrong_format(#[from] csv::Error),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CoordinateError {
+    #[error("Cannot read position {0}")]
+    invalid_format(String),
+    #[error("Wrong or missing hemisphere in {0}")]
+    wrong_hemisphere(String),
+    #[error("Latitude or longitude out of range in {0}")]
+    out_of_range(String),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum MapError {
+    #[error("Chart of {0} x {1} pixels is too large, lower the dpi or the scale")]
+    too_large(u64, u64),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ImportError {
+    #[error("Cannot read CSV file: {0}")]
+    csv(#[from] csv::Error),
+    #[error("Missing column {0} in CSV file")]
+    missing_column(String),
+    #[error("Database error: {0}")]
+    database(#[from] rusqlite::Error),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RunwayError {
+    #[error("{0}: no open runway with a known heading")]
+    no_runway(String),
+    #[error("{0} runway {1}: tailwind {2:.0} kt")]
+    tailwind(String, String, f64),
+    #[error("{0} runway {1}: crosswind {2:.0} kt exceeds the maximum demonstrated {3:.0} kt")]
+    crosswind_exceeded(String, String, f64, f64),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RouteError {
+    #[error("Unknown waypoint {0}")]
+    unknown_waypoint(String),
+    #[error("Database error: {0}")]
+    database(#[from] rusqlite::Error),
+    #[error("Cannot read route file: {0}")]
+    io(#[from] std::io::Error),
+    #[error("Wrong route file format: {0}")]
+    wrong_format(#[from] roxmltree::Error),
+    #[error("Unknown route file format {0}")]
+    unknown_format(String),
+    #[error("No route with at least two points in {0}")]
+    empty_route(String),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum FplError {
+    #[error("Item 7: aircraft identification {0} must be 2 to 7 letters or digits")]
+    invalid_identification(String),
+    #[error("Item 8: flight rules {0} must be V, I, Y or Z")]
+    invalid_flight_rules(char),
+    #[error("Item 9: type designator {0} must be 2 to 4 letters or digits, or ZZZZ")]
+    invalid_type_designator(String),
+    #[error("Item 9: wake turbulence category {0} must be L, M, H or J")]
+    invalid_wake_category(char),
+    #[error("Item 10: equipment {0} must be N, or S and/or letters A to Z with their digit where needed")]
+    invalid_equipment(String),
+    #[error("Item 10: surveillance {0} must be N, or codes among A C E H I L P S X B1 B2 U1 U2 V1 V2 D1 G1")]
+    invalid_surveillance(String),
+    #[error("Item {0}: aerodrome {1} must be a 4-letter ICAO location indicator or ZZZZ")]
+    invalid_aerodrome(u8, String),
+    #[error("Item 13: no off-block time")]
+    missing_eobt,
+    #[error("Item 15: cruising speed {0} must be N and 4 digits (kt), K and 4 digits (km/h) or M and 3 digits")]
+    invalid_speed(String),
+    #[error("Item 15: cruising level {0} must be A or F and 3 digits, or VFR")]
+    invalid_level(String),
+    #[error("Item 15: route element {0} is not a significant point, coordinates, bearing/distance or DCT")]
+    invalid_route_element(String),
+    #[error("Item 16: no total estimated elapsed time, estimate the leg times first")]
+    missing_eet,
+    #[error("Item 16: at most 2 alternates, {0} given")]
+    too_many_alternates(usize),
+    #[error("Item 19: persons on board {0} must be 1 to 999")]
+    invalid_persons_on_board(u32),
+    #[error("Item 19: endurance {0} is shorter than the total estimated elapsed time {1}")]
+    insufficient_endurance(String, String),
+    #[error("Item {0}: {1} contains characters not allowed in an FPL message")]
+    invalid_characters(u8, String),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PlanError {
+    #[error("Cannot read or write plan file: {0}")]
+    io(#[from] std::io::Error),
+    #[error("Wrong plan file format: {0}")]
+    json(#[from] serde_json::Error),
+    #[error("Plan document has no version")]
+    missing_version,
+    #[error("Plan document version {0} is newer than the supported version {1}, update the planner")]
+    newer_version(u64, u64),
+    #[error("Database error: {0}")]
+    database(#[from] rusqlite::Error),
+    #[error("Cannot load the aircraft: {0}")]
+    balance(#[from] BalanceError),
+}
@@ -1,7 +1,21 @@
-use super::geographics::{NavPoint, calculate_distance_and_course};
+use super::aircraft::Aircraft;
+use super::airport::Airport;
+use super::coordinates::CoordinateFormat;
+use super::error::{DaylightError, LevelError};
+use super::geographics::{
+    along_track_distance, calculate_distance_and_course, cross_track_distance, distance_and_course, intermediate_point,
+    rhumb_distance_and_course, rhumb_intermediate_point, rhumb_track_distances, EarthModel, LegType, NavPoint, KM_TO_NM,
+};
+use super::levels::{self, FlightRules};
+use super::profile::VerticalProfile;
+use super::runway::{select_runway, RunwaySelection};
+use super::timezone::format_utc_and_local;
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
 use reqwest::Error;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Wind {
     pub speed: f64,
     pub direction: f64,
@@ -11,6 +25,9 @@ pub struct Navigation {
     pub start: NavPoint,
     pub end: NavPoint,
     pub branches: Vec<NavBranch>,
+    pub off_block: Option<DateTime<Utc>>,
+    pub taxi_out: Duration,
+    pub taxi_in: Duration,
 }
 pub struct NavBranch {
     pub from: Option<NavPoint>,
@@ -18,7 +35,259 @@ pub struct NavBranch {
     pub distance: Option<f64>,
     pub course: Option<f64>,
     pub wind: Wind,
-    pub time: f64,
+    pub time: Option<Duration>,
+    pub altitude: Option<f64>, // cruise altitude in ft, None to keep the previous leg altitude
+    pub earth_model: EarthModel, // model used to calculate distance and course
+    pub leg_type: LegType,
+}
+
+/// Position of the aircraft relative to a leg, distances in km
+#[derive(Debug, Clone)]
+pub struct LegProgress {
+    pub along_track: f64,    // distance flown along the leg
+    pub cross_track: f64,    // distance off track, positive right of track
+    pub distance_to_go: f64, // direct distance to the end of the leg
+    pub course_to_go: f64,   // direct course to the end of the leg, degrees true
+}
+
+impl LegProgress {
+    pub fn is_right_of_track(&self) -> bool {
+        self.cross_track > 0.0
+    }
+}
+
+/// A point along a navigation
+#[derive(Debug, Clone)]
+pub enum RoutePosition {
+    /// Distance flown from the departure, in km
+    distance(f64),
+    /// Time flown since takeoff
+    time(Duration),
+    /// Actual position, matched to the nearest leg
+    position(NavPoint),
+}
+
+/// Estimated time over a waypoint
+pub struct Eta {
+    pub name: String,
+    pub time: DateTime<Utc>,
+}
+
+/// Times of a flight computed from the off-block time, all in UTC
+pub struct Timeline {
+    pub off_block: DateTime<Utc>,
+    pub takeoff: DateTime<Utc>,
+    pub etas: Vec<Eta>,
+    pub landing: DateTime<Utc>,
+    pub on_block: DateTime<Utc>,
+    pub block_time: Duration,
+}
+
+impl Navigation {
+    pub fn new(start: NavPoint, end: NavPoint, branches: Vec<NavBranch>) -> Navigation {
+        Navigation {
+            start,
+            end,
+            branches,
+            off_block: None,
+            taxi_out: Duration::zero(),
+            taxi_in: Duration::zero(),
+        }
+    }
+
+    /// Sum of the legs time, from takeoff to landing.
+    /// Returns `None` if a leg has no time yet (see `NavBranch::estimate_time`).
+    pub fn flight_time(&self) -> Option<Duration> {
+        self.branches.iter().map(|b| b.time).sum()
+    }
+
+    /// Flight time plus taxi out and taxi in
+    pub fn block_time(&self) -> Option<Duration> {
+        Some(self.taxi_out + self.flight_time()? + self.taxi_in)
+    }
+
+    /// Splits the navigation in climb, cruise and descent with top-of-climb and top-of-descent
+    /// pseudo-waypoints, see `VerticalProfile::compute`
+    pub fn vertical_profile(&self, aircraft: &Aircraft, departure_elevation: f64, destination_elevation: f64) -> Option<VerticalProfile> {
+        VerticalProfile::compute(self, aircraft, departure_elevation, destination_elevation)
+    }
+
+    /// Checks the planned altitude of every leg against the semicircular cruising levels.
+    /// `magnetic_variation` is in degrees, east positive.
+    /// A leg without altitude is checked at the altitude of the previous leg.
+    /// Returns one error per non compliant leg, with the nearest compliant level.
+    pub fn check_cruising_levels(&self, rules: FlightRules, magnetic_variation: f64) -> Vec<LevelError> {
+        let mut errors = Vec::new();
+        let mut altitude = None;
+        for branch in &self.branches {
+            altitude = branch.altitude.or(altitude);
+            if let Some(altitude) = altitude {
+                if let Err(error) = branch.check_cruising_level_at(altitude, rules, magnetic_variation) {
+                    errors.push(error);
+                }
+            }
+        }
+        errors
+    }
+
+    /// Nav log with one line per leg: name, true course, distance, altitude, time and the position
+    /// of the end of the leg in degrees and decimal minutes, as entered in a GPS.
+    /// When the rhumb line and the great circle differ by at least 1° or 0.5 NM, the
+    /// difference is shown under the leg.
+    pub fn nav_log(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for branch in &self.branches {
+            let course = branch.course.map_or("---".to_string(), |c| format!("{:03.0}°", c.rem_euclid(360.0)));
+            let distance = branch.distance.map_or("---".to_string(), |d| format!("{:.1} NM", d * KM_TO_NM));
+            let altitude = branch
+                .altitude
+                .map_or("---".to_string(), |a| levels::format_level(a, levels::TRANSITION_ALTITUDE));
+            let time = branch.time.map_or("---".to_string(), |t| format!("{}'", (t.num_seconds() as f64 / 60.0).round()));
+            let position = branch.to.as_ref().map_or(String::new(), |p| p.position(CoordinateFormat::ddm));
+            lines.push(format!("{:<15} {:>5} {:>9} {:>8} {:>5}  {}", branch.name(), course, distance, altitude, time, position));
+
+            if let Some((distance_difference, course_difference)) = branch.rhumb_difference() {
+                if course_difference.abs() >= 1.0 || (distance_difference * KM_TO_NM).abs() >= 0.5 {
+                    let other = match branch.leg_type {
+                        LegType::great_circle => "rhumb line",
+                        LegType::rhumb_line => "great circle",
+                    };
+                    let sign = match branch.leg_type {
+                        LegType::great_circle => 1.0,
+                        LegType::rhumb_line => -1.0,
+                    };
+                    lines.push(format!(
+                        "    {}: {:+.0}° {:+.1} NM",
+                        other,
+                        sign * course_difference,
+                        sign * distance_difference * KM_TO_NM
+                    ));
+                }
+            }
+        }
+        lines
+    }
+
+    /// Finds a point along the route, and the index of the leg it lies on.
+    /// Returns `None` if the point is beyond the end of the route, or if a leg
+    /// is missing its distance (or its time, to locate by time).
+    pub fn locate(&self, at: &RoutePosition) -> Option<(NavPoint, usize)> {
+        let (target, measure): (f64, fn(&NavBranch) -> Option<f64>) = match at {
+            RoutePosition::distance(distance) => (*distance, |b| b.distance),
+            RoutePosition::time(time) => (time.num_seconds() as f64, |b| b.time.map(|t| t.num_seconds() as f64)),
+            RoutePosition::position(position) => {
+                return self
+                    .branches
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, branch)| {
+                        let progress = branch.progress(position)?;
+                        let length = branch.distance?;
+                        let off_route = if (0.0..=length).contains(&progress.along_track) {
+                            progress.cross_track.abs()
+                        } else {
+                            progress.distance_to_go.min(progress.along_track.abs().hypot(progress.cross_track))
+                        };
+                        Some((index, off_route))
+                    })
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(index, _)| (position.clone(), index));
+            }
+        };
+        let mut start = 0.0;
+        for (index, branch) in self.branches.iter().enumerate() {
+            let length = measure(branch)?;
+            if target <= start + length {
+                let fraction = if length > 0.0 { ((target - start) / length).max(0.0) } else { 0.0 };
+                return Some((branch.point_at(fraction, "POS".to_string())?, index));
+            }
+            start += length;
+        }
+        None
+    }
+
+    /// Computes the ETA over each waypoint, the landing and on-block times from the off-block time.
+    /// Returns `None` if the off-block time is not set or a leg has no time.
+    pub fn timeline(&self) -> Option<Timeline> {
+        let off_block = self.off_block?;
+        let takeoff = off_block + self.taxi_out;
+        let mut current = takeoff;
+        let mut etas = Vec::new();
+        for branch in &self.branches {
+            current += branch.time?;
+            let name = branch.to.as_ref().map(|p| p.name.clone()).unwrap_or_default();
+            etas.push(Eta { name, time: current });
+        }
+        Some(Timeline {
+            off_block,
+            takeoff,
+            etas,
+            landing: current,
+            on_block: current + self.taxi_in,
+            block_time: self.block_time()?,
+        })
+    }
+
+    /// Checks that the flight can be done VFR day: takeoff within legal day at `departure` and landing
+    /// within legal day at `destination` (from 30 minutes before sunrise to 30 minutes after sunset),
+    /// each on the local date of the airport. Polar day counts as day all day long.
+    pub fn check_vfr_day(&self, departure: &Airport, destination: &Airport) -> Result<(), DaylightError> {
+        let timeline = self.timeline().ok_or(DaylightError::missing_times)?;
+        let zone = departure.timezone();
+        let sun = departure.sun_times(timeline.takeoff.with_timezone(&zone).date_naive());
+        if !sun.is_day(timeline.takeoff) {
+            let (start, end) = sun.day_start().zip(sun.day_end()).ok_or_else(|| DaylightError::no_daylight(departure.oaci_code.clone()))?;
+            return Err(DaylightError::takeoff_outside_day(
+                format_utc_and_local(timeline.takeoff, zone),
+                format_utc_and_local(start, zone),
+                format_utc_and_local(end, zone),
+            ));
+        }
+        let zone = destination.timezone();
+        let sun = destination.sun_times(timeline.landing.with_timezone(&zone).date_naive());
+        if !sun.is_day(timeline.landing) {
+            let (start, end) = sun.day_start().zip(sun.day_end()).ok_or_else(|| DaylightError::no_daylight(destination.oaci_code.clone()))?;
+            return Err(DaylightError::landing_outside_day(
+                format_utc_and_local(timeline.landing, zone),
+                format_utc_and_local(start, zone),
+                format_utc_and_local(end, zone),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Recommended runways at `departure` and `destination` for the wind at each airport (`winds`, in that order),
+    /// checked against the maximum demonstrated crosswind of the aircraft.
+    /// `magnetic_variation` is in degrees, east positive, for the runways known only by their number.
+    pub fn select_runways(&self, departure: &Airport, destination: &Airport, winds: (Wind, Wind), magnetic_variation: Option<f64>, aircraft: &Aircraft, db_path: &str) -> Result<(RunwaySelection, RunwaySelection), rusqlite::Error> {
+        let (departure_wind, destination_wind) = winds;
+        Ok((
+            select_runway(&departure.oaci_code, &departure.runways(db_path)?, departure_wind, magnetic_variation, aircraft.max_crosswind),
+            select_runway(&destination.oaci_code, &destination.runways(db_path)?, destination_wind, magnetic_variation, aircraft.max_crosswind),
+        ))
+    }
+}
+
+impl Timeline {
+    /// Renders the timeline as nav log lines, in UTC with the local time of the
+    /// departure airport before takeoff and of the destination airport after landing.
+    pub fn nav_log(&self, departure: Tz, destination: Tz) -> Vec<String> {
+        let mut lines = vec![
+            format!("Off-block : {}", format_utc_and_local(self.off_block, departure)),
+            format!("Takeoff   : {}", format_utc_and_local(self.takeoff, departure)),
+        ];
+        for eta in &self.etas {
+            lines.push(format!("{:<10}: {}", eta.name, eta.time.format("%H:%MZ")));
+        }
+        lines.push(format!("Landing   : {}", format_utc_and_local(self.landing, destination)));
+        lines.push(format!("On-block  : {}", format_utc_and_local(self.on_block, destination)));
+        lines.push(format!(
+            "Block time: {}h{:02}",
+            self.block_time.num_hours(),
+            self.block_time.num_minutes() % 60
+        ));
+        lines
+    }
 }
 
 /// Creates a new `NavBranch` instance.
@@ -29,23 +298,24 @@ pub struct NavBranch {
 /// - `distance`: An optional `f64` representing the distance between the `from` and `to` points. If not provided, it will be calculated.
 /// - `course`: An optional `f64` representing the course (bearing) from the `from` point to the `to` point. If not provided, it will be calculated.
 /// - `wind`: A `Wind` struct representing the wind conditions.
-/// - `time`: A `f64` representing the time taken for the navigation.
+/// - `time`: An optional `Duration` of the leg. If not provided, use `estimate_time` once the true airspeed is known.
+/// - `altitude`: An optional cruise altitude in feet. If not provided, the altitude of the previous leg is kept.
 /// - `fuel`: A `f64` representing the fuel consumed during the navigation.
 ///
 /// # Returns
 /// A `NavBranch` instance with the provided or calculated values.
 ///
 /// # Calculations
-/// If either `distance` or `course` is not provided:
-/// - The distance is calculated using the Haversine formula, which determines the great-circle distance between two points on a sphere given their longitudes and latitudes.
-/// - The course is calculated using the initial bearing formula, which determines the angle between the north direction and the line connecting the two points.
+/// If either `distance` or `course` is not provided, the distance is calculated using the Haversine formula, which determines the great-circle distance between two points on a sphere given their longitudes and latitudes.
+/// The course is calculated using the initial bearing formula, which determines the angle between the north direction and the line connecting the two points.
+/// Use `NavBranch::between` to solve them on the WGS-84 ellipsoid (Vincenty) instead.
 ///
 /// # Example
 /// ```
 /// let from = Some(NavPoint { latitude: 34.0522, longitude: -118.2437 });
 /// let to = Some(NavPoint { latitude: 40.7128, longitude: -74.0060 });
 /// let wind = Wind { speed: 10.0, direction: 90.0 };
-/// let nav_branch = NavBranch::new(from, to, None, None, wind, 5.0, 100.0);
+/// let nav_branch = NavBranch::new(from, to, None, None, wind, None, Some(2500.0));
 /// ```
 impl NavBranch {
     pub fn new(
@@ -54,11 +324,12 @@ impl NavBranch {
         distance: Option<f64>,
         course: Option<f64>,
         wind: Wind,
-        time: f64,
+        time: Option<Duration>,
+        altitude: Option<f64>,
     ) -> NavBranch {
         let (calculated_distance, calculated_course) = if distance.is_none() || course.is_none() {
             if let (Some(from), Some(to)) = (&from, &to) {
-                calculate_distance_and_course(from, to)
+                distance_and_course(from, to, EarthModel::spherical)
             } else {
                 (None, None)
             }
@@ -73,6 +344,320 @@ impl NavBranch {
             course: course.or(calculated_course),
             wind,
             time,
+            altitude,
+            earth_model: EarthModel::spherical,
+            leg_type: LegType::great_circle,
+        }
+    }
+
+    /// Leg from `from` to `to`, its distance and course computed with `earth_model`.
+    /// Vincenty does not converge between nearly antipodal points: the leg is then solved on the sphere,
+    /// and its `earth_model` records it.
+    pub fn between(from: NavPoint, to: NavPoint, wind: Wind, altitude: Option<f64>, earth_model: EarthModel) -> NavBranch {
+        let (mut distance, mut course) = distance_and_course(&from, &to, earth_model);
+        let mut earth_model = earth_model;
+        if distance.is_none() || course.is_none() {
+            (distance, course) = distance_and_course(&from, &to, EarthModel::spherical);
+            earth_model = EarthModel::spherical;
         }
+        let mut branch = NavBranch::new(Some(from), Some(to), distance, course, wind, None, altitude);
+        branch.earth_model = earth_model;
+        branch
     }
-}
\ No newline at end of file
+
+    /// Ground speed in knots for the given true airspeed in knots, solving the wind triangle.
+    /// Returns `None` if the course is unknown or the wind is stronger than the aircraft can correct.
+    pub fn ground_speed(&self, true_airspeed: f64) -> Option<f64> {
+        let course = self.course?.to_radians();
+        let wind_angle = self.wind.direction.to_radians() - course;
+        let drift_sin = self.wind.speed * wind_angle.sin() / true_airspeed;
+        if drift_sin.abs() > 1.0 {
+            return None;
+        }
+        let ground_speed = true_airspeed * drift_sin.asin().cos() - self.wind.speed * wind_angle.cos();
+        if ground_speed > 0.0 {
+            Some(ground_speed)
+        } else {
+            None
+        }
+    }
+
+    /// Sets the leg time from the distance and the ground speed at `true_airspeed` (knots)
+    pub fn estimate_time(&mut self, true_airspeed: f64) {
+        if let (Some(distance), Some(ground_speed)) = (self.distance, self.ground_speed(true_airspeed)) {
+            let hours = distance * KM_TO_NM / ground_speed;
+            self.time = Some(Duration::seconds((hours * 3600.0).round() as i64));
+        }
+    }
+
+    /// Sets the path followed along the leg and recalculates its distance and course
+    pub fn set_leg_type(&mut self, leg_type: LegType) {
+        self.leg_type = leg_type;
+        if let (Some(from), Some(to)) = (&self.from, &self.to) {
+            let (distance, course) = match leg_type {
+                LegType::great_circle => distance_and_course(from, to, self.earth_model),
+                LegType::rhumb_line => rhumb_distance_and_course(from, to, self.earth_model),
+            };
+            self.distance = distance;
+            self.course = course;
+        }
+    }
+
+    /// Difference between the rhumb line and the great circle of the leg, as
+    /// (rhumb line minus great circle distance in km, course difference in degrees at departure).
+    pub fn rhumb_difference(&self) -> Option<(f64, f64)> {
+        let (from, to) = (self.from.as_ref()?, self.to.as_ref()?);
+        let (great_circle_distance, great_circle_course) = distance_and_course(from, to, self.earth_model);
+        let (rhumb_distance, rhumb_course) = rhumb_distance_and_course(from, to, self.earth_model);
+        let course_difference = (rhumb_course? - great_circle_course? + 540.0).rem_euclid(360.0) - 180.0;
+        Some((rhumb_distance? - great_circle_distance?, course_difference))
+    }
+
+    /// Point at `fraction` of the leg along its great circle or rhumb line, 0.0 at `from` and 1.0 at `to`
+    pub fn point_at(&self, fraction: f64, name: String) -> Option<NavPoint> {
+        let (from, to) = (self.from.as_ref()?, self.to.as_ref()?);
+        Some(match self.leg_type {
+            LegType::great_circle => intermediate_point(from, to, fraction, name),
+            LegType::rhumb_line => rhumb_intermediate_point(from, to, fraction, name),
+        })
+    }
+
+    /// Points along the leg, at most `spacing` km apart, from `from` to `to` included
+    pub fn track(&self, spacing: f64) -> Vec<NavPoint> {
+        let steps = self.distance.map_or(1, |d| (d / spacing).ceil().max(1.0) as usize);
+        (0..=steps)
+            .filter_map(|k| self.point_at(k as f64 / steps as f64, String::new()))
+            .collect()
+    }
+
+    /// Along-track and cross-track distances of `position` relative to the leg, and the distance and course to go
+    pub fn progress(&self, position: &NavPoint) -> Option<LegProgress> {
+        let (from, to) = (self.from.as_ref()?, self.to.as_ref()?);
+        let (distance_to_go, course_to_go) = calculate_distance_and_course(position, to);
+        let (along_track, cross_track) = match self.leg_type {
+            LegType::great_circle => (along_track_distance(from, to, position)?, cross_track_distance(from, to, position)?),
+            LegType::rhumb_line => rhumb_track_distances(from, to, position)?,
+        };
+        Some(LegProgress {
+            along_track,
+            cross_track,
+            distance_to_go: distance_to_go?,
+            course_to_go: course_to_go?.rem_euclid(360.0),
+        })
+    }
+
+    /// Leg name as `FROM-TO`
+    pub fn name(&self) -> String {
+        let name = |p: &Option<NavPoint>| p.as_ref().map(|p| p.name.clone()).unwrap_or_default();
+        format!("{}-{}", name(&self.from), name(&self.to))
+    }
+
+    /// Magnetic course in degrees [0, 360), from the true course and the magnetic variation (east positive)
+    pub fn magnetic_course(&self, magnetic_variation: f64) -> Option<f64> {
+        self.course.map(|c| (c - magnetic_variation).rem_euclid(360.0))
+    }
+
+    /// Checks the planned altitude of the leg against the semicircular cruising levels.
+    /// A leg without altitude is considered compliant, see `Navigation::check_cruising_levels`
+    /// for legs keeping the altitude of the previous one.
+    pub fn check_cruising_level(&self, rules: FlightRules, magnetic_variation: f64) -> Result<(), LevelError> {
+        match self.altitude {
+            Some(altitude) => self.check_cruising_level_at(altitude, rules, magnetic_variation),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks `altitude` against the semicircular cruising levels for the track of the leg
+    fn check_cruising_level_at(&self, altitude: f64, rules: FlightRules, magnetic_variation: f64) -> Result<(), LevelError> {
+        let track = self.magnetic_course(magnetic_variation).ok_or_else(|| LevelError::missing_course(self.name()))?;
+        if levels::is_compliant(altitude, track, rules) {
+            return Ok(());
+        }
+        Err(LevelError::wrong_semicircle(
+            self.name(),
+            levels::format_level(altitude, levels::TRANSITION_ALTITUDE),
+            levels::format_level(levels::nearest_compliant(altitude, track, rules), levels::TRANSITION_ALTITUDE),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navigation::airport::AirportType;
+    use crate::navigation::geographics::project_point;
+
+    fn guadeloupe_flight(off_block: &str, minutes: i64) -> (Navigation, Airport, Airport) {
+        let departure = Airport::new("TFFR".to_string(), "Pointe-a-Pitre".to_string(), AirportType::large_airport, 16.2653, -61.5318, "GP".to_string());
+        let destination = Airport::new("TFFM".to_string(), "Marie-Galante".to_string(), AirportType::small_airport, 15.8687, -61.2700, "GP".to_string());
+        let mut branch = NavBranch::between(departure.to_nav_point(), destination.to_nav_point(), Wind { speed: 0.0, direction: 0.0 }, Some(2500.0), EarthModel::spherical);
+        branch.time = Some(Duration::minutes(minutes));
+        let mut navigation = Navigation::new(departure.to_nav_point(), destination.to_nav_point(), vec![branch]);
+        navigation.off_block = Some(DateTime::parse_from_rfc3339(off_block).unwrap().into());
+        (navigation, departure, destination)
+    }
+
+    #[test]
+    fn near_antipodal_leg_falls_back_to_the_sphere() {
+        let wind = Wind { speed: 0.0, direction: 0.0 };
+        let from = NavPoint::new("A".to_string(), 0.0, 0.0);
+        let to = NavPoint::new("B".to_string(), 0.5, 179.7);
+        assert_eq!(distance_and_course(&from, &to, EarthModel::wgs84), (None, None));
+        let branch = NavBranch::between(from.clone(), to.clone(), wind, None, EarthModel::wgs84);
+        assert_eq!(branch.earth_model, EarthModel::spherical);
+        assert_eq!((branch.distance, branch.course), distance_and_course(&from, &to, EarthModel::spherical));
+
+        let to = NavPoint::new("C".to_string(), 1.0, 1.0);
+        let branch = NavBranch::between(from, to, wind, None, EarthModel::wgs84);
+        assert_eq!(branch.earth_model, EarthModel::wgs84);
+    }
+
+    #[test]
+    fn rhumb_difference_on_the_same_earth_model() {
+        let wind = Wind { speed: 0.0, direction: 0.0 };
+        // Along the equator the rhumb line is the great circle, on any model
+        let from = NavPoint::new("A".to_string(), 0.0, 10.0);
+        let to = NavPoint::new("B".to_string(), 0.0, 12.0);
+        let branch = NavBranch::between(from, to, wind, None, EarthModel::wgs84);
+        let (distance_difference, course_difference) = branch.rhumb_difference().unwrap();
+        assert!(distance_difference.abs() < 1e-6);
+        assert!(course_difference.abs() < 1e-9);
+    }
+
+    #[test]
+    fn nav_log_shows_the_leg_end_position() {
+        let wind = Wind { speed: 0.0, direction: 0.0 };
+        let from = NavPoint::new("A".to_string(), 48.0, 2.0);
+        let to = NavPoint::new("B".to_string(), 48.5, 2.1);
+        let branch = NavBranch::between(from.clone(), to.clone(), wind, Some(2500.0), EarthModel::spherical);
+        let navigation = Navigation::new(from, to, vec![branch]);
+        let log = navigation.nav_log();
+        assert!(log[0].starts_with("A-B "), "{}", log[0]);
+        assert!(log[0].ends_with("  N48 30.00 E002 06.00"), "{}", log[0]);
+    }
+
+    #[test]
+    fn progress_along_and_across_a_great_circle_leg() {
+        let wind = Wind { speed: 0.0, direction: 0.0 };
+        let from = NavPoint::new("A".to_string(), 48.0, 2.0);
+        let to = NavPoint::new("B".to_string(), 49.0, 2.0);
+        let branch = NavBranch::between(from.clone(), to.clone(), wind, None, EarthModel::spherical);
+        let length = branch.distance.unwrap();
+
+        let quarter = branch.point_at(0.25, "Q".to_string()).unwrap();
+        assert!((quarter.latitude - 48.25).abs() < 1e-9 && (quarter.longitude - 2.0).abs() < 1e-9);
+        assert_eq!(quarter.name, "Q");
+
+        // 5 km right (east) of the middle of the northbound leg
+        let middle = branch.point_at(0.5, String::new()).unwrap();
+        let right = project_point(&middle, 90.0, 5.0, EarthModel::spherical, String::new());
+        let progress = branch.progress(&right).unwrap();
+        assert!((progress.along_track - length / 2.0).abs() < 0.01);
+        assert!((progress.cross_track - 5.0).abs() < 0.01);
+        assert!((progress.distance_to_go - (length / 2.0).hypot(5.0)).abs() < 0.1);
+        assert!(progress.course_to_go > 350.0);
+
+        let left = project_point(&middle, 270.0, 5.0, EarthModel::spherical, String::new());
+        assert!((branch.progress(&left).unwrap().cross_track + 5.0).abs() < 0.01);
+        // Behind the start of the leg
+        let behind = NavPoint::new(String::new(), 47.9, 2.0);
+        assert!(branch.progress(&behind).unwrap().along_track < 0.0);
+
+        let other = NavBranch::between(to, NavPoint::new("C".to_string(), 49.0, 3.0), wind, None, EarthModel::spherical);
+        let navigation = Navigation::new(from, NavPoint::new("C".to_string(), 49.0, 3.0), vec![branch, other]);
+        let (_, index) = navigation.locate(&RoutePosition::position(right)).unwrap();
+        assert_eq!(index, 0);
+        let (_, index) = navigation.locate(&RoutePosition::position(NavPoint::new(String::new(), 49.05, 2.5))).unwrap();
+        assert_eq!(index, 1);
+    }
+
+    #[test]
+    fn rhumb_leg_follows_its_constant_course() {
+        let wind = Wind { speed: 0.0, direction: 0.0 };
+        let from = NavPoint::new("A".to_string(), 45.0, -60.0);
+        let to = NavPoint::new("B".to_string(), 45.0, -40.0);
+        let mut branch = NavBranch::between(from, to, wind, None, EarthModel::spherical);
+        // The great circle bulges north of the parallel
+        assert!(branch.point_at(0.5, String::new()).unwrap().latitude > 45.4);
+        assert!(branch.progress(&NavPoint::new("P".to_string(), 45.0, -50.0)).unwrap().cross_track > 10.0);
+
+        branch.set_leg_type(LegType::rhumb_line);
+        assert_eq!(branch.course, Some(90.0));
+        let middle = branch.point_at(0.5, String::new()).unwrap();
+        assert!((middle.latitude - 45.0).abs() < 1e-9 && (middle.longitude + 50.0).abs() < 1e-9);
+        let progress = branch.progress(&middle).unwrap();
+        assert!((progress.along_track - branch.distance.unwrap() / 2.0).abs() < 1e-6);
+        assert!(progress.cross_track.abs() < 1e-6);
+        assert!(branch.track(100.0).iter().all(|p| (p.latitude - 45.0).abs() < 1e-9));
+    }
+
+    #[test]
+    fn timeline_adds_taxi_and_leg_times_to_the_off_block() {
+        let wind = Wind { speed: 0.0, direction: 0.0 };
+        let a = NavPoint::new("A".to_string(), 48.0, 2.0);
+        let b = NavPoint::new("B".to_string(), 48.5, 2.0);
+        let c = NavPoint::new("C".to_string(), 49.0, 2.0);
+        let mut first = NavBranch::between(a.clone(), b.clone(), wind, None, EarthModel::spherical);
+        let mut second = NavBranch::between(b, c.clone(), wind, None, EarthModel::spherical);
+        first.time = Some(Duration::minutes(25));
+        second.time = Some(Duration::minutes(40));
+        let mut navigation = Navigation::new(a, c, vec![first, second]);
+        assert!(navigation.timeline().is_none());
+
+        navigation.off_block = Some(DateTime::parse_from_rfc3339("2024-06-01T09:50:00Z").unwrap().into());
+        navigation.taxi_out = Duration::minutes(10);
+        navigation.taxi_in = Duration::minutes(5);
+        let timeline = navigation.timeline().unwrap();
+        let time = |t: DateTime<Utc>| t.format("%H:%M").to_string();
+        assert_eq!(time(timeline.takeoff), "10:00");
+        let etas: Vec<(&str, String)> = timeline.etas.iter().map(|eta| (eta.name.as_str(), time(eta.time))).collect();
+        assert_eq!(etas, [("B", "10:25".to_string()), ("C", "11:05".to_string())]);
+        assert_eq!(time(timeline.landing), "11:05");
+        assert_eq!(time(timeline.on_block), "11:10");
+        assert_eq!(timeline.block_time, Duration::minutes(80));
+
+        navigation.branches[1].time = None;
+        assert!(navigation.timeline().is_none());
+    }
+
+    #[test]
+    fn vfr_day_in_the_afternoon() {
+        // 14:00 local time in Guadeloupe (UTC-4)
+        let (navigation, departure, destination) = guadeloupe_flight("2026-10-19T18:00:00Z", 30);
+        assert!(navigation.check_vfr_day(&departure, &destination).is_ok());
+    }
+
+    #[test]
+    fn takeoff_after_sunset_west_of_greenwich() {
+        // 19:00 local, landing after midnight UTC: the UTC dates of takeoff and landing differ from the local one
+        let (navigation, departure, destination) = guadeloupe_flight("2026-10-19T23:00:00Z", 90);
+        assert!(matches!(navigation.check_vfr_day(&departure, &destination), Err(DaylightError::takeoff_outside_day(..))));
+    }
+
+    #[test]
+    fn landing_after_sunset_west_of_greenwich() {
+        // Takeoff at 17:00 local, landing at 20:30 local (00:30Z the next day)
+        let (navigation, departure, destination) = guadeloupe_flight("2026-10-19T21:00:00Z", 210);
+        assert!(matches!(navigation.check_vfr_day(&departure, &destination), Err(DaylightError::landing_outside_day(..))));
+    }
+
+    #[test]
+    fn inherited_altitude_is_checked_after_a_turn() {
+        let calm = Wind { speed: 0.0, direction: 0.0 };
+        let points = [
+            NavPoint::new("A".to_string(), 48.75, 2.1),
+            NavPoint::new("B".to_string(), 48.75, 1.5),
+            NavPoint::new("C".to_string(), 48.75, 2.5),
+        ];
+        // 4500 ft is right westbound, wrong when turning eastbound with the same altitude
+        let branches = vec![
+            NavBranch::between(points[0].clone(), points[1].clone(), calm, Some(4500.0), EarthModel::spherical),
+            NavBranch::between(points[1].clone(), points[2].clone(), calm, None, EarthModel::spherical),
+        ];
+        let navigation = Navigation::new(points[0].clone(), points[2].clone(), branches);
+        let errors = navigation.check_cruising_levels(FlightRules::vfr, 0.0);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(&errors[0], LevelError::wrong_semicircle(leg, ..) if leg == "B-C"));
+        assert!(navigation.branches[1].check_cruising_level(FlightRules::vfr, 0.0).is_ok());
+    }
+}
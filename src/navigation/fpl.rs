@@ -0,0 +1,483 @@
+use std::fmt;
+
+use chrono::{DateTime, Duration, Utc};
+
+use super::aircraft::Aircraft;
+use super::coordinates::{self, CoordinateFormat};
+use super::error::FplError;
+use super::flightplan::Navigation;
+use super::geographics::NavPoint;
+use super::levels::{self, FlightRules};
+use super::search::normalize;
+use super::waypoint::WaypointKind;
+
+/// Pilot in command, filed in item 19
+#[derive(Debug, Clone)]
+pub struct Pilot {
+    pub name: String,
+    pub phone: String, // filed in item 18 RMK/ when not empty
+}
+
+/// ICAO 2012 flight plan message, items 7 to 19
+#[derive(Debug, Clone)]
+pub struct FlightPlanMessage {
+    pub aircraft_identification: String, // item 7
+    pub flight_rules: char,              // item 8
+    pub flight_type: char,
+    pub aircraft_type: String, // item 9
+    pub wake_category: char,
+    pub equipment: String, // item 10
+    pub surveillance: String,
+    pub departure: String, // item 13
+    pub eobt: DateTime<Utc>,
+    pub cruising_speed: String, // item 15
+    pub cruising_level: String,
+    pub route: String,
+    pub destination: String, // item 16
+    pub total_eet: Duration,
+    pub alternates: Vec<String>,
+    pub other_information: Vec<String>, // item 18, e.g. "DOF/261019"
+    pub endurance: Duration,            // item 19
+    pub persons_on_board: u32,
+    pub colour: String,
+    pub pilot: String,
+}
+
+/// Free text in the FPL character set: upper case letters, digits and single spaces
+fn fpl_text(text: &str) -> String {
+    normalize(text).to_uppercase().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn hhmm(duration: Duration) -> String {
+    let minutes = (duration.num_seconds() as f64 / 60.0).round() as i64;
+    format!("{:02}{:02}", minutes / 60, minutes % 60)
+}
+
+/// Location indicator of an aerodrome, or ZZZZ and the text to file in item 18
+fn aerodrome(point: &NavPoint) -> (String, Option<String>) {
+    if let WaypointKind::airport(code) = &point.kind {
+        if code.len() == 4 && code.chars().all(|c| c.is_ascii_alphabetic()) {
+            return (code.to_uppercase(), None);
+        }
+    }
+    let position = coordinates::format(point.latitude, point.longitude, CoordinateFormat::icao);
+    ("ZZZZ".to_string(), Some(format!("{} {}", fpl_text(&point.name), position)))
+}
+
+/// Route element of a waypoint: navaid ident, bearing/distance or coordinates
+fn route_point(point: &NavPoint) -> String {
+    match &point.kind {
+        WaypointKind::navaid(ident) => ident.to_uppercase(),
+        WaypointKind::radial_distance(radial) if radial.magnetic_variation.is_some() => radial.icao(),
+        _ => coordinates::format(point.latitude, point.longitude, CoordinateFormat::icao),
+    }
+}
+
+/// Item 15 level: A and hundreds of ft below the transition altitude, else F and the flight level
+fn level(altitude: f64) -> String {
+    if altitude < levels::TRANSITION_ALTITUDE {
+        format!("A{:03.0}", altitude / 100.0)
+    } else {
+        format!("F{:03.0}", altitude / 100.0)
+    }
+}
+
+fn is_code(text: &str, letters: usize, digits: usize) -> bool {
+    text.is_ascii()
+        && text.len() == letters + digits
+        && text[..letters].chars().all(|c| c.is_ascii_uppercase())
+        && text[letters..].chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_coordinates(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let check = |lat_digits: usize, lon_digits: usize| {
+        text.len() == lat_digits + lon_digits + 2
+            && bytes[..lat_digits].iter().all(u8::is_ascii_digit)
+            && matches!(bytes[lat_digits], b'N' | b'S')
+            && bytes[lat_digits + 1..lat_digits + 1 + lon_digits].iter().all(u8::is_ascii_digit)
+            && matches!(bytes[text.len() - 1], b'E' | b'W')
+    };
+    check(2, 3) || check(4, 5)
+}
+
+fn is_significant_point(text: &str) -> bool {
+    (2..=5).contains(&text.len()) && text.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+fn is_bearing_distance(text: &str) -> bool {
+    text.is_ascii() && text.len() > 6 && is_significant_point(&text[..text.len() - 6]) && text[text.len() - 6..].chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_speed(text: &str) -> bool {
+    is_code(text, 1, 4) && matches!(&text[..1], "N" | "K") || is_code(text, 1, 3) && &text[..1] == "M"
+}
+
+fn is_level(text: &str) -> bool {
+    is_code(text, 1, 3) && matches!(&text[..1], "A" | "F")
+}
+
+/// Splits codes of item 10 such as "SDE2FGJ1" into "S", "D", "E2", "F", "G", "J1"
+fn split_codes(text: &str) -> Option<Vec<String>> {
+    let mut codes: Vec<String> = Vec::new();
+    for c in text.chars() {
+        if c.is_ascii_uppercase() {
+            codes.push(c.to_string());
+        } else if c.is_ascii_digit() {
+            codes.last_mut()?.push(c);
+        } else {
+            return None;
+        }
+    }
+    Some(codes)
+}
+
+fn is_equipment(text: &str) -> bool {
+    if text == "N" {
+        return true;
+    }
+    split_codes(text).is_some_and(|codes| {
+        !codes.is_empty()
+            && codes.iter().all(|code| match code.as_str() {
+                "E1" | "E2" | "E3" | "M1" | "M2" | "M3" => true,
+                code if code.starts_with('J') => matches!(code, "J1" | "J2" | "J3" | "J4" | "J5" | "J6" | "J7"),
+                code if code.starts_with('P') => code.len() == 2 && code[1..] != *"0",
+                code => code.len() == 1 && "SABCDFGHIKLORTUVWXYZ".contains(code),
+            })
+    })
+}
+
+fn is_surveillance(text: &str) -> bool {
+    if text == "N" {
+        return true;
+    }
+    split_codes(text).is_some_and(|codes| {
+        !codes.is_empty()
+            && codes.iter().all(|code| {
+                matches!(code.as_str(), "A" | "C" | "E" | "H" | "I" | "L" | "P" | "S" | "X" | "B1" | "B2" | "U1" | "U2" | "V1" | "V2" | "D1" | "G1")
+            })
+    })
+}
+
+fn is_fpl_text(text: &str) -> bool {
+    text.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == ' ' || c == '/' || c == '.')
+}
+
+impl FlightPlanMessage {
+    /// Fills the flight plan of a navigation: the departure and destination are its first and last points,
+    /// the EOBT its off-block time, the total EET its flight time and the endurance that of the fuel on board.
+    /// Aerodromes without ICAO code are filed as ZZZZ with DEP/, DEST/ or ALTN/ in item 18.
+    pub fn new(navigation: &Navigation, aircraft: &Aircraft, pilot: &Pilot, rules: FlightRules, persons_on_board: u32, alternates: &[NavPoint]) -> Result<FlightPlanMessage, FplError> {
+        let eobt = navigation.off_block.ok_or(FplError::missing_eobt)?;
+        let total_eet = navigation.flight_time().ok_or(FplError::missing_eet)?;
+        let mut other_information = vec![format!("DOF/{}", eobt.format("%y%m%d"))];
+
+        let data = &aircraft.flight_plan;
+        if data.type_designator == "ZZZZ" {
+            other_information.push(format!("TYP/{}", fpl_text(&aircraft.aircraft_type)));
+        }
+
+        let (departure, departure_text) = aerodrome(&navigation.start);
+        let (destination, destination_text) = aerodrome(&navigation.end);
+        let alternates: Vec<(String, Option<String>)> = alternates.iter().map(aerodrome).collect();
+        if let Some(text) = departure_text {
+            other_information.push(format!("DEP/{}", text));
+        }
+        if let Some(text) = destination_text {
+            other_information.push(format!("DEST/{}", text));
+        }
+        let alternate_texts: Vec<String> = alternates.iter().filter_map(|(_, text)| text.clone()).collect();
+        if !alternate_texts.is_empty() {
+            other_information.push(format!("ALTN/{}", alternate_texts.join(" ")));
+        }
+        if !pilot.phone.trim().is_empty() {
+            other_information.push(format!("RMK/PILOT TEL {}", fpl_text(&pilot.phone)));
+        }
+
+        // Route, with the speed and level changes at the points where the planned altitude changes
+        let speed = format!("N{:04.0}", aircraft.cruise_speed);
+        let mut altitude = navigation.branches.first().and_then(|b| b.altitude);
+        let cruising_level = match (altitude, rules) {
+            (Some(altitude), _) => level(altitude),
+            (None, FlightRules::vfr) => "VFR".to_string(),
+            (None, FlightRules::ifr) => String::new(),
+        };
+        let mut route = vec!["DCT".to_string()];
+        for (index, branch) in navigation.branches.iter().enumerate().skip(1) {
+            let Some(from) = &branch.from else {
+                continue;
+            };
+            let mut element = route_point(from);
+            if let Some(new_altitude) = branch.altitude.filter(|a| Some(*a) != altitude) {
+                element.push_str(&format!("/{}{}", speed, level(new_altitude)));
+                altitude = Some(new_altitude);
+            }
+            route.push(element);
+            if index + 1 < navigation.branches.len() {
+                route.push("DCT".to_string());
+            }
+        }
+
+        Ok(FlightPlanMessage {
+            aircraft_identification: aircraft.immatriculation.replace('-', "").to_uppercase(),
+            flight_rules: match rules {
+                FlightRules::vfr => 'V',
+                FlightRules::ifr => 'I',
+            },
+            flight_type: 'G',
+            aircraft_type: data.type_designator.to_uppercase(),
+            wake_category: data.wake_category.to_ascii_uppercase(),
+            equipment: data.equipment.to_uppercase(),
+            surveillance: data.surveillance.to_uppercase(),
+            departure,
+            eobt,
+            cruising_speed: speed,
+            cruising_level,
+            route: route.join(" "),
+            destination,
+            total_eet,
+            alternates: alternates.into_iter().map(|(code, _)| code).collect(),
+            other_information,
+            endurance: aircraft.endurance(),
+            persons_on_board,
+            colour: fpl_text(&data.colour),
+            pilot: fpl_text(&pilot.name),
+        })
+    }
+
+    /// Checks every field against the ICAO format rules, returning all the errors found
+    pub fn validate(&self) -> Vec<FplError> {
+        let mut errors = Vec::new();
+        let id = &self.aircraft_identification;
+        if !(2..=7).contains(&id.len()) || !id.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()) {
+            errors.push(FplError::invalid_identification(id.clone()));
+        }
+        if !matches!(self.flight_rules, 'V' | 'I' | 'Y' | 'Z') {
+            errors.push(FplError::invalid_flight_rules(self.flight_rules));
+        }
+        let designator = &self.aircraft_type;
+        if !(2..=4).contains(&designator.len())
+            || !designator.chars().next().is_some_and(|c| c.is_ascii_uppercase())
+            || !designator.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        {
+            errors.push(FplError::invalid_type_designator(designator.clone()));
+        }
+        if !matches!(self.wake_category, 'L' | 'M' | 'H' | 'J') {
+            errors.push(FplError::invalid_wake_category(self.wake_category));
+        }
+        if !is_equipment(&self.equipment) {
+            errors.push(FplError::invalid_equipment(self.equipment.clone()));
+        }
+        if !is_surveillance(&self.surveillance) {
+            errors.push(FplError::invalid_surveillance(self.surveillance.clone()));
+        }
+        if !is_code(&self.departure, 4, 0) {
+            errors.push(FplError::invalid_aerodrome(13, self.departure.clone()));
+        }
+        if !is_speed(&self.cruising_speed) {
+            errors.push(FplError::invalid_speed(self.cruising_speed.clone()));
+        }
+        if !(is_level(&self.cruising_level) || self.cruising_level == "VFR" && self.flight_rules == 'V') {
+            errors.push(FplError::invalid_level(self.cruising_level.clone()));
+        }
+        for element in self.route.split_whitespace() {
+            let (point, change) = match element.split_once('/') {
+                Some((point, change)) => (point, Some(change)),
+                None => (element, None),
+            };
+            let point_ok = point == "DCT" && change.is_none() || is_significant_point(point) || is_coordinates(point) || is_bearing_distance(point);
+            // Speed and level, e.g. N0100A035, or speed and VFR, e.g. N0100VFR
+            let change_ok = change.is_none_or(|c| {
+                c.strip_suffix("VFR").is_some_and(is_speed)
+                    || c.is_ascii() && c.len() > 4 && is_speed(&c[..c.len() - 4]) && is_level(&c[c.len() - 4..])
+            });
+            if !point_ok || !change_ok {
+                errors.push(FplError::invalid_route_element(element.to_string()));
+            }
+        }
+        if !is_code(&self.destination, 4, 0) {
+            errors.push(FplError::invalid_aerodrome(16, self.destination.clone()));
+        }
+        if self.alternates.len() > 2 {
+            errors.push(FplError::too_many_alternates(self.alternates.len()));
+        }
+        for alternate in self.alternates.iter().filter(|a| !is_code(a, 4, 0)) {
+            errors.push(FplError::invalid_aerodrome(16, alternate.clone()));
+        }
+        for information in self.other_information.iter().filter(|i| !is_fpl_text(i) || !i.contains('/')) {
+            errors.push(FplError::invalid_characters(18, information.clone()));
+        }
+        if !(1..=999).contains(&self.persons_on_board) {
+            errors.push(FplError::invalid_persons_on_board(self.persons_on_board));
+        }
+        if self.endurance < self.total_eet {
+            errors.push(FplError::insufficient_endurance(hhmm(self.endurance), hhmm(self.total_eet)));
+        }
+        for text in [&self.colour, &self.pilot] {
+            if !text.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == ' ') {
+                errors.push(FplError::invalid_characters(19, text.clone()));
+            }
+        }
+        errors
+    }
+}
+
+/// The message as sent to the ATS reporting office, one item per line
+impl fmt::Display for FlightPlanMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "(FPL-{}-{}{}", self.aircraft_identification, self.flight_rules, self.flight_type)?;
+        writeln!(f, "-{}/{}-{}/{}", self.aircraft_type, self.wake_category, self.equipment, self.surveillance)?;
+        writeln!(f, "-{}{}", self.departure, self.eobt.format("%H%M"))?;
+        writeln!(f, "-{}{} {}", self.cruising_speed, self.cruising_level, self.route)?;
+        let mut destination = format!("-{}{}", self.destination, hhmm(self.total_eet));
+        for alternate in &self.alternates {
+            destination.push(' ');
+            destination.push_str(alternate);
+        }
+        writeln!(f, "{}", destination)?;
+        let other = if self.other_information.is_empty() { "0".to_string() } else { self.other_information.join(" ") };
+        writeln!(f, "-{}", other)?;
+        let mut supplementary = vec![format!("E/{}", hhmm(self.endurance)), format!("P/{:03}", self.persons_on_board)];
+        if !self.colour.is_empty() {
+            supplementary.push(format!("A/{}", self.colour));
+        }
+        if !self.pilot.is_empty() {
+            supplementary.push(format!("C/{}", self.pilot));
+        }
+        write!(f, "-{})", supplementary.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navigation::aircraft::{BalanceCat, BalanceChart, BalanceElement, BalanceSheet, FlightPlanData, Performance};
+    use crate::navigation::flightplan::{NavBranch, Wind};
+    use crate::navigation::fuel::Fuel;
+    use crate::navigation::geographics::EarthModel;
+    use crate::navigation::route::resolve_waypoint;
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+
+    fn message() -> FlightPlanMessage {
+        FlightPlanMessage {
+            aircraft_identification: "FGJKL".to_string(),
+            flight_rules: 'V',
+            flight_type: 'G',
+            aircraft_type: "DR40".to_string(),
+            wake_category: 'L',
+            equipment: "SDFGY".to_string(),
+            surveillance: "S".to_string(),
+            departure: "LFPN".to_string(),
+            eobt: Utc.with_ymd_and_hms(2026, 10, 19, 8, 30, 0).unwrap(),
+            cruising_speed: "N0110".to_string(),
+            cruising_level: "VFR".to_string(),
+            route: "DCT PTS/N0110A035 DCT 4930N00205E/N0100VFR".to_string(),
+            destination: "LFOB".to_string(),
+            total_eet: Duration::minutes(42),
+            alternates: vec!["LFPT".to_string()],
+            other_information: vec!["DOF/261019".to_string()],
+            endurance: Duration::minutes(270),
+            persons_on_board: 2,
+            colour: "WHITE BLUE".to_string(),
+            pilot: "DUPONT".to_string(),
+        }
+    }
+
+    #[test]
+    fn display() {
+        let expected = "(FPL-FGJKL-VG\n\
+                        -DR40/L-SDFGY/S\n\
+                        -LFPN0830\n\
+                        -N0110VFR DCT PTS/N0110A035 DCT 4930N00205E/N0100VFR\n\
+                        -LFOB0042 LFPT\n\
+                        -DOF/261019\n\
+                        -E/0430 P/002 A/WHITE BLUE C/DUPONT)";
+        assert_eq!(message().to_string(), expected);
+        assert!(message().validate().is_empty());
+    }
+
+    #[test]
+    fn rejects_non_ascii_fields() {
+        let mut message = message();
+        message.cruising_speed = "Né110".to_string();
+        message.departure = "LFPÉ".to_string();
+        message.route = "DCT PTSé45012 DCT PTS/Né110VFR".to_string();
+        assert_eq!(message.validate().len(), 4);
+    }
+
+    /// DR400 at 100 kt burning 25 L/h, with 80 L on board
+    fn aircraft() -> Aircraft {
+        let chart = BalanceChart { catN: BalanceCat::new(), catU: BalanceCat::new(), catA: BalanceCat::new() };
+        let loading = BalanceSheet { elements: HashMap::from([("tank".to_string(), BalanceElement { arm: 1.12, weight: 0.0 })]) };
+        let flight_plan = FlightPlanData {
+            type_designator: "DR40".to_string(),
+            wake_category: 'L',
+            equipment: "SDFGY".to_string(),
+            surveillance: "S".to_string(),
+            colour: "White, blue".to_string(),
+        };
+        let mut aircraft = Aircraft::new("F-GJKL".to_string(), "DR400".to_string(), 120, 100.0, Fuel::AVGAS100LL, 25.0, 1, 110, 588.0, chart, loading, Performance::new(), None, flight_plan);
+        aircraft.load_fuel(80.0).unwrap();
+        aircraft
+    }
+
+    #[test]
+    fn message_from_a_navigation() {
+        let db_path = "data/airports.db";
+        let resolve = |name: &str| resolve_waypoint(db_path, name, None).unwrap().unwrap();
+        let mut field = NavPoint::new("Champ de Marcel".to_string(), 49.2, 1.95);
+        field.kind = WaypointKind::coordinates;
+        let calm = Wind { speed: 0.0, direction: 0.0 };
+        // Level change at the second point, none at the third
+        let route = [resolve("LFPN"), resolve("4900N00200E"), resolve("LFPT245010"), field.clone()];
+        let altitudes = [Some(2500.0), Some(3500.0), None];
+        let mut branches: Vec<NavBranch> = route
+            .windows(2)
+            .zip(altitudes)
+            .map(|(leg, altitude)| NavBranch::between(leg[0].clone(), leg[1].clone(), calm, altitude, EarthModel::spherical))
+            .collect();
+        branches.iter_mut().for_each(|branch| branch.estimate_time(100.0));
+        let mut navigation = Navigation::new(route[0].clone(), field.clone(), branches);
+        navigation.off_block = Some(Utc.with_ymd_and_hms(2026, 10, 19, 8, 30, 0).unwrap());
+
+        let pilot = Pilot { name: "Jean Dupont".to_string(), phone: "+33 6 12 34 56 78".to_string() };
+        let alternates = [resolve("LFOB"), field];
+        let message = FlightPlanMessage::new(&navigation, &aircraft(), &pilot, FlightRules::vfr, 2, &alternates).unwrap();
+
+        assert_eq!(message.aircraft_identification, "FGJKL");
+        assert_eq!(message.departure, "LFPN");
+        assert_eq!(message.destination, "ZZZZ");
+        assert_eq!(message.alternates, vec!["LFOB", "ZZZZ"]);
+        assert_eq!(message.cruising_speed, "N0100");
+        assert_eq!(message.cruising_level, "A025");
+        // Bearings from an airport have no published variation, the point is filed as coordinates
+        let radial = navigation.branches[2].from.as_ref().unwrap();
+        let radial = coordinates::format(radial.latitude, radial.longitude, CoordinateFormat::icao);
+        assert_eq!(message.route, format!("DCT 4900N00200E/N0100A035 DCT {}", radial));
+        assert_eq!(
+            message.other_information,
+            vec![
+                "DOF/261019".to_string(),
+                "DEST/CHAMP DE MARCEL 4912N00157E".to_string(),
+                "ALTN/CHAMP DE MARCEL 4912N00157E".to_string(),
+                "RMK/PILOT TEL 33 6 12 34 56 78".to_string(),
+            ]
+        );
+        assert_eq!(message.endurance, Duration::minutes(192));
+        assert_eq!(message.total_eet, navigation.flight_time().unwrap());
+        assert_eq!(message.colour, "WHITE BLUE");
+        assert_eq!(message.pilot, "JEAN DUPONT");
+        assert!(message.validate().is_empty(), "{:?}", message.validate());
+    }
+
+    #[test]
+    fn message_needs_an_off_block_time() {
+        let db_path = "data/airports.db";
+        let from = resolve_waypoint(db_path, "LFPN", None).unwrap().unwrap();
+        let to = resolve_waypoint(db_path, "LFOB", None).unwrap().unwrap();
+        let navigation = Navigation::new(from.clone(), to.clone(), vec![NavBranch::between(from, to, Wind { speed: 0.0, direction: 0.0 }, None, EarthModel::spherical)]);
+        let pilot = Pilot { name: String::new(), phone: String::new() };
+        assert!(matches!(FlightPlanMessage::new(&navigation, &aircraft(), &pilot, FlightRules::vfr, 1, &[]), Err(FplError::missing_eobt)));
+    }
+}
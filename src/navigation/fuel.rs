@@ -3,7 +3,7 @@ pub struct FuelProperties {
     pub density: f64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Fuel {
     AVGAS100LL,
     AVGAS100,
@@ -16,6 +16,21 @@ pub enum Fuel {
 }
 
 impl Fuel {
+    /// Reads a fuel as stored in the database, e.g. `AVGAS100LL` or `JET_A1`
+    pub fn from_db_type(fuel: &str) -> Option<Fuel> {
+        match fuel.trim().to_uppercase().as_str() {
+            "AVGAS100LL" => Some(Fuel::AVGAS100LL),
+            "AVGAS100" => Some(Fuel::AVGAS100),
+            "AVGAS82" => Some(Fuel::AVGAS82),
+            "AVGAS80" => Some(Fuel::AVGAS80),
+            "JET_A" => Some(Fuel::JET_A),
+            "JET_A1" => Some(Fuel::JET_A1),
+            "JET_B" => Some(Fuel::JET_B),
+            "MOGAS" => Some(Fuel::MOGAS),
+            _ => None,
+        }
+    }
+
     pub fn properties(&self) -> FuelProperties {
         match self {
             Fuel::AVGAS100LL => FuelProperties {
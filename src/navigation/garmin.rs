@@ -0,0 +1,416 @@
+use std::fs;
+
+use chrono::Utc;
+use roxmltree::{Document, Node};
+
+use super::error::RouteError;
+use super::flightplan::{NavBranch, Navigation, Wind};
+use super::geographics::{calculate_distance_and_course, EarthModel, LegType, NavPoint};
+use super::gpx::escape_xml;
+use super::navaid::{Navaid, NavaidType};
+use super::route::resolve_waypoint;
+use super::search::normalize;
+use super::waypoint::WaypointKind;
+
+/// Points of an imported plan farther than this from the database entry they name keep their own position, km
+const MATCH_RADIUS: f64 = 0.5;
+
+/// Namespace of the `leg` element added to the extensions of route points, keeping what the Garmin format has no room for
+const LEG_NAMESPACE: &str = "urn:flight-planner:fpl-leg:1";
+
+/// A waypoint of the Garmin FlightPlan v1 `waypoint-table`
+#[derive(Debug, Clone)]
+struct GarminWaypoint {
+    identifier: String,
+    waypoint_type: &'static str, // AIRPORT, VOR, NDB, INT or USER WAYPOINT
+    country_code: String,        // ICAO region, e.g. "LF"
+    latitude: f64,
+    longitude: f64,
+    comment: String, // name of the point in the navigation, used to find it back on import
+}
+
+/// Planning data of the leg ending at a route point, written in a `leg` element of `LEG_NAMESPACE`
+#[derive(Debug, Clone, Copy)]
+struct GarminLeg {
+    altitude: Option<f64>, // ft
+    wind: Wind,
+    earth_model: EarthModel,
+    leg_type: LegType,
+}
+
+impl GarminLeg {
+    fn new(branch: &NavBranch) -> GarminLeg {
+        GarminLeg {
+            altitude: branch.altitude,
+            wind: branch.wind,
+            earth_model: branch.earth_model,
+            leg_type: branch.leg_type,
+        }
+    }
+
+    fn to_xml(self) -> String {
+        let altitude = self.altitude.map_or(String::new(), |altitude| format!(" altitude=\"{}\"", altitude));
+        let earth_model = match self.earth_model {
+            EarthModel::spherical => "spherical",
+            EarthModel::wgs84 => "wgs84",
+        };
+        let leg_type = match self.leg_type {
+            LegType::great_circle => "great_circle",
+            LegType::rhumb_line => "rhumb_line",
+        };
+        format!(
+            "<leg xmlns=\"{}\"{} wind-direction=\"{}\" wind-speed=\"{}\" earth-model=\"{}\" leg-type=\"{}\"/>",
+            LEG_NAMESPACE, altitude, self.wind.direction, self.wind.speed, earth_model, leg_type
+        )
+    }
+
+    /// Reads the `leg` element in the extensions of a route point, unknown values falling back to `wind` and the defaults
+    fn read(route_point: Node, wind: Wind) -> Option<GarminLeg> {
+        let node = route_point
+            .children()
+            .filter(|child| child.has_tag_name("extensions"))
+            .flat_map(|extensions| extensions.children())
+            .find(|child| child.has_tag_name((LEG_NAMESPACE, "leg")))?;
+        let number = |name: &str| node.attribute(name).and_then(|value| value.parse::<f64>().ok());
+        Some(GarminLeg {
+            altitude: number("altitude"),
+            wind: match (number("wind-direction"), number("wind-speed")) {
+                (Some(direction), Some(speed)) => Wind { speed, direction },
+                _ => wind,
+            },
+            earth_model: match node.attribute("earth-model") {
+                Some("wgs84") => EarthModel::wgs84,
+                _ => EarthModel::spherical,
+            },
+            leg_type: match node.attribute("leg-type") {
+                Some("rhumb_line") => LegType::rhumb_line,
+                _ => LegType::great_circle,
+            },
+        })
+    }
+}
+
+/// Garmin identifiers: upper case letters and digits, 6 at most for user waypoints
+fn garmin_identifier(name: &str) -> String {
+    name.to_uppercase().chars().filter(|c| c.is_ascii_alphanumeric()).take(6).collect()
+}
+
+/// Garmin comments: upper case letters, digits, spaces, hyphens and slashes, 25 characters at most
+fn garmin_comment(text: &str) -> String {
+    let comment: String = normalize(text)
+        .to_uppercase()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, ' ' | '-' | '/'))
+        .collect();
+    comment.split_whitespace().collect::<Vec<_>>().join(" ").chars().take(25).collect::<String>().trim_end().to_string()
+}
+
+/// Maps a navigation point to its Garmin waypoint. Navaids are looked up in the database to tell NDBs from VORs;
+/// coordinates and bearing/distance points become user waypoints.
+fn garmin_waypoint(db_path: &str, point: &NavPoint) -> Result<GarminWaypoint, rusqlite::Error> {
+    let (identifier, waypoint_type, country_code) = match &point.kind {
+        WaypointKind::airport(code) => {
+            let region = if code.len() == 4 && code.chars().all(|c| c.is_ascii_alphabetic()) { code[..2].to_uppercase() } else { String::new() };
+            (code.to_uppercase(), "AIRPORT", region)
+        }
+        WaypointKind::navaid(ident) => {
+            let navaid_type = Navaid::nearest(db_path, ident, point)?.map(|navaid| navaid.navaid_type);
+            let waypoint_type = match navaid_type {
+                Some(NavaidType::ndb | NavaidType::ndb_dme) => "NDB",
+                _ => "VOR",
+            };
+            (ident.to_uppercase(), waypoint_type, String::new())
+        }
+        _ => (garmin_identifier(&point.name), "USER WAYPOINT", String::new()),
+    };
+    Ok(GarminWaypoint {
+        identifier,
+        waypoint_type,
+        country_code,
+        latitude: point.latitude,
+        longitude: point.longitude,
+        comment: garmin_comment(&match &point.kind {
+            WaypointKind::radial_distance(radial) => radial.icao(),
+            _ => point.name.clone(),
+        }),
+    })
+}
+
+/// Renders a navigation as a Garmin FlightPlan v1 document (`.fpl`), read by Garmin units, Garmin Pilot and ForeFlight.
+/// User waypoints sharing an identifier at different positions are told apart by a numbered suffix.
+/// The altitude, wind, Earth model and leg type of each leg go in a `leg` element in the extensions of the route
+/// point ending it, in a namespace of their own so that `import_garmin_fpl` restores them.
+pub fn to_garmin_fpl(db_path: &str, navigation: &Navigation) -> Result<String, rusqlite::Error> {
+    let mut points = vec![(&navigation.start, None)];
+    points.extend(navigation.branches.iter().filter_map(|branch| Some((branch.to.as_ref()?, Some(GarminLeg::new(branch))))));
+
+    let mut table: Vec<GarminWaypoint> = Vec::new();
+    let mut route: Vec<(usize, Option<GarminLeg>)> = Vec::new();
+    for (point, leg) in points {
+        let mut waypoint = garmin_waypoint(db_path, point)?;
+        let same = |w: &GarminWaypoint| w.waypoint_type == waypoint.waypoint_type && w.comment == waypoint.comment && w.latitude == waypoint.latitude && w.longitude == waypoint.longitude;
+        if let Some(index) = table.iter().position(same) {
+            route.push((index, leg));
+            continue;
+        }
+        if waypoint.waypoint_type == "USER WAYPOINT" {
+            let base = if waypoint.identifier.is_empty() { "WPT".to_string() } else { waypoint.identifier.clone() };
+            let mut suffix = 1;
+            while waypoint.identifier.is_empty() || table.iter().any(|w| w.identifier == waypoint.identifier && w.waypoint_type == waypoint.waypoint_type) {
+                let number = suffix.to_string();
+                waypoint.identifier = format!("{}{}", &base[..base.len().min(6 - number.len())], number);
+                suffix += 1;
+            }
+        }
+        route.push((table.len(), leg));
+        table.push(waypoint);
+    }
+
+    let route_name: String = format!("{} {}", navigation.start.name, navigation.end.name)
+        .to_uppercase()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == ' ')
+        .take(25)
+        .collect();
+    let mut fpl = String::new();
+    fpl.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    fpl.push_str("<flight-plan xmlns=\"http://www8.garmin.com/xmlschemas/FlightPlan/v1\">\n");
+    fpl.push_str(&format!("  <created>{}</created>\n", Utc::now().format("%Y-%m-%dT%H:%M:%SZ")));
+    fpl.push_str("  <waypoint-table>\n");
+    for waypoint in &table {
+        fpl.push_str("    <waypoint>\n");
+        fpl.push_str(&format!("      <identifier>{}</identifier>\n", escape_xml(&waypoint.identifier)));
+        fpl.push_str(&format!("      <type>{}</type>\n", waypoint.waypoint_type));
+        fpl.push_str(&format!("      <country-code>{}</country-code>\n", escape_xml(&waypoint.country_code)));
+        fpl.push_str(&format!("      <lat>{:.6}</lat>\n", waypoint.latitude));
+        fpl.push_str(&format!("      <lon>{:.6}</lon>\n", waypoint.longitude));
+        fpl.push_str(&format!("      <comment>{}</comment>\n", escape_xml(&waypoint.comment)));
+        fpl.push_str("    </waypoint>\n");
+    }
+    fpl.push_str("  </waypoint-table>\n");
+    fpl.push_str("  <route>\n");
+    fpl.push_str(&format!("    <route-name>{}</route-name>\n", escape_xml(route_name.trim())));
+    fpl.push_str("    <flight-plan-index>1</flight-plan-index>\n");
+    for (index, leg) in route {
+        let waypoint = &table[index];
+        fpl.push_str("    <route-point>\n");
+        fpl.push_str(&format!("      <waypoint-identifier>{}</waypoint-identifier>\n", escape_xml(&waypoint.identifier)));
+        fpl.push_str(&format!("      <waypoint-type>{}</waypoint-type>\n", waypoint.waypoint_type));
+        fpl.push_str(&format!("      <waypoint-country-code>{}</waypoint-country-code>\n", escape_xml(&waypoint.country_code)));
+        if let Some(leg) = leg {
+            fpl.push_str("      <extensions>\n");
+            fpl.push_str(&format!("        {}\n", leg.to_xml()));
+            fpl.push_str("      </extensions>\n");
+        }
+        fpl.push_str("    </route-point>\n");
+    }
+    fpl.push_str("  </route>\n</flight-plan>\n");
+    Ok(fpl)
+}
+
+/// Writes a navigation to a Garmin `.fpl` file, see `to_garmin_fpl`
+pub fn export_garmin_fpl(db_path: &str, navigation: &Navigation, path: &str) -> Result<(), RouteError> {
+    fs::write(path, to_garmin_fpl(db_path, navigation)?)?;
+    Ok(())
+}
+
+/// Text of the first child element named `name`
+fn child_text<'a>(node: Node<'a, 'a>, name: &str) -> &'a str {
+    node.children().find(|child| child.has_tag_name(name)).and_then(|child| child.text()).map_or("", str::trim)
+}
+
+/// Garmin waypoint types this module tells apart, the others being read as user waypoints
+fn garmin_type(waypoint_type: &str) -> &'static str {
+    match waypoint_type {
+        "AIRPORT" => "AIRPORT",
+        "VOR" => "VOR",
+        "NDB" => "NDB",
+        "INT" | "INT-VRP" => "INT",
+        _ => "USER WAYPOINT",
+    }
+}
+
+fn read_waypoint(node: Node) -> Option<GarminWaypoint> {
+    Some(GarminWaypoint {
+        identifier: child_text(node, "identifier").to_string(),
+        waypoint_type: garmin_type(child_text(node, "type")),
+        country_code: child_text(node, "country-code").to_string(),
+        latitude: child_text(node, "lat").parse().ok()?,
+        longitude: child_text(node, "lon").parse().ok()?,
+        comment: child_text(node, "comment").to_string(),
+    })
+}
+
+/// Finds back the navigation point of a Garmin waypoint: the airport, navaid or user waypoint of the database
+/// it names, or the coordinates or bearing/distance point of its comment, when they lie where the file puts it.
+/// Other points keep the position of the file, as an airport, a navaid or coordinates after their Garmin type.
+fn resolve_garmin_waypoint(db_path: &str, waypoint: &GarminWaypoint, previous: Option<&NavPoint>) -> Result<NavPoint, rusqlite::Error> {
+    let position = NavPoint::new(waypoint.identifier.clone(), waypoint.latitude, waypoint.longitude);
+    let names = match waypoint.waypoint_type {
+        "USER WAYPOINT" => vec![&waypoint.comment, &waypoint.identifier],
+        _ => vec![&waypoint.identifier],
+    };
+    for name in names.into_iter().filter(|name| !name.is_empty()) {
+        if let Some(point) = resolve_waypoint(db_path, name, previous.or(Some(&position)))? {
+            if calculate_distance_and_course(&position, &point).0.is_some_and(|distance| distance <= MATCH_RADIUS) {
+                return Ok(point);
+            }
+        }
+    }
+
+    let name = if waypoint.comment.is_empty() { waypoint.identifier.clone() } else { waypoint.comment.clone() };
+    let mut point = NavPoint::new(name, waypoint.latitude, waypoint.longitude);
+    point.kind = match waypoint.waypoint_type {
+        "AIRPORT" => WaypointKind::airport(waypoint.identifier.clone()),
+        "VOR" | "NDB" => WaypointKind::navaid(waypoint.identifier.clone()),
+        _ => WaypointKind::coordinates,
+    };
+    Ok(point)
+}
+
+/// Reads the route of a Garmin `.fpl` file, its points matched to the database as described in `resolve_garmin_waypoint`
+pub fn read_garmin_fpl(db_path: &str, path: &str) -> Result<Vec<NavPoint>, RouteError> {
+    let route = read_garmin_route(db_path, path, Wind { speed: 0.0, direction: 0.0 })?;
+    Ok(route.into_iter().map(|(point, _)| point).collect())
+}
+
+/// Route points of a Garmin `.fpl` file with the data of the leg ending at each, see `GarminLeg::read`
+fn read_garmin_route(db_path: &str, path: &str, wind: Wind) -> Result<Vec<(NavPoint, Option<GarminLeg>)>, RouteError> {
+    let text = fs::read_to_string(path)?;
+    let document = Document::parse(&text)?;
+    let root = document.root_element();
+    let table: Vec<GarminWaypoint> = root
+        .children()
+        .filter(|child| child.has_tag_name("waypoint-table"))
+        .flat_map(|table| table.children().filter(|child| child.has_tag_name("waypoint")))
+        .filter_map(read_waypoint)
+        .collect();
+
+    let mut points: Vec<(NavPoint, Option<GarminLeg>)> = Vec::new();
+    let Some(route) = root.children().find(|child| child.has_tag_name("route")) else {
+        return Ok(points);
+    };
+    for route_point in route.children().filter(|child| child.has_tag_name("route-point")) {
+        let identifier = child_text(route_point, "waypoint-identifier");
+        let waypoint_type = child_text(route_point, "waypoint-type");
+        let country_code = child_text(route_point, "waypoint-country-code");
+        let waypoint = table
+            .iter()
+            .find(|w| w.identifier == identifier && garmin_type(waypoint_type) == w.waypoint_type && w.country_code == country_code)
+            .or_else(|| table.iter().find(|w| w.identifier == identifier))
+            .ok_or_else(|| RouteError::unknown_waypoint(identifier.to_string()))?;
+        let point = resolve_garmin_waypoint(db_path, waypoint, points.last().map(|(point, _)| point))?;
+        points.push((point, GarminLeg::read(route_point, wind)));
+    }
+    Ok(points)
+}
+
+/// Imports a Garmin `.fpl` flight plan as a navigation with one leg between each pair of route points.
+/// Legs written by `to_garmin_fpl` get back their altitude, wind, Earth model and leg type;
+/// those of other software have no altitude and fly `wind` along great circles on the spherical model.
+pub fn import_garmin_fpl(db_path: &str, path: &str, wind: Wind) -> Result<Navigation, RouteError> {
+    let points = read_garmin_route(db_path, path, wind)?;
+    if points.len() < 2 {
+        return Err(RouteError::empty_route(path.to_string()));
+    }
+    let branches = points
+        .windows(2)
+        .map(|leg| {
+            let (from, (to, data)) = (&leg[0].0, &leg[1]);
+            let data = data.unwrap_or(GarminLeg { altitude: None, wind, earth_model: EarthModel::spherical, leg_type: LegType::great_circle });
+            let mut branch = NavBranch::between(from.clone(), to.clone(), data.wind, data.altitude, data.earth_model);
+            if data.leg_type != LegType::great_circle {
+                branch.set_leg_type(data.leg_type);
+            }
+            branch
+        })
+        .collect();
+    Ok(Navigation::new(points[0].0.clone(), points[points.len() - 1].0.clone(), branches))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navigation::user_waypoint::{UserWaypoint, UserWaypointType};
+
+    #[test]
+    fn legs_survive_export_and_import() {
+        let db_path = "data/airports.db";
+        let points: Vec<NavPoint> = ["LFPN", "LFPT", "LFOB"]
+            .iter()
+            .map(|name| resolve_waypoint(db_path, name, None).unwrap().unwrap())
+            .collect();
+        let mut first = NavBranch::between(points[0].clone(), points[1].clone(), Wind { speed: 15.0, direction: 270.0 }, Some(2500.0), EarthModel::wgs84);
+        first.set_leg_type(LegType::rhumb_line);
+        let second = NavBranch::between(points[1].clone(), points[2].clone(), Wind { speed: 8.0, direction: 320.0 }, Some(3500.0), EarthModel::spherical);
+        let navigation = Navigation::new(points[0].clone(), points[2].clone(), vec![first, second]);
+
+        let path = std::env::temp_dir().join("flight_planner_legs.fpl");
+        let path = path.to_str().unwrap();
+        export_garmin_fpl(db_path, &navigation, path).unwrap();
+        let imported = import_garmin_fpl(db_path, path, Wind { speed: 0.0, direction: 0.0 }).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(imported.branches.len(), 2);
+        for (branch, original) in imported.branches.iter().zip(&navigation.branches) {
+            assert_eq!(branch.to.as_ref().map(|p| p.name.clone()), original.to.as_ref().map(|p| p.name.clone()));
+            assert_eq!(branch.altitude, original.altitude);
+            assert_eq!((branch.wind.speed, branch.wind.direction), (original.wind.speed, original.wind.direction));
+            assert_eq!(branch.earth_model, original.earth_model);
+            assert_eq!(branch.leg_type, original.leg_type);
+            assert!((branch.distance.unwrap() - original.distance.unwrap()).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn comments_are_sanitised() {
+        assert_eq!(garmin_comment("Château <d'O> & lac"), "CHATEAU D O LAC");
+        assert_eq!(garmin_comment("PTS245012"), "PTS245012");
+        assert_eq!(garmin_comment("Very long name of a landmark by the river"), "VERY LONG NAME OF A LANDM");
+    }
+
+    #[test]
+    fn navaids_user_waypoints_and_coordinates_survive_export_and_import() {
+        let db_path = std::env::temp_dir().join("flight_planner_garmin_points.db");
+        let db_path = db_path.to_str().unwrap();
+        std::fs::copy("data/airports.db", db_path).unwrap();
+        rusqlite::Connection::open(db_path)
+            .unwrap()
+            .execute_batch(
+                "INSERT INTO navaids (id, ident, name, type, frequency_khz, latitude_deg, longitude_deg, magnetic_variation_deg)
+                VALUES ('1', 'PTS', 'PONTOISE', 'NDB', 339, 49.1, 2.04, 1.5);
+                DELETE FROM user_waypoints;",
+            )
+            .unwrap();
+        let mut nord = UserWaypoint::new("NORD".to_string(), 48.95, 2.3, UserWaypointType::reporting_point, None, String::new());
+        nord.insert(db_path).unwrap();
+
+        let points: Vec<NavPoint> = ["LFPN", "PTS", "NORD", "4900N00230E", "LFOB"]
+            .iter()
+            .map(|name| resolve_waypoint(db_path, name, None).unwrap().unwrap())
+            .collect();
+        let wind = Wind { speed: 0.0, direction: 0.0 };
+        let branches = points.windows(2).map(|p| NavBranch::between(p[0].clone(), p[1].clone(), wind, Some(2500.0), EarthModel::spherical)).collect();
+        let navigation = Navigation::new(points[0].clone(), points[4].clone(), branches);
+
+        let fpl = to_garmin_fpl(db_path, &navigation).unwrap();
+        assert!(fpl.contains("<type>NDB</type>"));
+        assert!(fpl.contains("<type>USER WAYPOINT</type>"));
+        assert!(fpl.contains("      <extensions>\n        <leg xmlns="));
+        let path = std::env::temp_dir().join("flight_planner_points.fpl");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, fpl).unwrap();
+        let imported = read_garmin_fpl(db_path, path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(db_path).unwrap();
+
+        assert_eq!(imported.len(), 5);
+        assert!(matches!(&imported[1].kind, WaypointKind::navaid(ident) if ident == "PTS"));
+        assert!(matches!(&imported[2].kind, WaypointKind::user_waypoint(name) if name == "NORD"));
+        assert!(matches!(imported[3].kind, WaypointKind::coordinates));
+        for (point, original) in imported.iter().zip(&points) {
+            assert_eq!(point.name, original.name);
+            assert!((point.latitude - original.latitude).abs() < 1e-6 && (point.longitude - original.longitude).abs() < 1e-6);
+        }
+    }
+}
@@ -1,16 +1,31 @@
+use super::coordinates::{self, CoordinateFormat};
+use super::error::CoordinateError;
+use super::sun::SunTimes;
+use super::waypoint::WaypointKind;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Conversion factor from kilometers to nautical miles
+pub const KM_TO_NM: f64 = 0.539957;
+
 /// Represents a navigation point with a name, latitude, and longitude.
 ///
 /// # Fields
 /// - `name`: A `String` representing the name of the navigation point.
 /// - `latitude`: A `f64` representing the latitude of the navigation point in degrees.
 /// - `longitude`: A `f64` representing the longitude of the navigation point in degrees.
+/// - `kind`: A `WaypointKind` telling what the point was defined from (coordinates by default).
 ///
 /// # Methods
 /// - `new(name: String, latitude: f64, longitude: f64) -> NavPoint`: Creates a new `NavPoint` instance with the given name, latitude, and longitude in radians.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NavPoint {
     pub name: String,
     pub latitude: f64,
     pub longitude: f64,
+    #[serde(default)]
+    pub kind: WaypointKind,
 }
 
 impl NavPoint {
@@ -19,6 +34,7 @@ impl NavPoint {
             name,
             latitude,
             longitude,
+            kind: WaypointKind::coordinates,
         }
     }
 
@@ -27,8 +43,81 @@ impl NavPoint {
             name,
             latitude: latitude.to_degrees(),
             longitude: longitude.to_degrees(),
+            kind: WaypointKind::coordinates,
+        }
+    }
+
+    /// Creates a `NavPoint` from a position written as on charts, see `coordinates::parse`
+    pub fn from_coordinates(name: String, position: &str) -> Result<NavPoint, CoordinateError> {
+        let (latitude, longitude) = coordinates::parse(position)?;
+        Ok(NavPoint::new(name, latitude, longitude))
+    }
+
+    /// Position of the point in the given format
+    pub fn position(&self, format: CoordinateFormat) -> String {
+        coordinates::format(self.latitude, self.longitude, format)
+    }
+
+    /// Name of the point as typed in a route (see `route::resolve_waypoint`):
+    /// points defined by their coordinates are written in the ICAO form
+    pub fn route_name(&self) -> String {
+        match self.kind {
+            WaypointKind::coordinates => self.position(CoordinateFormat::icao),
+            _ => self.name.clone(),
         }
     }
+
+    /// Sunrise, sunset and civil twilight at this point on `date`
+    pub fn sun_times(&self, date: NaiveDate) -> SunTimes {
+        SunTimes::compute(self.latitude, self.longitude, date)
+    }
+}
+
+/// WGS-84 semi-major axis, in meters
+const WGS84_A: f64 = 6378137.0;
+/// WGS-84 flattening
+const WGS84_F: f64 = 1.0 / 298.257223563;
+
+/// Model of the Earth used for distances and courses
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum EarthModel {
+    /// Sphere of radius 6371 km, fast
+    #[default]
+    spherical,
+    /// WGS-84 ellipsoid (Vincenty), matches EFB software to the meter
+    wgs84,
+}
+
+/// Distance in kilometers and initial course in degrees between two points, using the given Earth model
+pub fn distance_and_course(from: &NavPoint, to: &NavPoint, model: EarthModel) -> (Option<f64>, Option<f64>) {
+    match model {
+        EarthModel::spherical => calculate_distance_and_course(from, to),
+        EarthModel::wgs84 => ellipsoidal_distance_and_course(from, to),
+    }
+}
+
+/// Point reached from `from` with initial `course` (degrees true) after `distance` kilometers, using the given Earth model
+pub fn project_point(from: &NavPoint, course: f64, distance: f64, model: EarthModel, name: String) -> NavPoint {
+    match model {
+        EarthModel::spherical => destination_point(from, course, distance, name),
+        EarthModel::wgs84 => ellipsoidal_destination_point(from, course, distance, name),
+    }
+}
+
+/// Path followed along a leg
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum LegType {
+    /// Shortest path, course changes along the leg
+    #[default]
+    great_circle,
+    /// Constant course (loxodrome), a straight line on a Mercator chart
+    rhumb_line,
+}
+
+impl fmt::Display for NavPoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.name, self.position(CoordinateFormat::dms))
+    }
 }
 
 pub fn calculate_distance_and_course(
@@ -54,4 +143,397 @@ pub fn calculate_distance_and_course(
     let calculated_course = y.atan2(x).to_degrees();
 
     (Some(calculated_distance), Some(calculated_course))
-}
\ No newline at end of file
+}
+
+/// Distance in kilometers from `point` to the great circle through `from` and `to`,
+/// positive when the point is right of the track.
+pub fn cross_track_distance(from: &NavPoint, to: &NavPoint, point: &NavPoint) -> Option<f64> {
+    let (distance_to_point, course_to_point) = calculate_distance_and_course(from, point);
+    let (_, course) = calculate_distance_and_course(from, to);
+    let angular_distance = distance_to_point? / 6371.0;
+    let angle = (course_to_point? - course?).to_radians();
+    Some((angular_distance.sin() * angle.sin()).asin() * 6371.0)
+}
+
+/// Distance in kilometers from `from` to the projection of `point` on the great circle through `from` and `to`,
+/// negative when the point is behind `from`.
+pub fn along_track_distance(from: &NavPoint, to: &NavPoint, point: &NavPoint) -> Option<f64> {
+    let (distance_to_point, course_to_point) = calculate_distance_and_course(from, point);
+    let (_, course) = calculate_distance_and_course(from, to);
+    let angular_distance = distance_to_point? / 6371.0;
+    let angle = (course_to_point? - course?).to_radians();
+    let cross_track = (angular_distance.sin() * angle.sin()).asin();
+    let along_track = (angular_distance.cos() / cross_track.cos()).clamp(-1.0, 1.0).acos();
+    Some(along_track * angle.cos().signum() * 6371.0)
+}
+
+/// Distance in kilometers and constant course in degrees of the rhumb line between two points, using the given Earth model
+pub fn rhumb_distance_and_course(from: &NavPoint, to: &NavPoint, model: EarthModel) -> (Option<f64>, Option<f64>) {
+    match model {
+        EarthModel::spherical => calculate_rhumb_distance_and_course(from, to),
+        EarthModel::wgs84 => ellipsoidal_rhumb_distance_and_course(from, to),
+    }
+}
+
+/// Longitude difference from `from` to `to` in radians, the short way around
+fn longitude_difference(from: f64, to: f64) -> f64 {
+    let mut dlon = (to - from).to_radians();
+    if dlon.abs() > std::f64::consts::PI {
+        dlon -= dlon.signum() * 2.0 * std::f64::consts::PI;
+    }
+    dlon
+}
+
+/// Latitude in radians projected on a Mercator chart of the sphere
+fn mercator_latitude(latitude: f64) -> f64 {
+    (std::f64::consts::FRAC_PI_4 + latitude / 2.0).tan().ln()
+}
+
+/// Distance in kilometers and constant course in degrees of the rhumb line between two points, on a sphere.
+pub fn calculate_rhumb_distance_and_course(from: &NavPoint, to: &NavPoint) -> (Option<f64>, Option<f64>) {
+    let lat1 = from.latitude.to_radians();
+    let lat2 = to.latitude.to_radians();
+    let dlat = lat2 - lat1;
+    let dlon = longitude_difference(from.longitude, to.longitude);
+
+    // Difference of the latitudes projected on a Mercator chart
+    let dproj = mercator_latitude(lat2) - mercator_latitude(lat1);
+    let q = if dproj.abs() > 1e-12 { dlat / dproj } else { lat1.cos() };
+
+    let earth_radius_km = 6371.0;
+    let calculated_distance = (dlat * dlat + q * q * dlon * dlon).sqrt() * earth_radius_km;
+    let calculated_course = dlon.atan2(dproj).to_degrees();
+
+    (Some(calculated_distance), Some(calculated_course))
+}
+
+/// Distance in kilometers and constant course in degrees of the rhumb line between two points,
+/// on the WGS-84 ellipsoid.
+pub fn ellipsoidal_rhumb_distance_and_course(from: &NavPoint, to: &NavPoint) -> (Option<f64>, Option<f64>) {
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+    let e = e2.sqrt();
+    // Isometric latitude, the latitude projected on a Mercator chart of the ellipsoid
+    let isometric = |lat: f64| lat.sin().atanh() - e * (e * lat.sin()).atanh();
+    // Distance from the equator along the meridian, in meters
+    let meridian_arc = |lat: f64| {
+        WGS84_A
+            * ((1.0 - e2 / 4.0 - 3.0 * e2 * e2 / 64.0 - 5.0 * e2 * e2 * e2 / 256.0) * lat
+                - (3.0 * e2 / 8.0 + 3.0 * e2 * e2 / 32.0 + 45.0 * e2 * e2 * e2 / 1024.0) * (2.0 * lat).sin()
+                + (15.0 * e2 * e2 / 256.0 + 45.0 * e2 * e2 * e2 / 1024.0) * (4.0 * lat).sin()
+                - 35.0 * e2 * e2 * e2 / 3072.0 * (6.0 * lat).sin())
+    };
+
+    let lat1 = from.latitude.to_radians();
+    let lat2 = to.latitude.to_radians();
+    let dlon = longitude_difference(from.longitude, to.longitude);
+    let course = dlon.atan2(isometric(lat2) - isometric(lat1));
+
+    let distance = if (lat2 - lat1).abs() > 1e-12 {
+        (meridian_arc(lat2) - meridian_arc(lat1)) / course.cos()
+    } else {
+        // Along a parallel, the radius of the parallel times the longitude difference
+        WGS84_A * lat1.cos() / (1.0 - e2 * lat1.sin().powi(2)).sqrt() * dlon.abs()
+    };
+
+    (Some(distance / 1000.0), Some(course.to_degrees()))
+}
+
+/// Returns the point at `fraction` (0.0 at `from`, 1.0 at `to`) along the rhumb line between two points, on a sphere.
+pub fn rhumb_intermediate_point(from: &NavPoint, to: &NavPoint, fraction: f64, name: String) -> NavPoint {
+    let lat1 = from.latitude.to_radians();
+    let lat2 = to.latitude.to_radians();
+    let dlon = longitude_difference(from.longitude, to.longitude);
+
+    // The latitude changes linearly with the distance along a rhumb line, the longitude with the Mercator latitude
+    let lat = lat1 + fraction * (lat2 - lat1);
+    let dproj = mercator_latitude(lat2) - mercator_latitude(lat1);
+    let lon = if dproj.abs() > 1e-12 {
+        from.longitude.to_radians() + dlon * (mercator_latitude(lat) - mercator_latitude(lat1)) / dproj
+    } else {
+        from.longitude.to_radians() + dlon * fraction
+    };
+
+    NavPoint::from_radians(name, lat, (lon + 3.0 * std::f64::consts::PI).rem_euclid(2.0 * std::f64::consts::PI) - std::f64::consts::PI)
+}
+
+/// Along-track and cross-track distances in kilometers of `point` relative to the rhumb line from `from` to `to`,
+/// on a sphere. The along-track distance is negative when the point is behind `from`, the cross-track distance
+/// positive when the point is right of the track.
+pub fn rhumb_track_distances(from: &NavPoint, to: &NavPoint, point: &NavPoint) -> Option<(f64, f64)> {
+    // The rhumb line is straight on a Mercator chart, and the chart is conformal: the foot of the
+    // perpendicular from the point to the line is found on the chart.
+    let y1 = mercator_latitude(from.latitude.to_radians());
+    let (dx, dy) = (longitude_difference(from.longitude, to.longitude), mercator_latitude(to.latitude.to_radians()) - y1);
+    let (px, py) = (longitude_difference(from.longitude, point.longitude), mercator_latitude(point.latitude.to_radians()) - y1);
+    let length = dx * dx + dy * dy;
+    if length == 0.0 {
+        return None;
+    }
+    let t = (px * dx + py * dy) / length;
+
+    // Fraction of the leg at the foot, from its latitude
+    let dlat = to.latitude - from.latitude;
+    let fraction = if dlat.abs() > 1e-6 {
+        let latitude = (y1 + t * dy).sinh().atan().to_degrees();
+        (latitude - from.latitude) / dlat
+    } else {
+        t
+    };
+    let foot = rhumb_intermediate_point(from, to, fraction, String::new());
+
+    let (leg_distance, _) = calculate_rhumb_distance_and_course(from, to);
+    let (off_track, _) = calculate_distance_and_course(&foot, point);
+    let side = if dx * py - dy * px > 0.0 { -1.0 } else { 1.0 };
+    Some((fraction * leg_distance?, side * off_track?))
+}
+
+/// Returns the point at `fraction` (0.0 at `from`, 1.0 at `to`) along the great circle between two points.
+pub fn intermediate_point(from: &NavPoint, to: &NavPoint, fraction: f64, name: String) -> NavPoint {
+    let lat1 = from.latitude.to_radians();
+    let lon1 = from.longitude.to_radians();
+    let lat2 = to.latitude.to_radians();
+    let lon2 = to.longitude.to_radians();
+
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    let angular_distance = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
+    if angular_distance == 0.0 {
+        return NavPoint::new(name, from.latitude, from.longitude);
+    }
+
+    let coef_from = ((1.0 - fraction) * angular_distance).sin() / angular_distance.sin();
+    let coef_to = (fraction * angular_distance).sin() / angular_distance.sin();
+    let x = coef_from * lat1.cos() * lon1.cos() + coef_to * lat2.cos() * lon2.cos();
+    let y = coef_from * lat1.cos() * lon1.sin() + coef_to * lat2.cos() * lon2.sin();
+    let z = coef_from * lat1.sin() + coef_to * lat2.sin();
+
+    NavPoint::from_radians(name, z.atan2((x * x + y * y).sqrt()), y.atan2(x))
+}
+
+/// Returns the point reached from `from` following the great circle with initial `course` (degrees true)
+/// for `distance` kilometers.
+pub fn destination_point(from: &NavPoint, course: f64, distance: f64, name: String) -> NavPoint {
+    let lat1 = from.latitude.to_radians();
+    let lon1 = from.longitude.to_radians();
+    let course = course.to_radians();
+    let angular_distance = distance / 6371.0;
+
+    let lat2 = (lat1.sin() * angular_distance.cos() + lat1.cos() * angular_distance.sin() * course.cos()).asin();
+    let lon2 = lon1
+        + (course.sin() * angular_distance.sin() * lat1.cos()).atan2(angular_distance.cos() - lat1.sin() * lat2.sin());
+
+    NavPoint::from_radians(name, lat2, (lon2 + 3.0 * std::f64::consts::PI) % (2.0 * std::f64::consts::PI) - std::f64::consts::PI)
+}
+
+/// Inverse geodesic problem on the WGS-84 ellipsoid (Vincenty).
+/// Returns the distance in kilometers and the initial course in degrees,
+/// or `None` for nearly antipodal points where the iteration does not converge.
+pub fn ellipsoidal_distance_and_course(from: &NavPoint, to: &NavPoint) -> (Option<f64>, Option<f64>) {
+    let b = WGS84_A * (1.0 - WGS84_F);
+    let l = (to.longitude - from.longitude).to_radians();
+    let u1 = ((1.0 - WGS84_F) * from.latitude.to_radians().tan()).atan();
+    let u2 = ((1.0 - WGS84_F) * to.latitude.to_radians().tan()).atan();
+    let (sin_u1, cos_u1) = u1.sin_cos();
+    let (sin_u2, cos_u2) = u2.sin_cos();
+
+    let mut lambda = l;
+    for _ in 0..200 {
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+        let sin_sigma = ((cos_u2 * sin_lambda).powi(2) + (cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda).powi(2)).sqrt();
+        if sin_sigma == 0.0 {
+            return (Some(0.0), Some(0.0));
+        }
+        let cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+        let sigma = sin_sigma.atan2(cos_sigma);
+        let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+        let cos_sq_alpha = 1.0 - sin_alpha * sin_alpha;
+        let cos_2sigma_m = if cos_sq_alpha != 0.0 { cos_sigma - 2.0 * sin_u1 * sin_u2 / cos_sq_alpha } else { 0.0 };
+        let c = WGS84_F / 16.0 * cos_sq_alpha * (4.0 + WGS84_F * (4.0 - 3.0 * cos_sq_alpha));
+        let previous = lambda;
+        lambda = l + (1.0 - c) * WGS84_F * sin_alpha
+            * (sigma + c * sin_sigma * (cos_2sigma_m + c * cos_sigma * (-1.0 + 2.0 * cos_2sigma_m * cos_2sigma_m)));
+
+        if (lambda - previous).abs() < 1e-12 {
+            let u_sq = cos_sq_alpha * (WGS84_A * WGS84_A - b * b) / (b * b);
+            let big_a = 1.0 + u_sq / 16384.0 * (4096.0 + u_sq * (-768.0 + u_sq * (320.0 - 175.0 * u_sq)));
+            let big_b = u_sq / 1024.0 * (256.0 + u_sq * (-128.0 + u_sq * (74.0 - 47.0 * u_sq)));
+            let delta_sigma = big_b * sin_sigma
+                * (cos_2sigma_m + big_b / 4.0
+                    * (cos_sigma * (-1.0 + 2.0 * cos_2sigma_m * cos_2sigma_m)
+                        - big_b / 6.0 * cos_2sigma_m * (-3.0 + 4.0 * sin_sigma * sin_sigma) * (-3.0 + 4.0 * cos_2sigma_m * cos_2sigma_m)));
+            let distance = b * big_a * (sigma - delta_sigma) / 1000.0;
+            let (sin_lambda, cos_lambda) = lambda.sin_cos();
+            let course = (cos_u2 * sin_lambda).atan2(cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda).to_degrees();
+            return (Some(distance), Some(course));
+        }
+    }
+    (None, None)
+}
+
+/// Direct geodesic problem on the WGS-84 ellipsoid (Vincenty): point reached from `from`
+/// with initial `course` (degrees true) after `distance` kilometers.
+pub fn ellipsoidal_destination_point(from: &NavPoint, course: f64, distance: f64, name: String) -> NavPoint {
+    let b = WGS84_A * (1.0 - WGS84_F);
+    let (sin_alpha1, cos_alpha1) = course.to_radians().sin_cos();
+    let tan_u1 = (1.0 - WGS84_F) * from.latitude.to_radians().tan();
+    let cos_u1 = 1.0 / (1.0 + tan_u1 * tan_u1).sqrt();
+    let sin_u1 = tan_u1 * cos_u1;
+    let sigma1 = tan_u1.atan2(cos_alpha1);
+    let sin_alpha = cos_u1 * sin_alpha1;
+    let cos_sq_alpha = 1.0 - sin_alpha * sin_alpha;
+    let u_sq = cos_sq_alpha * (WGS84_A * WGS84_A - b * b) / (b * b);
+    let big_a = 1.0 + u_sq / 16384.0 * (4096.0 + u_sq * (-768.0 + u_sq * (320.0 - 175.0 * u_sq)));
+    let big_b = u_sq / 1024.0 * (256.0 + u_sq * (-128.0 + u_sq * (74.0 - 47.0 * u_sq)));
+
+    let distance = distance * 1000.0;
+    let mut sigma = distance / (b * big_a);
+    let mut cos_2sigma_m;
+    let mut sin_sigma;
+    let mut cos_sigma;
+    let mut iterations = 0;
+    loop {
+        cos_2sigma_m = (2.0 * sigma1 + sigma).cos();
+        sin_sigma = sigma.sin();
+        cos_sigma = sigma.cos();
+        let delta_sigma = big_b * sin_sigma
+            * (cos_2sigma_m + big_b / 4.0
+                * (cos_sigma * (-1.0 + 2.0 * cos_2sigma_m * cos_2sigma_m)
+                    - big_b / 6.0 * cos_2sigma_m * (-3.0 + 4.0 * sin_sigma * sin_sigma) * (-3.0 + 4.0 * cos_2sigma_m * cos_2sigma_m)));
+        let previous = sigma;
+        sigma = distance / (b * big_a) + delta_sigma;
+        iterations += 1;
+        if (sigma - previous).abs() < 1e-12 || iterations >= 200 {
+            break;
+        }
+    }
+
+    let x = sin_u1 * sin_sigma - cos_u1 * cos_sigma * cos_alpha1;
+    let lat2 = (sin_u1 * cos_sigma + cos_u1 * sin_sigma * cos_alpha1).atan2((1.0 - WGS84_F) * (sin_alpha * sin_alpha + x * x).sqrt());
+    let lambda = (sin_sigma * sin_alpha1).atan2(cos_u1 * cos_sigma - sin_u1 * sin_sigma * cos_alpha1);
+    let c = WGS84_F / 16.0 * cos_sq_alpha * (4.0 + WGS84_F * (4.0 - 3.0 * cos_sq_alpha));
+    let l = lambda - (1.0 - c) * WGS84_F * sin_alpha
+        * (sigma + c * sin_sigma * (cos_2sigma_m + c * cos_sigma * (-1.0 + 2.0 * cos_2sigma_m * cos_2sigma_m)));
+    let lon2 = from.longitude.to_radians() + l;
+
+    NavPoint::from_radians(name, lat2, (lon2 + 3.0 * std::f64::consts::PI) % (2.0 * std::f64::consts::PI) - std::f64::consts::PI)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dms(degrees: f64, minutes: f64, seconds: f64) -> f64 {
+        degrees.signum() * (degrees.abs() + minutes / 60.0 + seconds / 3600.0)
+    }
+
+    // Geodetic survey test line from Vincenty (1975): 54 972.271 m, initial azimuth 306°52'05.37"
+    fn flinders_peak() -> NavPoint {
+        NavPoint::new("FLINDERS PEAK".to_string(), dms(-37.0, 57.0, 3.72030), dms(144.0, 25.0, 29.52440))
+    }
+
+    fn buninyong() -> NavPoint {
+        NavPoint::new("BUNINYONG".to_string(), dms(-37.0, 39.0, 10.15610), dms(143.0, 55.0, 35.38390))
+    }
+
+    #[test]
+    fn route_name_writes_coordinates_in_icao_form() {
+        let point = NavPoint::new("WP01".to_string(), 48.7533, -1.7667);
+        assert_eq!(point.route_name(), "4845N00146W");
+        let (latitude, longitude) = coordinates::parse(&point.route_name()).unwrap();
+        assert!((latitude - 48.75).abs() < 1e-9 && (longitude + 1.7666666).abs() < 1e-6);
+
+        let mut airport = NavPoint::new("LFPN".to_string(), 48.7519, 2.1061);
+        airport.kind = WaypointKind::airport("LFPN".to_string());
+        assert_eq!(airport.route_name(), "LFPN");
+    }
+
+    #[test]
+    fn vincenty_inverse() {
+        let (distance, course) = ellipsoidal_distance_and_course(&flinders_peak(), &buninyong());
+        assert!((distance.unwrap() - 54.972271).abs() < 1e-6);
+        assert!((course.unwrap().rem_euclid(360.0) - dms(306.0, 52.0, 5.37)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn vincenty_direct() {
+        let point = ellipsoidal_destination_point(&flinders_peak(), dms(306.0, 52.0, 5.37), 54.972271, "B".to_string());
+        assert!((point.latitude - buninyong().latitude).abs() < 1e-7);
+        assert!((point.longitude - buninyong().longitude).abs() < 1e-7);
+    }
+
+    #[test]
+    fn vincenty_antipodal_does_not_converge() {
+        let from = NavPoint::new("A".to_string(), 0.0, 0.0);
+        let to = NavPoint::new("B".to_string(), 0.5, 179.7);
+        assert_eq!(ellipsoidal_distance_and_course(&from, &to), (None, None));
+    }
+
+    #[test]
+    fn spherical_and_ellipsoidal_agree_within_half_a_percent() {
+        let (spherical, _) = distance_and_course(&flinders_peak(), &buninyong(), EarthModel::spherical);
+        let (ellipsoidal, _) = distance_and_course(&flinders_peak(), &buninyong(), EarthModel::wgs84);
+        assert!((spherical.unwrap() / ellipsoidal.unwrap() - 1.0).abs() < 0.005);
+    }
+
+    #[test]
+    fn rhumb_line_on_the_sphere() {
+        // Plymouth to Boston
+        let from = NavPoint::new("PLYMOUTH".to_string(), dms(50.0, 21.0, 59.0), dms(-4.0, 8.0, 2.0));
+        let to = NavPoint::new("BOSTON".to_string(), dms(42.0, 21.0, 4.0), dms(-71.0, 2.0, 27.0));
+        let (distance, course) = rhumb_distance_and_course(&from, &to, EarthModel::spherical);
+        assert!((distance.unwrap() - 5198.0).abs() < 1.0);
+        assert!((course.unwrap().rem_euclid(360.0) - dms(260.0, 7.0, 38.0)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn rhumb_line_on_the_ellipsoid() {
+        // Meridians are both rhumb lines and geodesics
+        let from = NavPoint::new("A".to_string(), 0.0, 0.0);
+        let to = NavPoint::new("B".to_string(), 45.0, 0.0);
+        let (rhumb, course) = rhumb_distance_and_course(&from, &to, EarthModel::wgs84);
+        let (geodesic, _) = ellipsoidal_distance_and_course(&from, &to);
+        assert!((rhumb.unwrap() - geodesic.unwrap()).abs() < 1e-3);
+        assert_eq!(course, Some(0.0));
+
+        // So is the equator
+        let to = NavPoint::new("C".to_string(), 0.0, 1.0);
+        let (rhumb, course) = rhumb_distance_and_course(&from, &to, EarthModel::wgs84);
+        assert!((rhumb.unwrap() - WGS84_A / 1000.0 * 1f64.to_radians()).abs() < 1e-6);
+        assert_eq!(course, Some(90.0));
+
+        let (spherical, _) = rhumb_distance_and_course(&flinders_peak(), &buninyong(), EarthModel::spherical);
+        let (ellipsoidal, _) = rhumb_distance_and_course(&flinders_peak(), &buninyong(), EarthModel::wgs84);
+        assert!((spherical.unwrap() / ellipsoidal.unwrap() - 1.0).abs() < 0.005);
+    }
+
+    #[test]
+    fn rhumb_line_crosses_the_antimeridian() {
+        let from = NavPoint::new("A".to_string(), 10.0, 179.0);
+        let to = NavPoint::new("B".to_string(), 10.0, -179.0);
+        let (distance, course) = rhumb_distance_and_course(&from, &to, EarthModel::spherical);
+        assert!((distance.unwrap() - 6371.0 * 2f64.to_radians() * 10f64.to_radians().cos()).abs() < 1e-6);
+        assert_eq!(course, Some(90.0));
+        let middle = rhumb_intermediate_point(&from, &to, 0.5, String::new());
+        assert!((middle.longitude.abs() - 180.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn points_along_a_rhumb_line_keep_the_course() {
+        let from = NavPoint::new("A".to_string(), 43.0, 1.0);
+        let to = NavPoint::new("B".to_string(), 49.0, 5.0);
+        let (distance, course) = calculate_rhumb_distance_and_course(&from, &to);
+        let quarter = rhumb_intermediate_point(&from, &to, 0.25, String::new());
+        let (first_distance, first_course) = calculate_rhumb_distance_and_course(&from, &quarter);
+        assert!((first_distance.unwrap() - distance.unwrap() / 4.0).abs() < 1e-6);
+        assert!((first_course.unwrap() - course.unwrap()).abs() < 1e-9);
+
+        let (along_track, cross_track) = rhumb_track_distances(&from, &to, &quarter).unwrap();
+        assert!((along_track - distance.unwrap() / 4.0).abs() < 1e-6);
+        assert!(cross_track.abs() < 1e-6);
+
+        // 10 km west of the northeastbound track, which is left of it
+        let west = NavPoint::new("W".to_string(), quarter.latitude, quarter.longitude - 10.0 / (111.195 * quarter.latitude.to_radians().cos()));
+        let (_, cross_track) = rhumb_track_distances(&from, &to, &west).unwrap();
+        let expected = 10.0 * (course.unwrap().to_radians()).cos();
+        assert!((cross_track + expected).abs() < 0.05);
+    }
+}
@@ -0,0 +1,200 @@
+use std::fs;
+
+use roxmltree::{Document, Node};
+
+use super::error::RouteError;
+use super::flightplan::Navigation;
+use super::geographics::{NavPoint, KM_TO_NM};
+use super::levels;
+use super::route::RoutePoint;
+use super::waypoint::WaypointKind;
+
+pub const FEET_PER_METER: f64 = 3.28084;
+
+/// Escapes the characters with a meaning in XML text and double-quoted attributes
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Garmin symbol of a waypoint, understood by most GPS units and EFB apps
+fn symbol(point: &NavPoint) -> &'static str {
+    match point.kind {
+        WaypointKind::airport(_) => "Airport",
+        WaypointKind::navaid(_) => "Navaid, White",
+        _ => "Waypoint",
+    }
+}
+
+/// Renders a navigation as a GPX 1.1 route. Each route point has the name of the waypoint,
+/// the leg leading to it in its description, and the planned altitude of the leg leaving it
+/// as elevation in meters (the previous one when a leg keeps its altitude).
+pub fn to_gpx(navigation: &Navigation) -> String {
+    let mut points: Vec<(&NavPoint, String)> = vec![(&navigation.start, "Departure".to_string())];
+    for branch in &navigation.branches {
+        if let Some(to) = &branch.to {
+            let mut description = vec![branch.name()];
+            if let Some(course) = branch.course {
+                description.push(format!("{:03.0}T", course.rem_euclid(360.0)));
+            }
+            if let Some(distance) = branch.distance {
+                description.push(format!("{:.1} NM", distance * KM_TO_NM));
+            }
+            if let Some(time) = branch.time {
+                description.push(format!("{}'", (time.num_seconds() as f64 / 60.0).round()));
+            }
+            points.push((to, description.join(" ")));
+        }
+    }
+
+    let mut altitude = None;
+    let altitudes: Vec<Option<f64>> = navigation
+        .branches
+        .iter()
+        .map(|branch| {
+            altitude = branch.altitude.or(altitude);
+            altitude
+        })
+        .collect();
+
+    let route_name = format!("{} - {}", navigation.start.name, navigation.end.name);
+    let mut gpx = String::new();
+    gpx.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    gpx.push_str("<gpx version=\"1.1\" creator=\"flight_planner\" xmlns=\"http://www.topografix.com/GPX/1/1\" ");
+    gpx.push_str("xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" ");
+    gpx.push_str("xsi:schemaLocation=\"http://www.topografix.com/GPX/1/1 http://www.topografix.com/GPX/1/1/gpx.xsd\">\n");
+    gpx.push_str(&format!("  <metadata>\n    <name>{}</name>\n  </metadata>\n", escape_xml(&route_name)));
+    gpx.push_str(&format!("  <rte>\n    <name>{}</name>\n", escape_xml(&route_name)));
+    for (index, (point, description)) in points.iter().enumerate() {
+        let name = if point.name.trim().is_empty() { format!("WP{:02}", index) } else { point.name.clone() };
+        gpx.push_str(&format!("    <rtept lat=\"{:.6}\" lon=\"{:.6}\">\n", point.latitude, point.longitude));
+        if let Some(Some(altitude)) = altitudes.get(index) {
+            gpx.push_str(&format!("      <ele>{:.1}</ele>\n", altitude / FEET_PER_METER));
+        }
+        gpx.push_str(&format!("      <name>{}</name>\n", escape_xml(&name)));
+        let mut description = description.clone();
+        if let Some(Some(altitude)) = altitudes.get(index) {
+            description.push_str(&format!(", then {}", levels::format_level(*altitude, levels::TRANSITION_ALTITUDE)));
+        }
+        gpx.push_str(&format!("      <desc>{}</desc>\n", escape_xml(&description)));
+        gpx.push_str(&format!("      <sym>{}</sym>\n", symbol(point)));
+        gpx.push_str("    </rtept>\n");
+    }
+    gpx.push_str("  </rte>\n</gpx>\n");
+    gpx
+}
+
+/// Writes a navigation to a GPX 1.1 file, see `to_gpx`
+pub fn export_gpx(navigation: &Navigation, path: &str) -> Result<(), std::io::Error> {
+    fs::write(path, to_gpx(navigation))
+}
+
+/// Text of the first child element named `name`
+fn child_text<'a>(node: Node<'a, 'a>, name: &str) -> Option<&'a str> {
+    node.children().find(|child| child.has_tag_name(name)).and_then(|child| child.text()).map(str::trim)
+}
+
+/// Reads a `rtept`, `trkpt` or `wpt` element, its elevation in meters becoming an altitude in ft
+fn read_point(node: Node) -> Option<RoutePoint> {
+    let latitude = node.attribute("lat")?.trim().parse().ok()?;
+    let longitude = node.attribute("lon")?.trim().parse().ok()?;
+    let name = child_text(node, "name").unwrap_or("").to_string();
+    Some(RoutePoint {
+        point: NavPoint::new(name, latitude, longitude),
+        altitude: child_text(node, "ele").and_then(|e| e.parse::<f64>().ok()).map(|e| e * FEET_PER_METER),
+    })
+}
+
+/// Reads the first route of a GPX file, or else its first track (all segments joined), or else its waypoints.
+/// Returns the points and whether they come from a recorded track.
+pub fn read_gpx(path: &str) -> Result<(Vec<RoutePoint>, bool), RouteError> {
+    let text = fs::read_to_string(path)?;
+    let document = Document::parse(&text)?;
+    let root = document.root_element();
+    let elements = |parent: Node, name: &str| -> Vec<RoutePoint> {
+        parent.children().filter(|child| child.has_tag_name(name)).filter_map(read_point).collect()
+    };
+
+    if let Some(route) = root.children().find(|child| child.has_tag_name("rte")) {
+        return Ok((elements(route, "rtept"), false));
+    }
+    if let Some(track) = root.children().find(|child| child.has_tag_name("trk")) {
+        let points = track
+            .children()
+            .filter(|child| child.has_tag_name("trkseg"))
+            .flat_map(|segment| elements(segment, "trkpt"))
+            .collect();
+        return Ok((points, true));
+    }
+    Ok((elements(root, "wpt"), false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navigation::flightplan::{NavBranch, Wind};
+    use crate::navigation::geographics::EarthModel;
+
+    /// LFPN to LFOB through an unnamed point and a named one, the second leg keeping the first altitude
+    fn navigation() -> Navigation {
+        let points = [
+            NavPoint::new("LFPN".to_string(), 48.7519, 2.1061),
+            NavPoint::new(String::new(), 48.9, 2.2),
+            NavPoint::new("Château & <lac>".to_string(), 49.2, 2.1),
+            NavPoint::new("LFOB".to_string(), 49.4544, 2.1128),
+        ];
+        let wind = Wind { speed: 0.0, direction: 0.0 };
+        let altitudes = [Some(2500.0), None, Some(3500.0)];
+        let branches = points
+            .windows(2)
+            .zip(altitudes)
+            .map(|(leg, altitude)| NavBranch::between(leg[0].clone(), leg[1].clone(), wind, altitude, EarthModel::spherical))
+            .collect();
+        Navigation::new(points[0].clone(), points[3].clone(), branches)
+    }
+
+    #[test]
+    fn escapes_xml() {
+        assert_eq!(escape_xml("A&B <C> \"D\" 'E'"), "A&amp;B &lt;C&gt; &quot;D&quot; 'E'");
+    }
+
+    #[test]
+    fn route_points_follow_the_gpx_element_order() {
+        let gpx = to_gpx(&navigation());
+        assert!(gpx.find("<metadata>").unwrap() < gpx.find("<rte>").unwrap());
+        let first = &gpx[gpx.find("<rtept").unwrap()..gpx.find("</rtept>").unwrap()];
+        let order: Vec<usize> = ["<ele>", "<name>", "<desc>", "<sym>"].iter().map(|tag| first.find(tag).unwrap()).collect();
+        assert!(order.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn elevations_in_meters_names_and_escaping() {
+        let gpx = to_gpx(&navigation());
+        // 2500 ft, kept on the second leg, then 3500 ft; no altitude after the arrival
+        let elevations: Vec<&str> = gpx.lines().filter(|line| line.contains("<ele>")).map(str::trim).collect();
+        assert_eq!(elevations, ["<ele>762.0</ele>", "<ele>762.0</ele>", "<ele>1066.8</ele>"]);
+        assert!(gpx.contains("<name>WP01</name>"));
+        assert!(gpx.contains("<name>Château &amp; &lt;lac&gt;</name>"));
+        assert!(gpx.contains("<name>LFPN - LFOB</name>"));
+
+        let path = std::env::temp_dir().join("flight_planner_route.gpx");
+        let path = path.to_str().unwrap();
+        export_gpx(&navigation(), path).unwrap();
+        let (points, track) = read_gpx(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(!track);
+        let names: Vec<&str> = points.iter().map(|p| p.point.name.as_str()).collect();
+        assert_eq!(names, ["LFPN", "WP01", "Château & <lac>", "LFOB"]);
+        assert!((points[0].altitude.unwrap() - 2500.0).abs() < 0.5);
+        assert_eq!(points[3].altitude, None);
+    }
+}
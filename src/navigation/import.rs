@@ -0,0 +1,398 @@
+use std::collections::HashMap;
+
+use rusqlite::Connection;
+
+use super::error::ImportError;
+use super::search;
+use super::spatial;
+
+/// Columns of the OurAirports runways.csv file, also those of the `runways` table
+pub const RUNWAYS_CSV_COLUMNS: [&str; 20] = [
+    "id", "airport_ref", "airport_ident", "length_ft", "width_ft", "surface", "lighted", "closed",
+    "le_ident", "le_latitude_deg", "le_longitude_deg", "le_elevation_ft", "le_heading_degT", "le_displaced_threshold_ft",
+    "he_ident", "he_latitude_deg", "he_longitude_deg", "he_elevation_ft", "he_heading_degT", "he_displaced_threshold_ft",
+];
+
+/// Columns of the OurAirports airport-frequencies.csv file, also those of the `frequencies` table
+pub const FREQUENCIES_CSV_COLUMNS: [&str; 6] = ["id", "airport_ref", "airport_ident", "type", "description", "frequency_mhz"];
+
+/// Columns of the airport fuels CSV file, one line per fuel sold at an airport, e.g. `LFPN,AVGAS100LL`
+pub const FUELS_CSV_COLUMNS: [&str; 2] = ["airport_ident", "fuel"];
+
+/// Columns of the OurAirports navaids.csv file, also those of the `navaids` table
+pub const NAVAIDS_CSV_COLUMNS: [&str; 20] = [
+    "id", "filename", "ident", "name", "type", "frequency_khz", "latitude_deg", "longitude_deg", "elevation_ft",
+    "iso_country", "dme_frequency_khz", "dme_channel", "dme_latitude_deg", "dme_longitude_deg", "dme_elevation_ft",
+    "slaved_variation_deg", "magnetic_variation_deg", "usageType", "power", "associated_airport",
+];
+
+const CREATE_RUNWAYS: &str = r#"CREATE TABLE IF NOT EXISTS "runways" (
+	"id"	TEXT,
+	"airport_ref"	TEXT,
+	"airport_ident"	TEXT,
+	"length_ft"	NUMERIC,
+	"width_ft"	NUMERIC,
+	"surface"	TEXT,
+	"lighted"	NUMERIC,
+	"closed"	NUMERIC,
+	"le_ident"	TEXT,
+	"le_latitude_deg"	NUMERIC,
+	"le_longitude_deg"	NUMERIC,
+	"le_elevation_ft"	NUMERIC,
+	"le_heading_degT"	NUMERIC,
+	"le_displaced_threshold_ft"	NUMERIC,
+	"he_ident"	TEXT,
+	"he_latitude_deg"	NUMERIC,
+	"he_longitude_deg"	NUMERIC,
+	"he_elevation_ft"	NUMERIC,
+	"he_heading_degT"	NUMERIC,
+	"he_displaced_threshold_ft"	NUMERIC,
+	PRIMARY KEY("id")
+);
+CREATE INDEX IF NOT EXISTS "runways_airport" ON "runways" ("airport_ident");"#;
+
+const CREATE_FREQUENCIES: &str = r#"CREATE TABLE IF NOT EXISTS "frequencies" (
+	"id"	TEXT,
+	"airport_ref"	TEXT,
+	"airport_ident"	TEXT,
+	"type"	TEXT,
+	"description"	TEXT,
+	"frequency_mhz"	NUMERIC,
+	PRIMARY KEY("id")
+);
+CREATE INDEX IF NOT EXISTS "frequencies_airport" ON "frequencies" ("airport_ident");"#;
+
+const CREATE_NAVAIDS: &str = r#"CREATE TABLE IF NOT EXISTS "navaids" (
+	"id"	TEXT,
+	"filename"	TEXT,
+	"ident"	TEXT,
+	"name"	TEXT,
+	"type"	TEXT,
+	"frequency_khz"	NUMERIC,
+	"latitude_deg"	NUMERIC,
+	"longitude_deg"	NUMERIC,
+	"elevation_ft"	NUMERIC,
+	"iso_country"	TEXT,
+	"dme_frequency_khz"	NUMERIC,
+	"dme_channel"	TEXT,
+	"dme_latitude_deg"	NUMERIC,
+	"dme_longitude_deg"	NUMERIC,
+	"dme_elevation_ft"	NUMERIC,
+	"slaved_variation_deg"	NUMERIC,
+	"magnetic_variation_deg"	NUMERIC,
+	"usageType"	TEXT,
+	"power"	TEXT,
+	"associated_airport"	TEXT,
+	PRIMARY KEY("id")
+);
+CREATE INDEX IF NOT EXISTS "navaids_ident" ON "navaids" ("ident");"#;
+
+const CREATE_AIRPORT_FUELS: &str = r#"CREATE TABLE IF NOT EXISTS "airport_fuels" (
+	"airport_ident"	TEXT NOT NULL,
+	"fuel"	TEXT NOT NULL,
+	PRIMARY KEY("airport_ident","fuel")
+);"#;
+
+/// Creates the `runways`, `frequencies`, `navaids` and `airport_fuels` tables if they do not exist yet
+pub fn create_tables(db_path: &str) -> Result<(), rusqlite::Error> {
+    let conn = Connection::open(db_path)?;
+    conn.execute_batch(CREATE_RUNWAYS)?;
+    conn.execute_batch(CREATE_FREQUENCIES)?;
+    conn.execute_batch(CREATE_NAVAIDS)?;
+    conn.execute_batch(CREATE_AIRPORT_FUELS)
+}
+
+/// Position of each of `columns` in the CSV header
+fn column_positions(headers: &csv::StringRecord, columns: &[&str]) -> Result<Vec<usize>, ImportError> {
+    columns
+        .iter()
+        .map(|column| {
+            headers
+                .iter()
+                .position(|header| header == *column)
+                .ok_or_else(|| ImportError::missing_column(column.to_string()))
+        })
+        .collect()
+}
+
+/// Inserts or replaces the rows of an OurAirports CSV file into `table`, in a single transaction.
+/// Only the rows whose `airport_ident` is already in the `airports` table are kept, so that
+/// importing the worldwide files does not fill the database with unused airports.
+/// Returns the number of imported rows.
+fn import_csv(db_path: &str, csv_path: &str, table: &str, columns: &[&str]) -> Result<usize, ImportError> {
+    let mut reader = csv::Reader::from_path(csv_path)?;
+    let positions = column_positions(reader.headers()?, columns)?;
+    let airport_ident = columns.iter().position(|column| *column == "airport_ident");
+
+    let mut conn = Connection::open(db_path)?;
+    let transaction = conn.transaction()?;
+    let mut count = 0;
+    {
+        let mut known = transaction.prepare("SELECT 1 FROM airports WHERE ident = ?1")?;
+        let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+        let mut insert = transaction.prepare(&format!(
+            "INSERT OR REPLACE INTO {} ({}) VALUES ({})",
+            table,
+            columns.join(", "),
+            placeholders.join(", ")
+        ))?;
+        for record in reader.records() {
+            let record = record?;
+            let values: Vec<&str> = positions.iter().map(|p| record.get(*p).unwrap_or("")).collect();
+            if let Some(index) = airport_ident {
+                if !known.exists([values[index]])? {
+                    continue;
+                }
+            }
+            insert.execute(rusqlite::params_from_iter(values))?;
+            count += 1;
+        }
+    }
+    transaction.commit()?;
+    Ok(count)
+}
+
+/// Imports the OurAirports runways.csv file into the `runways` table
+pub fn import_runways(db_path: &str, csv_path: &str) -> Result<usize, ImportError> {
+    create_tables(db_path)?;
+    import_csv(db_path, csv_path, "runways", &RUNWAYS_CSV_COLUMNS)
+}
+
+/// Imports the OurAirports airport-frequencies.csv file into the `frequencies` table
+pub fn import_frequencies(db_path: &str, csv_path: &str) -> Result<usize, ImportError> {
+    create_tables(db_path)?;
+    import_csv(db_path, csv_path, "frequencies", &FREQUENCIES_CSV_COLUMNS)
+}
+
+/// Imports the fuels sold at each airport, keeping only airports already in the database
+pub fn import_fuels(db_path: &str, csv_path: &str) -> Result<usize, ImportError> {
+    create_tables(db_path)?;
+    import_csv(db_path, csv_path, "airport_fuels", &FUELS_CSV_COLUMNS)
+}
+
+/// Imports the OurAirports navaids.csv file into the `navaids` table.
+/// Navaids are kept worldwide, they are not linked to the airports of the database.
+pub fn import_navaids(db_path: &str, csv_path: &str) -> Result<usize, ImportError> {
+    create_tables(db_path)?;
+    import_csv(db_path, csv_path, "navaids", &NAVAIDS_CSV_COLUMNS)
+}
+
+/// Which rows of airports.csv are imported, empty lists meaning no restriction
+#[derive(Debug, Clone, Default)]
+pub struct AirportImportFilter {
+    pub countries: Vec<String>,  // ISO 3166-1 codes, e.g. "FR"
+    pub continents: Vec<String>, // OurAirports codes, e.g. "EU"
+    pub types: Vec<String>,      // OurAirports types, e.g. "small_airport"
+}
+
+impl AirportImportFilter {
+    fn accepts(list: &[String], value: &str) -> bool {
+        list.is_empty() || list.iter().any(|item| item.eq_ignore_ascii_case(value))
+    }
+}
+
+/// Outcome of an airports.csv import
+#[derive(Debug, Clone, Default)]
+pub struct AirportImportReport {
+    pub inserted: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub closed: usize, // airports of the database now marked as closed
+}
+
+/// Inserts or updates the `airports` table from a local OurAirports airports.csv file, in a single transaction.
+///
+/// Rows are matched on `ident`. A known airport is only updated when its `last_updated` differs
+/// (always when the file has no such column). An airport of the database that became closed is
+/// updated even if the type filter leaves out closed airports. Columns of the file missing from
+/// the table (`icao_code`, ...) are ignored, and no other table than `airports` and its spatial
+/// index is modified.
+pub fn import_airports(db_path: &str, csv_path: &str, filter: &AirportImportFilter) -> Result<AirportImportReport, ImportError> {
+    search::index_airports(db_path)?;
+    spatial::ensure_index(db_path)?;
+    let mut reader = csv::Reader::from_path(csv_path)?;
+    let headers = reader.headers()?.clone();
+    let mut conn = Connection::open(db_path)?;
+
+    let table_columns: Vec<String> = conn
+        .prepare("SELECT name FROM pragma_table_info('airports')")?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    let columns: Vec<&str> = table_columns
+        .iter()
+        .map(String::as_str)
+        .filter(|column| headers.iter().any(|header| header == *column))
+        .collect();
+    let positions = column_positions(&headers, &columns)?;
+    let [ident, airport_type, iso_country, continent] =
+        ["ident", "type", "iso_country", "continent"].map(|column| columns.iter().position(|c| *c == column));
+    let ident = ident.ok_or_else(|| ImportError::missing_column("ident".to_string()))?;
+    let airport_type = airport_type.ok_or_else(|| ImportError::missing_column("type".to_string()))?;
+    let last_updated = columns.iter().position(|c| *c == "last_updated");
+
+    // Known airports: ident -> (type, last_updated)
+    let known: HashMap<String, (String, Option<String>)> = conn
+        .prepare("SELECT ident, type, last_updated FROM airports")?
+        .query_map([], |row| Ok((row.get(0)?, (row.get::<_, Option<String>>(1)?.unwrap_or_default(), row.get(2)?))))?
+        .collect::<Result<_, _>>()?;
+
+    let mut report = AirportImportReport::default();
+    let transaction = conn.transaction()?;
+    {
+        let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+        let updates: Vec<String> = columns
+            .iter()
+            .filter(|column| **column != "ident")
+            .map(|column| format!("{} = excluded.{}", column, column))
+            .chain(["search_text = NULL".to_string()])
+            .collect();
+        let mut upsert = transaction.prepare(&format!(
+            "INSERT INTO airports ({}) VALUES ({}) ON CONFLICT(ident) DO UPDATE SET {}",
+            columns.join(", "),
+            placeholders.join(", "),
+            updates.join(", ")
+        ))?;
+        for record in reader.records() {
+            let record = record?;
+            let values: Vec<&str> = positions.iter().map(|p| record.get(*p).unwrap_or("")).collect();
+            let previous = known.get(values[ident]);
+            let closing = values[airport_type] == "closed" && previous.is_some_and(|(t, _)| t != "closed");
+            let selected = AirportImportFilter::accepts(&filter.types, values[airport_type])
+                && iso_country.is_none_or(|i| AirportImportFilter::accepts(&filter.countries, values[i]))
+                && continent.is_none_or(|i| AirportImportFilter::accepts(&filter.continents, values[i]));
+            if !selected && !closing {
+                continue;
+            }
+            match previous {
+                None => report.inserted += 1,
+                Some((_, known_update)) => {
+                    let changed = match (last_updated, known_update) {
+                        (Some(i), Some(known_update)) => values[i] != known_update,
+                        _ => true,
+                    };
+                    if !changed && !closing {
+                        report.unchanged += 1;
+                        continue;
+                    }
+                    report.updated += 1;
+                    if closing {
+                        report.closed += 1;
+                    }
+                }
+            }
+            upsert.execute(rusqlite::params_from_iter(values))?;
+        }
+    }
+    transaction.commit()?;
+    search::index_airports(db_path)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navigation::airport::{Airport, AirportType};
+    use crate::navigation::runway::{Frequency, Runway};
+
+    /// Copy of the test database and a CSV file next to it, removed by `clean`
+    fn fixture(name: &str, csv: &str) -> (String, String) {
+        let db_path = std::env::temp_dir().join(format!("flight_planner_import_{}.db", name));
+        let csv_path = std::env::temp_dir().join(format!("flight_planner_import_{}.csv", name));
+        std::fs::copy("data/airports.db", &db_path).unwrap();
+        std::fs::write(&csv_path, csv).unwrap();
+        (db_path.to_str().unwrap().to_string(), csv_path.to_str().unwrap().to_string())
+    }
+
+    fn clean(db_path: &str, csv_path: &str) {
+        std::fs::remove_file(db_path).unwrap();
+        std::fs::remove_file(csv_path).unwrap();
+    }
+
+    #[test]
+    fn imports_runways_of_known_airports() {
+        let csv = format!(
+            "{}\n{}\n{}\n{}\n",
+            RUNWAYS_CSV_COLUMNS.join(","),
+            "1,1,LFPN,3608,98,ASP,1,0,07,48.75,2.09,538,66.5,,25,48.75,2.12,538,246.5,",
+            "2,1,LFPN,1000,60,GRS,0,1,11,48.75,2.10,538,,,29,48.75,2.11,538,,",
+            "3,9,ZZZZ,2000,60,ASP,0,0,09,0,0,0,90,,27,0,0,0,270,",
+        );
+        let (db_path, csv_path) = fixture("runways", &csv);
+        assert_eq!(import_runways(&db_path, &csv_path).unwrap(), 2);
+        // Importing again replaces the rows
+        assert_eq!(import_runways(&db_path, &csv_path).unwrap(), 2);
+
+        let runways = Runway::from_database(&db_path, "LFPN").unwrap();
+        assert_eq!(runways.len(), 2);
+        let paved = runways.iter().find(|r| r.ident() == "07/25").unwrap();
+        assert_eq!(paved.length, Some(3608.0));
+        assert!(paved.lighted && !paved.closed && paved.is_paved());
+        assert_eq!(paved.low_end.heading, Some(66.5));
+        let grass = runways.iter().find(|r| r.ident() == "11/29").unwrap();
+        assert!(grass.closed && !grass.is_paved());
+        assert_eq!(grass.low_end.heading, None);
+        assert!(Runway::from_database(&db_path, "ZZZZ").unwrap().is_empty());
+        clean(&db_path, &csv_path);
+    }
+
+    #[test]
+    fn imports_frequencies_of_known_airports() {
+        let csv = "id,airport_ref,airport_ident,type,description,frequency_mhz\n\
+                   10,1,LFPN,TWR,TOUSSUS TOUR,120.3\n\
+                   11,1,LFPN,ATIS,,128.65\n\
+                   12,9,ZZZZ,TWR,,118.1\n";
+        let (db_path, csv_path) = fixture("frequencies", csv);
+        assert_eq!(import_frequencies(&db_path, &csv_path).unwrap(), 2);
+        let frequencies = Frequency::from_database(&db_path, "LFPN").unwrap();
+        assert_eq!(frequencies.len(), 2);
+        let tower = frequencies.iter().find(|f| f.frequency_type == "TWR").unwrap();
+        assert_eq!(tower.description, "TOUSSUS TOUR");
+        assert_eq!(tower.mhz, 120.3);
+        clean(&db_path, &csv_path);
+    }
+
+    #[test]
+    fn imports_airports_with_filter_and_report() {
+        let csv = "id,ident,type,name,latitude_deg,longitude_deg,elevation_ft,continent,iso_country,municipality,icao_code,last_updated\n\
+                   4188,LFPN,medium_airport,Toussus-le-Noble,48.7519,2.1061,538,EU,FR,Toussus,LFPN,2024-06-22T13:28:33+00:00\n\
+                   4169,LFOB,medium_airport,Beauvais-Tille Renamed,49.4544,2.1128,359,EU,FR,Beauvais,LFOB,2025-01-01T00:00:00+00:00\n\
+                   28981,LFPZ,closed,Saint-Cyr-l'Ecole,48.8114,2.0747,371,EU,FR,Saint-Cyr,,2025-01-01T00:00:00+00:00\n\
+                   990001,LF99,small_airport,Nouveau terrain,47.5,3.5,600,EU,FR,Nulle Part,,2025-01-01T00:00:00+00:00\n\
+                   990002,EDXX,small_airport,Flugplatz,50.0,8.0,400,EU,DE,Irgendwo,,2025-01-01T00:00:00+00:00\n\
+                   990003,LF98,heliport,Helistation,47.6,3.6,600,EU,FR,Nulle Part,,2025-01-01T00:00:00+00:00\n\
+                   990004,LF97,closed,Ancien terrain,47.7,3.7,600,EU,FR,Nulle Part,,2025-01-01T00:00:00+00:00\n\
+                   990005,LF96,small_airport,Terrain lointain,-21.0,55.5,100,AF,FR,Saint-Denis,,2025-01-01T00:00:00+00:00\n";
+        let (db_path, csv_path) = fixture("airports", csv);
+        let filter = AirportImportFilter {
+            countries: vec!["fr".to_string()],
+            continents: vec!["EU".to_string()],
+            types: vec!["small_airport".to_string(), "medium_airport".to_string()],
+        };
+
+        let report = import_airports(&db_path, &csv_path, &filter).unwrap();
+        assert_eq!((report.inserted, report.updated, report.unchanged, report.closed), (1, 2, 1, 1));
+        let airport = |ident: &str| Airport::from_db(&db_path, ident).unwrap().into_iter().next();
+        assert_eq!(airport("LFOB").unwrap().name, "Beauvais-Tille Renamed");
+        // Closing an airport bypasses the type filter
+        assert!(matches!(airport("LFPZ").unwrap().airport_type, AirportType::closed));
+        assert_eq!(airport("LF99").unwrap().name, "Nouveau terrain");
+        for ident in ["EDXX", "LF98", "LF97", "LF96"] {
+            assert!(airport(ident).is_none(), "{} should be filtered out", ident);
+        }
+        // New airports are searchable and indexed by position
+        assert!(Airport::search(&db_path, "nouveau terrain", &Default::default()).unwrap().iter().any(|a| a.oaci_code == "LF99"));
+        assert!(Airport::in_area(&db_path, 47.4, 47.6, 3.4, 3.6).unwrap().iter().any(|a| a.oaci_code == "LF99"));
+
+        // Nothing changed since the first import
+        let report = import_airports(&db_path, &csv_path, &filter).unwrap();
+        assert_eq!((report.inserted, report.updated, report.unchanged, report.closed), (0, 0, 3, 0));
+        clean(&db_path, &csv_path);
+    }
+
+    #[test]
+    fn rejects_a_file_without_the_expected_columns() {
+        let (db_path, csv_path) = fixture("missing_column", "id,airport_ident,type\n10,LFPN,TWR\n");
+        assert!(matches!(import_frequencies(&db_path, &csv_path), Err(ImportError::missing_column(column)) if column == "airport_ref"));
+        clean(&db_path, &csv_path);
+    }
+}
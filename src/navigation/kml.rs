@@ -0,0 +1,327 @@
+use std::fs;
+
+use roxmltree::{Document, Node};
+
+use super::coordinates::CoordinateFormat;
+use super::error::RouteError;
+use super::flightplan::Navigation;
+use super::geographics::{LegType, NavPoint, KM_TO_NM};
+use super::gpx::{escape_xml, FEET_PER_METER};
+use super::levels;
+use super::profile::VerticalProfile;
+use super::route::RoutePoint;
+
+/// Reads KML coordinates, "lon,lat[,alt]" tuples separated by spaces, altitudes in meters
+fn read_coordinates(text: &str) -> Vec<(f64, f64, Option<f64>)> {
+    text.split_whitespace()
+        .filter_map(|tuple| {
+            let mut values = tuple.split(',').map(|v| v.trim().parse::<f64>());
+            let longitude = values.next()?.ok()?;
+            let latitude = values.next()?.ok()?;
+            let altitude = values.next().and_then(Result::ok);
+            Some((longitude, latitude, altitude))
+        })
+        .collect()
+}
+
+fn coordinates_of<'a>(node: Node<'a, 'a>) -> Option<&'a str> {
+    node.descendants().find(|n| n.has_tag_name("coordinates")).and_then(|n| n.text())
+}
+
+/// Reads the route of a KML file: the first LineString, followed by those continuing it, if any,
+/// its vertices taking the name of a Point placemark at the same position, or else the Point
+/// placemarks in document order. When the route is made of several LineStrings, one per leg as
+/// written by `to_kml`, only their ends are kept: the vertices in between draw the path of the leg.
+/// Altitudes of 0 are taken as clamped to the ground, i.e. unknown.
+pub fn read_kml(path: &str) -> Result<Vec<RoutePoint>, RouteError> {
+    let text = fs::read_to_string(path)?;
+    let document = Document::parse(&text)?;
+
+    let mut placemarks: Vec<RoutePoint> = Vec::new();
+    let mut line: Option<Vec<(f64, f64, Option<f64>)>> = None;
+    let mut legs = 0;
+    for placemark in document.descendants().filter(|n| n.has_tag_name("Placemark")) {
+        let name = placemark
+            .children()
+            .find(|n| n.has_tag_name("name"))
+            .and_then(|n| n.text())
+            .unwrap_or("")
+            .trim()
+            .to_string();
+        if let Some(point) = placemark.descendants().find(|n| n.has_tag_name("Point")) {
+            if let Some(&(longitude, latitude, altitude)) = coordinates_of(point).map(read_coordinates).unwrap_or_default().first() {
+                placemarks.push(RoutePoint {
+                    point: NavPoint::new(name, latitude, longitude),
+                    altitude: altitude.filter(|a| *a != 0.0).map(|a| a * FEET_PER_METER),
+                });
+            }
+        } else if let Some(line_string) = placemark.descendants().find(|n| n.has_tag_name("LineString")) {
+            let coordinates = coordinates_of(line_string).map(read_coordinates).unwrap_or_default();
+            let ends = |line: &[(f64, f64, Option<f64>)]| -> Vec<(f64, f64, Option<f64>)> {
+                line.first().into_iter().chain(line.last().filter(|_| line.len() > 1)).copied().collect()
+            };
+            match &mut line {
+                None => {
+                    line = Some(coordinates);
+                    legs = 1;
+                }
+                // One line per leg: append the ends of the lines starting where the route ends
+                Some(route) => {
+                    let same = |a: &(f64, f64, Option<f64>), b: &(f64, f64, Option<f64>)| (a.0 - b.0).abs() < 1e-5 && (a.1 - b.1).abs() < 1e-5;
+                    if let (Some(end), Some(start)) = (route.last(), coordinates.first()) {
+                        if same(end, start) {
+                            if legs == 1 {
+                                *route = ends(route);
+                            }
+                            route.extend(ends(&coordinates).into_iter().skip(1));
+                            legs += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let Some(line) = line else {
+        return Ok(placemarks);
+    };
+    Ok(line
+        .into_iter()
+        .map(|(longitude, latitude, altitude)| {
+            let name = placemarks
+                .iter()
+                .find(|p| (p.point.latitude - latitude).abs() < 1e-5 && (p.point.longitude - longitude).abs() < 1e-5)
+                .map(|p| p.point.name.clone())
+                .unwrap_or_default();
+            RoutePoint {
+                point: NavPoint::new(name, latitude, longitude),
+                altitude: altitude.filter(|a| *a != 0.0).map(|a| a * FEET_PER_METER),
+            }
+        })
+        .collect())
+}
+
+/// Longest segment of a rhumb line leg drawn as a great circle, km
+const RHUMB_LINE_SPACING: f64 = 10.0;
+
+const AIRPORT_ICON: &str = "http://maps.google.com/mapfiles/kml/shapes/airports.png";
+const WAYPOINT_ICON: &str = "http://maps.google.com/mapfiles/kml/shapes/placemark_circle.png";
+
+/// Icon styles of the placemarks (id, icon, color as aabbggrr) and line styles (id, color, width)
+const ICON_STYLES: [(&str, &str, &str); 4] = [
+    ("departure", AIRPORT_ICON, "ff00c000"),
+    ("destination", AIRPORT_ICON, "ff0000e0"),
+    ("alternate", AIRPORT_ICON, "ff00a5ff"),
+    ("waypoint", WAYPOINT_ICON, "ff00ffff"),
+];
+const LINE_STYLES: [(&str, &str, u32); 2] = [("leg", "ff0000ff", 3), ("profile", "ffff8000", 2)];
+
+/// KML coordinates tuple, altitude in ft converted to meters
+fn coordinates(point: &NavPoint, altitude: Option<f64>) -> String {
+    format!("{:.6},{:.6},{:.0}", point.longitude, point.latitude, altitude.unwrap_or(0.0) / FEET_PER_METER)
+}
+
+fn placemark(kml: &mut String, name: &str, style: &str, description: &str, point: &NavPoint) {
+    kml.push_str(&format!(
+        "      <Placemark>\n        <name>{}</name>\n        <styleUrl>#{}</styleUrl>\n        <description><![CDATA[{}]]></description>\n        <Point><coordinates>{}</coordinates></Point>\n      </Placemark>\n",
+        escape_xml(name),
+        style,
+        description.replace("]]>", "]] >"),
+        coordinates(point, None)
+    ));
+}
+
+/// Renders a navigation as KML for Google Earth:
+/// - each leg as a line extruded to the ground at its planned altitude, following its great circle or rhumb line,
+/// - each waypoint as a placemark, its balloon showing the nav log of the leg leading to it,
+/// - departure, destination and `alternates` with their own styles,
+/// - the climb and descent path of `profile` as a 3D line, with its pseudo-waypoints.
+pub fn to_kml(navigation: &Navigation, profile: Option<&VerticalProfile>, alternates: &[NavPoint]) -> String {
+    let route_name = format!("{} - {}", navigation.start.name, navigation.end.name);
+    let mut kml = String::new();
+    kml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n  <Document>\n");
+    kml.push_str(&format!("    <name>{}</name>\n", escape_xml(&route_name)));
+    for (id, icon, color) in ICON_STYLES {
+        kml.push_str(&format!(
+            "    <Style id=\"{}\"><IconStyle><color>{}</color><Icon><href>{}</href></Icon></IconStyle></Style>\n",
+            id, color, icon
+        ));
+    }
+    for (id, color, width) in LINE_STYLES {
+        kml.push_str(&format!(
+            "    <Style id=\"{}\"><LineStyle><color>{}</color><width>{}</width></LineStyle><PolyStyle><color>40{}</color></PolyStyle></Style>\n",
+            id, color, width, &color[2..]
+        ));
+    }
+
+    // Legs, rhumb lines drawn through intermediate points as Google Earth joins vertices along great circles
+    kml.push_str("    <Folder>\n      <name>Legs</name>\n");
+    let mut altitude = None;
+    for branch in &navigation.branches {
+        let (Some(from), Some(to)) = (&branch.from, &branch.to) else {
+            continue;
+        };
+        altitude = branch.altitude.or(altitude);
+        let (mode, extrude) = if altitude.is_some() { ("absolute", 1) } else { ("clampToGround", 0) };
+        let path: Vec<String> = match branch.leg_type {
+            LegType::great_circle => vec![coordinates(from, altitude), coordinates(to, altitude)],
+            LegType::rhumb_line => branch.track(RHUMB_LINE_SPACING).iter().map(|p| coordinates(p, altitude)).collect(),
+        };
+        kml.push_str(&format!(
+            "      <Placemark>\n        <name>{}</name>\n        <styleUrl>#leg</styleUrl>\n        <LineString><extrude>{}</extrude><tessellate>1</tessellate><altitudeMode>{}</altitudeMode><coordinates>{}</coordinates></LineString>\n      </Placemark>\n",
+            escape_xml(&branch.name()),
+            extrude,
+            mode,
+            path.join(" ")
+        ));
+    }
+    kml.push_str("    </Folder>\n");
+
+    // Waypoints, with the nav log of the leg leading to them
+    kml.push_str("    <Folder>\n      <name>Waypoints</name>\n");
+    placemark(
+        &mut kml,
+        &navigation.start.name,
+        "departure",
+        &format!("<b>Departure</b><br/>{}", escape_xml(&navigation.start.position(CoordinateFormat::dms))),
+        &navigation.start,
+    );
+    let mut altitude = None;
+    for (index, branch) in navigation.branches.iter().enumerate() {
+        let Some(to) = &branch.to else {
+            continue;
+        };
+        altitude = branch.altitude.or(altitude);
+        let mut rows = vec![format!("<tr><td>Position</td><td>{}</td></tr>", escape_xml(&to.position(CoordinateFormat::dms)))];
+        if let Some(course) = branch.course {
+            rows.push(format!("<tr><td>Course</td><td>{:03.0}° true</td></tr>", course.rem_euclid(360.0)));
+        }
+        if let Some(distance) = branch.distance {
+            rows.push(format!("<tr><td>Distance</td><td>{:.1} NM</td></tr>", distance * KM_TO_NM));
+        }
+        if let Some(altitude) = altitude {
+            rows.push(format!("<tr><td>Altitude</td><td>{}</td></tr>", levels::format_level(altitude, levels::TRANSITION_ALTITUDE)));
+        }
+        if let Some(time) = branch.time {
+            rows.push(format!("<tr><td>Time</td><td>{}'</td></tr>", (time.num_seconds() as f64 / 60.0).round()));
+        }
+        let last = index + 1 == navigation.branches.len();
+        let description = format!("<b>{}</b><table>{}</table>", escape_xml(&branch.name()), rows.join(""));
+        placemark(&mut kml, &to.name, if last { "destination" } else { "waypoint" }, &description, to);
+    }
+    for alternate in alternates {
+        placemark(
+            &mut kml,
+            &alternate.name,
+            "alternate",
+            &format!("<b>Alternate</b><br/>{}", escape_xml(&alternate.position(CoordinateFormat::dms))),
+            alternate,
+        );
+    }
+    kml.push_str("    </Folder>\n");
+
+    // Altitude profile
+    if let Some(profile) = profile.filter(|p| !p.segments.is_empty()) {
+        kml.push_str("    <Folder>\n      <name>Profile</name>\n");
+        let mut path = vec![coordinates(&profile.segments[0].from, Some(profile.segments[0].start_altitude))];
+        path.extend(profile.segments.iter().map(|s| coordinates(&s.to, Some(s.end_altitude))));
+        kml.push_str(&format!(
+            "      <Placemark>\n        <name>Profile</name>\n        <styleUrl>#profile</styleUrl>\n        <LineString><tessellate>1</tessellate><altitudeMode>absolute</altitudeMode><coordinates>{}</coordinates></LineString>\n      </Placemark>\n",
+            path.join(" ")
+        ));
+        for point in &profile.pseudo_waypoints {
+            placemark(&mut kml, &point.name, "waypoint", &escape_xml(&point.name), point);
+        }
+        kml.push_str("    </Folder>\n");
+    }
+
+    kml.push_str("  </Document>\n</kml>\n");
+    kml
+}
+
+/// Writes a navigation to a KML file, see `to_kml`
+pub fn export_kml(navigation: &Navigation, profile: Option<&VerticalProfile>, alternates: &[NavPoint], path: &str) -> Result<(), std::io::Error> {
+    fs::write(path, to_kml(navigation, profile, alternates))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navigation::flightplan::{NavBranch, Wind};
+    use crate::navigation::geographics::EarthModel;
+
+    fn navigation() -> Navigation {
+        let points = [
+            NavPoint::new("LFPN".to_string(), 48.7519, 2.1061),
+            NavPoint::new("R&D <site>".to_string(), 49.5, 3.5),
+            NavPoint::new("LFOB".to_string(), 49.4544, 2.1128),
+        ];
+        let wind = Wind { speed: 0.0, direction: 0.0 };
+        let mut first = NavBranch::between(points[0].clone(), points[1].clone(), wind, Some(2500.0), EarthModel::spherical);
+        first.set_leg_type(LegType::rhumb_line);
+        let second = NavBranch::between(points[1].clone(), points[2].clone(), wind, None, EarthModel::spherical);
+        Navigation::new(points[0].clone(), points[2].clone(), vec![first, second])
+    }
+
+    /// Coordinates of the LineString of the leg placemark named `name`
+    fn leg_coordinates<'a>(kml: &'a str, name: &str) -> Vec<&'a str> {
+        let start = kml.find(&format!("<name>{}</name>", name)).unwrap();
+        let line = &kml[start..];
+        let coordinates = &line[line.find("<coordinates>").unwrap() + 13..line.find("</coordinates>").unwrap()];
+        coordinates.split_whitespace().collect()
+    }
+
+    #[test]
+    fn legs_are_extruded_along_their_path() {
+        let navigation = navigation();
+        let kml = to_kml(&navigation, None, &[NavPoint::new("LFPT".to_string(), 49.0967, 2.0408)]);
+        assert!(Document::parse(&kml).is_ok());
+
+        // The rhumb line is drawn through points at most 10 km apart, at 2500 ft (762 m)
+        let rhumb = leg_coordinates(&kml, "LFPN-R&amp;D &lt;site&gt;");
+        assert_eq!(rhumb.len() as f64 - 1.0, (navigation.branches[0].distance.unwrap() / RHUMB_LINE_SPACING).ceil());
+        assert!(rhumb.iter().all(|c| c.ends_with(",762")));
+        assert_eq!(rhumb[0], "2.106100,48.751900,762");
+        // The second leg keeps the altitude of the first one
+        let great_circle = leg_coordinates(&kml, "R&amp;D &lt;site&gt;-LFOB");
+        assert_eq!(great_circle, ["3.500000,49.500000,762", "2.112800,49.454400,762"]);
+        assert_eq!(kml.matches("<extrude>1</extrude>").count(), 2);
+
+        assert!(kml.contains("<styleUrl>#departure</styleUrl>"));
+        assert!(kml.contains("<styleUrl>#destination</styleUrl>"));
+        assert!(kml.contains("<name>LFPT</name>\n        <styleUrl>#alternate</styleUrl>"));
+    }
+
+    #[test]
+    fn exported_route_reads_back() {
+        let path = std::env::temp_dir().join("flight_planner_route.kml");
+        let path = path.to_str().unwrap();
+        export_kml(&navigation(), None, &[], path).unwrap();
+        let points = read_kml(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let names: Vec<&str> = points.iter().map(|p| p.point.name.as_str()).collect();
+        assert_eq!(names, ["LFPN", "R&D <site>", "LFOB"]);
+        assert!(points.iter().all(|p| (p.altitude.unwrap() - 2500.0).abs() < 2.0));
+    }
+
+    #[test]
+    fn single_line_keeps_all_its_vertices() {
+        let kml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<kml xmlns="http://www.opengis.net/kml/2.2"><Document>
+  <Placemark><name>Start</name><Point><coordinates>2.1061,48.7519,0</coordinates></Point></Placemark>
+  <Placemark><name>Route</name><LineString><coordinates>2.1061,48.7519,0 2.5,49.0,0 2.1128,49.4544,914.4</coordinates></LineString></Placemark>
+</Document></kml>"#;
+        let path = std::env::temp_dir().join("flight_planner_line.kml");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, kml).unwrap();
+        let points = read_kml(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[0].point.name, "Start");
+        assert_eq!(points[1].point.name, "");
+        assert_eq!(points[0].altitude, None);
+        assert!((points[2].altitude.unwrap() - 3000.0).abs() < 0.1);
+    }
+}
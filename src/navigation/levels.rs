@@ -0,0 +1,115 @@
+use super::error::LevelError;
+
+/// Transition altitude in France, in ft
+pub const TRANSITION_ALTITUDE: f64 = 5000.0;
+/// VFR cruising levels only apply above this altitude, in ft
+pub const SEMICIRCULAR_MIN_ALTITUDE: f64 = 3000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlightRules {
+    /// Visual flight rules
+    vfr,
+    /// Instrument flight rules
+    ifr,
+}
+
+/// Lowest cruising level allowed by the semicircular rule for a magnetic track, in ft.
+/// Allowed levels are then every 2000 ft above it.
+fn semicircular_base(magnetic_track: f64, rules: FlightRules) -> f64 {
+    let eastbound = magnetic_track.rem_euclid(360.0) < 180.0;
+    match (rules, eastbound) {
+        (FlightRules::ifr, true) => 1000.0,
+        (FlightRules::ifr, false) => 2000.0,
+        (FlightRules::vfr, true) => 3500.0,
+        (FlightRules::vfr, false) => 4500.0,
+    }
+}
+
+/// Checks an altitude in ft against the semicircular cruising levels for a magnetic track
+/// (ICAO Annex 2 appendix 3, below FL290).
+/// VFR levels at or below 3000 ft are not constrained.
+pub fn is_compliant(altitude: f64, magnetic_track: f64, rules: FlightRules) -> bool {
+    if rules == FlightRules::vfr && altitude <= SEMICIRCULAR_MIN_ALTITUDE {
+        return true;
+    }
+    let base = semicircular_base(magnetic_track, rules);
+    altitude >= base && ((altitude - base) % 2000.0).abs() < 1.0
+}
+
+/// Returns the cruising level nearest to `altitude` that complies with the semicircular rule
+pub fn nearest_compliant(altitude: f64, magnetic_track: f64, rules: FlightRules) -> f64 {
+    if is_compliant(altitude, magnetic_track, rules) {
+        return altitude;
+    }
+    let base = semicircular_base(magnetic_track, rules);
+    let steps = ((altitude - base) / 2000.0).round().max(0.0);
+    base + steps * 2000.0
+}
+
+/// Formats an altitude as written on a nav log: `2500 ft` at or below the transition
+/// altitude, `FL065` above it.
+pub fn format_level(altitude: f64, transition_altitude: f64) -> String {
+    if altitude > transition_altitude {
+        format!("FL{:03}", (altitude / 100.0).round() as i32)
+    } else {
+        format!("{:.0} ft", altitude)
+    }
+}
+
+/// Parses a level entered by the pilot into feet: `FL065`, `F065`, `2500` or `2500ft`.
+pub fn parse_level(level: &str) -> Result<f64, LevelError> {
+    let level = level.trim().to_uppercase();
+    let invalid = || LevelError::invalid_level(level.clone());
+    if let Some(flight_level) = level.strip_prefix("FL").or_else(|| level.strip_prefix('F')) {
+        let flight_level: u32 = flight_level.trim().parse().map_err(|_| invalid())?;
+        return Ok(flight_level as f64 * 100.0);
+    }
+    let altitude = level.strip_suffix("FT").unwrap_or(&level).trim();
+    let altitude: f64 = altitude.parse().map_err(|_| invalid())?;
+    if altitude < 0.0 {
+        return Err(invalid());
+    }
+    Ok(altitude)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vfr_levels() {
+        assert!(is_compliant(2500.0, 270.0, FlightRules::vfr));
+        assert!(is_compliant(3500.0, 90.0, FlightRules::vfr));
+        assert!(is_compliant(4500.0, 270.0, FlightRules::vfr));
+        assert!(is_compliant(6500.0, 180.0, FlightRules::vfr));
+    }
+
+    #[test]
+    fn vfr_wrong_side_plus_500() {
+        // Eastbound at a westbound level, and the other way round
+        assert!(!is_compliant(4500.0, 90.0, FlightRules::vfr));
+        assert_eq!(nearest_compliant(4500.0, 90.0, FlightRules::vfr), 5500.0);
+        assert!(!is_compliant(5500.0, 270.0, FlightRules::vfr));
+        assert_eq!(nearest_compliant(5500.0, 270.0, FlightRules::vfr), 6500.0);
+        assert!(!is_compliant(3500.0, 359.0, FlightRules::vfr));
+        assert_eq!(nearest_compliant(3500.0, 359.0, FlightRules::vfr), 4500.0);
+    }
+
+    #[test]
+    fn ifr_levels_below_3000_ft() {
+        assert!(is_compliant(1000.0, 90.0, FlightRules::ifr));
+        assert!(is_compliant(2000.0, 270.0, FlightRules::ifr));
+        assert!(!is_compliant(2000.0, 90.0, FlightRules::ifr));
+        assert!(!is_compliant(2500.0, 270.0, FlightRules::ifr));
+        assert_eq!(nearest_compliant(2000.0, 90.0, FlightRules::ifr), 3000.0);
+    }
+
+    #[test]
+    fn levels_format_and_parse() {
+        assert_eq!(format_level(2500.0, TRANSITION_ALTITUDE), "2500 ft");
+        assert_eq!(format_level(6500.0, TRANSITION_ALTITUDE), "FL065");
+        assert_eq!(parse_level("FL065").unwrap(), 6500.0);
+        assert_eq!(parse_level("2500ft").unwrap(), 2500.0);
+        assert!(parse_level("high").is_err());
+    }
+}
@@ -0,0 +1,288 @@
+use std::error::Error;
+use std::f64::consts::FRAC_PI_4;
+use std::path::Path;
+
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters::style::full_palette::{GREY, PURPLE};
+
+use super::airport::{Airport, AirportType};
+use super::error::MapError;
+use super::flightplan::Navigation;
+use super::geographics::{NavPoint, KM_TO_NM};
+
+const EARTH_RADIUS_M: f64 = 6371000.0;
+const METERS_PER_INCH: f64 = 0.0254;
+/// Spacing of the graticule lines, in degrees
+const GRATICULE_STEP: f64 = 0.5;
+/// Largest chart rendered, in pixels: an A0 sheet at 300 dpi
+const MAX_PIXELS: f64 = 140_000_000.0;
+/// Longest straight segment drawn along a leg, in km
+const TRACK_SPACING: f64 = 5.0;
+
+/// Lambert conformal conic projection on a sphere, as used by ICAO 1:500 000 VFR charts
+#[derive(Debug, Clone)]
+pub struct LambertConformal {
+    n: f64,
+    f: f64,
+    rho0: f64,
+    central_meridian: f64,
+}
+
+impl LambertConformal {
+    /// Creates a projection with two standard parallels, centered on `origin_latitude` and `central_meridian` (degrees)
+    pub fn new(parallel1: f64, parallel2: f64, origin_latitude: f64, central_meridian: f64) -> LambertConformal {
+        let phi1 = parallel1.to_radians();
+        let phi2 = parallel2.to_radians();
+        let t = |phi: f64| (FRAC_PI_4 + phi / 2.0).tan();
+        let n = if (parallel1 - parallel2).abs() < 1e-9 {
+            phi1.sin()
+        } else {
+            (phi1.cos() / phi2.cos()).ln() / (t(phi2) / t(phi1)).ln()
+        };
+        let f = phi1.cos() * t(phi1).powf(n) / n;
+        let rho0 = EARTH_RADIUS_M * f / t(origin_latitude.to_radians()).powf(n);
+        LambertConformal {
+            n,
+            f,
+            rho0,
+            central_meridian,
+        }
+    }
+
+    /// Projects a point in degrees to meters on the chart (x east, y north)
+    pub fn project(&self, latitude: f64, longitude: f64) -> (f64, f64) {
+        let rho = EARTH_RADIUS_M * self.f / (FRAC_PI_4 + latitude.to_radians() / 2.0).tan().powf(self.n);
+        let theta = self.n * (longitude - self.central_meridian).to_radians();
+        (rho * theta.sin(), self.rho0 - rho * theta.cos())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MapFormat {
+    png,
+    svg,
+}
+
+/// Settings of the rendered chart
+#[derive(Debug, Clone)]
+pub struct MapConfig {
+    pub scale: f64,  // chart scale denominator, 500000 for 1:500 000
+    pub dpi: f64,    // printing resolution, so the chart can be printed at true scale
+    pub margin: f64, // km around the route
+    pub database: String,
+}
+
+impl MapConfig {
+    pub fn new(database: &str) -> MapConfig {
+        MapConfig {
+            scale: 500000.0,
+            dpi: 150.0,
+            margin: 15.0,
+            database: database.to_string(),
+        }
+    }
+}
+
+/// Projection and pixel frame of a chart
+struct Chart {
+    projection: LambertConformal,
+    pixels_per_meter: f64,
+    min_x: f64,
+    max_y: f64,
+}
+
+impl Chart {
+    fn pixel(&self, latitude: f64, longitude: f64) -> (i32, i32) {
+        let (x, y) = self.projection.project(latitude, longitude);
+        (
+            ((x - self.min_x) * self.pixels_per_meter).round() as i32,
+            ((self.max_y - y) * self.pixels_per_meter).round() as i32,
+        )
+    }
+}
+
+fn route_points(navigation: &Navigation) -> Vec<&NavPoint> {
+    let mut points = vec![&navigation.start];
+    for branch in &navigation.branches {
+        if let Some(to) = &branch.to {
+            points.push(to);
+        }
+    }
+    if points.len() == 1 {
+        points.push(&navigation.end);
+    }
+    points
+}
+
+/// Renders a navigation on a Lambert conformal conic chart, with the airports around the route,
+/// a graticule and a scale bar, to a PNG or SVG file.
+///
+/// The image is sized for `config.dpi`, so that once printed at that resolution it matches
+/// a paper chart at `config.scale` and can be laid over it.
+/// Charts larger than an A0 sheet at 300 dpi are refused with `MapError::too_large`.
+pub fn render_navigation(navigation: &Navigation, path: &str, format: MapFormat, config: &MapConfig) -> Result<(), Box<dyn Error>> {
+    let points = route_points(navigation);
+    let min_lat = points.iter().map(|p| p.latitude).fold(f64::INFINITY, f64::min);
+    let max_lat = points.iter().map(|p| p.latitude).fold(f64::NEG_INFINITY, f64::max);
+    let min_lon = points.iter().map(|p| p.longitude).fold(f64::INFINITY, f64::min);
+    let max_lon = points.iter().map(|p| p.longitude).fold(f64::NEG_INFINITY, f64::max);
+
+    // Standard parallels at 1/6 and 5/6 of the latitude range
+    let span = (max_lat - min_lat).max(0.1);
+    let center_lat = (min_lat + max_lat) / 2.0;
+    let projection = LambertConformal::new(
+        center_lat - span / 3.0,
+        center_lat + span / 3.0,
+        center_lat,
+        (min_lon + max_lon) / 2.0,
+    );
+
+    let margin_lat = config.margin / 111.0;
+    let margin_lon = config.margin / (111.0 * center_lat.to_radians().cos());
+    let (south, north) = (min_lat - margin_lat, max_lat + margin_lat);
+    let (west, east) = (min_lon - margin_lon, max_lon + margin_lon);
+    let airports = Airport::in_area(&config.database, south, north, west, east)?;
+
+    let corners: Vec<(f64, f64)> = points
+        .iter()
+        .map(|p| projection.project(p.latitude, p.longitude))
+        .collect();
+    let margin = config.margin * 1000.0;
+    let min_x = corners.iter().map(|c| c.0).fold(f64::INFINITY, f64::min) - margin;
+    let max_x = corners.iter().map(|c| c.0).fold(f64::NEG_INFINITY, f64::max) + margin;
+    let min_y = corners.iter().map(|c| c.1).fold(f64::INFINITY, f64::min) - margin;
+    let max_y = corners.iter().map(|c| c.1).fold(f64::NEG_INFINITY, f64::max) + margin;
+
+    let pixels_per_meter = config.dpi / METERS_PER_INCH / config.scale;
+    let (width, height) = (((max_x - min_x) * pixels_per_meter).ceil(), ((max_y - min_y) * pixels_per_meter).ceil());
+    if width.is_nan() || height.is_nan() || width * height > MAX_PIXELS {
+        return Err(MapError::too_large(width as u64, height as u64).into());
+    }
+    let size = (width as u32, height as u32);
+    let chart = Chart {
+        projection,
+        pixels_per_meter,
+        min_x,
+        max_y,
+    };
+    let bounds = (south, north, west, east);
+
+    match format {
+        MapFormat::png => draw_chart(BitMapBackend::new(Path::new(path), size).into_drawing_area(), &chart, navigation, &airports, bounds, config),
+        MapFormat::svg => draw_chart(SVGBackend::new(Path::new(path), size).into_drawing_area(), &chart, navigation, &airports, bounds, config),
+    }
+}
+
+fn draw_chart<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    chart: &Chart,
+    navigation: &Navigation,
+    airports: &[Airport],
+    (south, north, west, east): (f64, f64, f64, f64),
+    config: &MapConfig,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+    let label = ("sans-serif", 12).into_font().color(&BLACK);
+    let small_label = ("sans-serif", 10).into_font().color(&GREY);
+
+    // Graticule
+    let first_lat = (south / GRATICULE_STEP).ceil() as i32;
+    let last_lat = (north / GRATICULE_STEP).floor() as i32;
+    let first_lon = (west / GRATICULE_STEP).ceil() as i32;
+    let last_lon = (east / GRATICULE_STEP).floor() as i32;
+    for i in first_lat..=last_lat {
+        let lat = i as f64 * GRATICULE_STEP;
+        let line: Vec<(i32, i32)> = (0..=50)
+            .map(|k| chart.pixel(lat, west + (east - west) * k as f64 / 50.0))
+            .collect();
+        let start = line[0];
+        root.draw(&PathElement::new(line, GREY.stroke_width(1)))?;
+        let hemisphere = if lat < 0.0 { 'S' } else { 'N' };
+        root.draw(&Text::new(format!("{:02}°{:02}'{}", lat.abs().trunc(), (lat.abs().fract() * 60.0).round(), hemisphere), (start.0 + 2, start.1 - 12), small_label.clone()))?;
+    }
+    for i in first_lon..=last_lon {
+        let lon = i as f64 * GRATICULE_STEP;
+        let line: Vec<(i32, i32)> = (0..=50)
+            .map(|k| chart.pixel(south + (north - south) * k as f64 / 50.0, lon))
+            .collect();
+        let end = line[line.len() - 1];
+        root.draw(&PathElement::new(line, GREY.stroke_width(1)))?;
+        let hemisphere = if lon < 0.0 { 'W' } else { 'E' };
+        root.draw(&Text::new(format!("{:03}°{:02}'{}", lon.abs().trunc(), (lon.abs().fract() * 60.0).round(), hemisphere), (end.0 + 2, end.1 + 2), small_label.clone()))?;
+    }
+
+    // Airports, styled by type
+    for airport in airports {
+        let center = chart.pixel(airport.latitude, airport.longitude);
+        match airport.airport_type {
+            AirportType::large_airport => root.draw(&Circle::new(center, 7, BLUE.filled()))?,
+            AirportType::medium_airport => root.draw(&Circle::new(center, 6, BLUE.stroke_width(2)))?,
+            AirportType::small_airport => root.draw(&Circle::new(center, 5, PURPLE.stroke_width(2)))?,
+            AirportType::helliport => root.draw(&Text::new("H", (center.0 - 4, center.1 - 6), ("sans-serif", 12).into_font().color(&PURPLE)))?,
+            AirportType::closed => root.draw(&Cross::new(center, 4, GREY.stroke_width(2)))?,
+            AirportType::unknown => root.draw(&Circle::new(center, 2, GREY.filled()))?,
+        }
+        if !matches!(airport.airport_type, AirportType::closed | AirportType::unknown) {
+            root.draw(&Text::new(airport.oaci_code.clone(), (center.0 + 8, center.1 + 2), small_label.clone()))?;
+        }
+    }
+
+    // Route, each leg along its great circle or rhumb line, and waypoints
+    for branch in &navigation.branches {
+        let track: Vec<(i32, i32)> = branch
+            .track(TRACK_SPACING)
+            .iter()
+            .map(|p| chart.pixel(p.latitude, p.longitude))
+            .collect();
+        root.draw(&PathElement::new(track, RED.stroke_width(3)))?;
+    }
+    let route: Vec<(i32, i32)> = route_points(navigation)
+        .iter()
+        .map(|p| chart.pixel(p.latitude, p.longitude))
+        .collect();
+    if navigation.branches.is_empty() {
+        root.draw(&PathElement::new(route.clone(), RED.stroke_width(3)))?;
+    }
+    for (point, pixel) in route_points(navigation).iter().zip(route) {
+        root.draw(&Circle::new(pixel, 4, RED.filled()))?;
+        root.draw(&Text::new(point.name.clone(), (pixel.0 + 6, pixel.1 - 16), label.clone()))?;
+    }
+
+    // Scale bar of 10 NM
+    let (_, height) = root.dim_in_pixel();
+    let bar = (10.0 / KM_TO_NM * 1000.0 * chart.pixels_per_meter).round() as i32;
+    let origin = (20, height as i32 - 30);
+    root.draw(&PathElement::new(vec![origin, (origin.0 + bar, origin.1)], BLACK.stroke_width(3)))?;
+    for tick in 0..=2 {
+        let x = origin.0 + bar * tick / 2;
+        root.draw(&PathElement::new(vec![(x, origin.1 - 5), (x, origin.1 + 5)], BLACK.stroke_width(2)))?;
+    }
+    root.draw(&Text::new(format!("10 NM - 1:{:.0}", config.scale), (origin.0, origin.1 + 8), label))?;
+
+    root.present()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navigation::flightplan::{NavBranch, Wind};
+    use crate::navigation::geographics::EarthModel;
+
+    #[test]
+    fn refuses_huge_charts() {
+        let start = NavPoint::new("LFPN".to_string(), 48.75, 2.11);
+        let end = NavPoint::new("LFMN".to_string(), 43.66, 7.22);
+        let branch = NavBranch::between(start.clone(), end.clone(), Wind { speed: 0.0, direction: 0.0 }, None, EarthModel::spherical);
+        let navigation = Navigation::new(start, end, vec![branch]);
+        let mut config = MapConfig::new("data/airports.db");
+        config.scale = 50000.0;
+        config.dpi = 300.0;
+        let error = render_navigation(&navigation, "/tmp/huge_chart.png", MapFormat::png, &config).unwrap_err();
+        assert!(error.downcast_ref::<MapError>().is_some());
+    }
+}
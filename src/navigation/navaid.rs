@@ -0,0 +1,213 @@
+use rusqlite::Connection;
+
+use super::database::parse_database;
+use super::geographics::{calculate_distance_and_course, EarthModel, NavPoint};
+use super::runway::optional_number;
+use super::spatial;
+use super::waypoint::{RadialDistance, WaypointKind};
+
+/// Columns of the `navaids` table read by `Navaid::navaid_mapper`
+pub const NAVAID_COLUMNS: &str = "ident, name, type, frequency_khz, latitude_deg, longitude_deg,
+    elevation_ft, magnetic_variation_deg, iso_country, associated_airport";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NavaidType {
+    vor,
+    vor_dme,
+    vortac,
+    ndb,
+    ndb_dme,
+    dme,
+    tacan,
+    unknown,
+}
+
+impl NavaidType {
+    pub fn from_db_type(navaid_type: &str) -> NavaidType {
+        match navaid_type {
+            "VOR" => NavaidType::vor,
+            "VOR-DME" => NavaidType::vor_dme,
+            "VORTAC" => NavaidType::vortac,
+            "NDB" => NavaidType::ndb,
+            "NDB-DME" => NavaidType::ndb_dme,
+            "DME" => NavaidType::dme,
+            "TACAN" => NavaidType::tacan,
+            _ => NavaidType::unknown,
+        }
+    }
+
+    /// NDBs are tuned in kHz, the other navaids in MHz
+    pub fn is_ndb(&self) -> bool {
+        matches!(self, NavaidType::ndb | NavaidType::ndb_dme)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Navaid {
+    pub ident: String,
+    pub name: String,
+    pub navaid_type: NavaidType,
+    pub frequency: f64, // kHz
+    pub latitude: f64,
+    pub longitude: f64,
+    pub elevation: Option<f64>,          // ft
+    pub magnetic_variation: Option<f64>, // degrees east positive
+    pub iso_country: String,
+    pub associated_airport: String,
+}
+
+impl Navaid {
+    pub fn navaid_mapper(row: &rusqlite::Row) -> rusqlite::Result<Navaid> {
+        Ok(Navaid {
+            ident: row.get(0)?,
+            name: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            navaid_type: NavaidType::from_db_type(&row.get::<_, Option<String>>(2)?.unwrap_or_default()),
+            frequency: optional_number(row, 3)?.unwrap_or(0.0),
+            latitude: row.get(4)?,
+            longitude: row.get(5)?,
+            elevation: optional_number(row, 6)?,
+            magnetic_variation: optional_number(row, 7)?,
+            iso_country: row.get::<_, Option<String>>(8)?.unwrap_or_default(),
+            associated_airport: row.get::<_, Option<String>>(9)?.unwrap_or_default(),
+        })
+    }
+
+    /// All navaids with this ident; idents are not unique worldwide
+    pub fn from_database(database: &str, ident: &str) -> Result<Vec<Navaid>, rusqlite::Error> {
+        parse_database(database, "navaids", "ident", &ident.to_uppercase(), NAVAID_COLUMNS, Self::navaid_mapper)
+    }
+
+    /// The navaid with this ident nearest to `previous`, usually the previous waypoint of the route
+    pub fn nearest(database: &str, ident: &str, previous: &NavPoint) -> Result<Option<Navaid>, rusqlite::Error> {
+        let distance = |navaid: &Navaid| {
+            calculate_distance_and_course(previous, &NavPoint::new(String::new(), navaid.latitude, navaid.longitude))
+                .0
+                .unwrap_or(f64::INFINITY)
+        };
+        Ok(Self::from_database(database, ident)?
+            .into_iter()
+            .min_by(|a, b| distance(a).total_cmp(&distance(b))))
+    }
+
+    /// Navaids within `radius` km of a point, nearest first
+    pub fn within_radius(database: &str, point: &NavPoint, radius: f64) -> Result<Vec<Navaid>, rusqlite::Error> {
+        let conn = Connection::open(database)?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM navaids WHERE latitude_deg BETWEEN ?1 AND ?2 AND longitude_deg BETWEEN ?3 AND ?4",
+            NAVAID_COLUMNS
+        ))?;
+        let distance = |navaid: &Navaid| {
+            calculate_distance_and_course(point, &NavPoint::new(String::new(), navaid.latitude, navaid.longitude))
+                .0
+                .unwrap_or(f64::INFINITY)
+        };
+        let mut navaids = Vec::new();
+        for (south, north, west, east) in spatial::boxes_around(point.latitude, point.longitude, radius) {
+            for navaid in stmt.query_map([south, north, west, east], Self::navaid_mapper)? {
+                navaids.push(navaid?);
+            }
+        }
+        navaids.retain(|navaid| distance(navaid) <= radius);
+        navaids.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+        Ok(navaids)
+    }
+
+    /// Describes `position` as a radial and distance from the nearest navaid within `radius` km,
+    /// the radial being magnetic with the variation of that navaid when known
+    pub fn describe_position(database: &str, position: &NavPoint, radius: f64, model: EarthModel) -> Result<Option<RadialDistance>, rusqlite::Error> {
+        let references: Vec<(NavPoint, Option<f64>)> = Self::within_radius(database, position, radius)?
+            .iter()
+            .map(|navaid| (navaid.to_nav_point(), navaid.magnetic_variation))
+            .collect();
+        Ok(RadialDistance::describe(position, &references, model))
+    }
+
+    /// Frequency as tuned: "115.60 MHz" or "339 kHz"
+    pub fn frequency_label(&self) -> String {
+        if self.navaid_type.is_ndb() {
+            format!("{:.0} kHz", self.frequency)
+        } else {
+            format!("{:.2} MHz", self.frequency / 1000.0)
+        }
+    }
+
+    /// Navigation point at the navaid, named after its ident
+    pub fn to_nav_point(&self) -> NavPoint {
+        let mut point = NavPoint::new(self.ident.clone(), self.latitude, self.longitude);
+        point.kind = WaypointKind::navaid(self.ident.clone());
+        point
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navigation::geographics::{project_point, KM_TO_NM};
+
+    /// Copy of the test database with navaids at `(ident, latitude, longitude, magnetic variation)`
+    fn database(name: &str, navaids: &[(&str, f64, f64, &str)]) -> String {
+        let db_path = std::env::temp_dir().join(format!("flight_planner_navaid_{}.db", name));
+        let db_path = db_path.to_str().unwrap().to_string();
+        std::fs::copy("data/airports.db", &db_path).unwrap();
+        let conn = Connection::open(&db_path).unwrap();
+        for (id, (ident, latitude, longitude, variation)) in navaids.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO navaids (id, ident, name, type, frequency_khz, latitude_deg, longitude_deg, magnetic_variation_deg)
+                VALUES (?1, ?2, ?2, 'VOR-DME', 115600, ?3, ?4, ?5)",
+                rusqlite::params![id.to_string(), ident, latitude, longitude, variation],
+            )
+            .unwrap();
+        }
+        db_path
+    }
+
+    #[test]
+    fn describes_a_position_from_the_nearest_navaid() {
+        let db_path = database("describe", &[("PTS", 49.0, 2.0, "2.0"), ("RBT", 48.6, 1.2, "")]);
+        let pts = NavPoint::new("PTS".to_string(), 49.0, 2.0);
+        let position = project_point(&pts, 247.0, 12.0 / KM_TO_NM, EarthModel::spherical, String::new());
+
+        let radial = Navaid::describe_position(&db_path, &position, 50.0, EarthModel::spherical).unwrap().unwrap();
+        assert_eq!(radial.reference.name, "PTS");
+        assert_eq!(radial.magnetic_variation, Some(2.0));
+        assert!((radial.bearing - 245.0).abs() < 1e-6);
+        assert!((radial.distance - 12.0).abs() < 1e-6);
+        assert_eq!(radial.to_string(), "PTS 245°M/12.0 NM");
+
+        // Near a navaid of unknown variation, the radial is true
+        let rbt = NavPoint::new("RBT".to_string(), 48.6, 1.2);
+        let position = project_point(&rbt, 90.0, 5.0 / KM_TO_NM, EarthModel::spherical, String::new());
+        let radial = Navaid::describe_position(&db_path, &position, 50.0, EarthModel::spherical).unwrap().unwrap();
+        assert_eq!(radial.reference.name, "RBT");
+        assert_eq!(radial.to_string(), "RBT 090°T/5.0 NM");
+
+        let far = NavPoint::new("FAR".to_string(), 45.0, 5.0);
+        assert!(Navaid::describe_position(&db_path, &far, 50.0, EarthModel::spherical).unwrap().is_none());
+        std::fs::remove_file(db_path).unwrap();
+    }
+
+    #[test]
+    fn nearest_picks_the_candidate_closest_to_the_previous_point() {
+        let db_path = database("nearest", &[("BRY", 48.4, 3.3, ""), ("BRY", -33.9, 18.6, "")]);
+        let paris = NavPoint::new("LFPN".to_string(), 48.75, 2.1);
+        let cape_town = NavPoint::new("FACT".to_string(), -33.97, 18.6);
+
+        let navaid = Navaid::nearest(&db_path, "BRY", &paris).unwrap().unwrap();
+        assert_eq!(navaid.latitude, 48.4);
+        let navaid = Navaid::nearest(&db_path, "BRY", &cape_town).unwrap().unwrap();
+        assert_eq!(navaid.latitude, -33.9);
+        assert!(Navaid::nearest(&db_path, "XXX", &paris).unwrap().is_none());
+        std::fs::remove_file(db_path).unwrap();
+    }
+
+    #[test]
+    fn finds_navaids_across_the_antimeridian() {
+        let db_path = database("antimeridian", &[("TAV", -17.8, 179.9, ""), ("FAR", -17.8, 170.0, "")]);
+        let position = NavPoint::new(String::new(), -17.8, -179.9);
+
+        let navaids = Navaid::within_radius(&db_path, &position, 50.0).unwrap();
+        assert_eq!(navaids.len(), 1);
+        assert_eq!(navaids[0].ident, "TAV");
+        std::fs::remove_file(db_path).unwrap();
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::aircraft::Aircraft;
+use super::error::PlanError;
+use super::flightplan::{NavBranch, Navigation, Wind};
+use super::geographics::{EarthModel, LegType, NavPoint};
+
+/// Version written by `PlanDocument::save`
+pub const PLAN_VERSION: u64 = 1;
+
+/// Upgrades of a plan document, `MIGRATIONS[n]` turning a version n + 1 document into a version n + 2 one.
+/// A change of layout bumps `PLAN_VERSION` and adds its migration here, so that older plans still open.
+const MIGRATIONS: [fn(&mut Value); PLAN_VERSION as usize - 1] = [];
+
+/// Balance stations left out of the saved loading: the empty aircraft comes from the database, the fuel from the fuel plan
+const COMPUTED_STATIONS: [&str; 2] = ["Empty", "tank"];
+
+const CREATE_RECENT_PLANS: &str = r#"CREATE TABLE IF NOT EXISTS "recent_plans" (
+	"path"	TEXT NOT NULL,
+	"name"	TEXT,
+	"opened"	TEXT NOT NULL,
+	PRIMARY KEY("path")
+);"#;
+
+/// A leg of a saved plan, ending at `to`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanLeg {
+    pub to: NavPoint,
+    pub altitude: Option<f64>, // ft, None to keep the previous leg altitude
+    pub wind: Wind,
+    #[serde(default)]
+    pub earth_model: EarthModel,
+    #[serde(default)]
+    pub leg_type: LegType,
+    #[serde(default)]
+    pub time: Option<i64>, // s, estimated leg time
+}
+
+/// Fuel loaded for the flight and planned consumption
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FuelPlan {
+    pub on_board: f64,    // L
+    pub consumption: f64, // L/h
+}
+
+/// A flight plan saved as a JSON document: route, altitudes, winds, aircraft, loading, fuel and departure time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanDocument {
+    pub version: u64,
+    pub name: String,
+    pub saved: DateTime<Utc>,
+    pub departure: NavPoint,
+    pub legs: Vec<PlanLeg>,
+    pub off_block: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub taxi_out: i64, // min
+    #[serde(default)]
+    pub taxi_in: i64, // min
+    pub aircraft: Option<String>, // registration
+    #[serde(default)]
+    pub loading: BTreeMap<String, f64>, // kg by balance station
+    pub fuel: Option<FuelPlan>,
+}
+
+/// A plan file recently opened or saved
+#[derive(Debug, Clone)]
+pub struct RecentPlan {
+    pub path: String,
+    pub name: String,
+    pub opened: String, // RFC 3339, UTC
+}
+
+impl PlanDocument {
+    /// Captures a navigation and, if given, the aircraft flying it with its loading and fuel
+    pub fn new(name: String, navigation: &Navigation, aircraft: Option<&Aircraft>) -> PlanDocument {
+        let legs = navigation
+            .branches
+            .iter()
+            .filter_map(|branch| {
+                Some(PlanLeg {
+                    to: branch.to.clone()?,
+                    altitude: branch.altitude,
+                    wind: branch.wind,
+                    earth_model: branch.earth_model,
+                    leg_type: branch.leg_type,
+                    time: branch.time.map(|time| time.num_seconds()),
+                })
+            })
+            .collect();
+        let loading = aircraft.map_or_else(BTreeMap::new, |aircraft| {
+            aircraft
+                .loading
+                .elements
+                .iter()
+                .filter(|(station, _)| !COMPUTED_STATIONS.contains(&station.as_str()))
+                .map(|(station, element)| (station.clone(), element.weight))
+                .collect()
+        });
+        PlanDocument {
+            version: PLAN_VERSION,
+            name,
+            saved: Utc::now(),
+            departure: navigation.start.clone(),
+            legs,
+            off_block: navigation.off_block,
+            taxi_out: navigation.taxi_out.num_minutes(),
+            taxi_in: navigation.taxi_in.num_minutes(),
+            aircraft: aircraft.map(|aircraft| aircraft.immatriculation.clone()),
+            loading,
+            fuel: aircraft.map(|aircraft| FuelPlan {
+                on_board: aircraft.fuel_on_board(),
+                consumption: aircraft.consomatation,
+            }),
+        }
+    }
+
+    /// Rebuilds the navigation, each leg starting where the previous one ends
+    pub fn navigation(&self) -> Navigation {
+        let mut from = self.departure.clone();
+        let mut branches = Vec::with_capacity(self.legs.len());
+        for leg in &self.legs {
+            let mut branch = NavBranch::between(from, leg.to.clone(), leg.wind, leg.altitude, leg.earth_model);
+            branch.time = leg.time.map(Duration::seconds);
+            if leg.leg_type != LegType::great_circle {
+                branch.set_leg_type(leg.leg_type);
+            }
+            branches.push(branch);
+            from = leg.to.clone();
+        }
+        let mut navigation = Navigation::new(self.departure.clone(), from, branches);
+        navigation.off_block = self.off_block;
+        navigation.taxi_out = Duration::minutes(self.taxi_out);
+        navigation.taxi_in = Duration::minutes(self.taxi_in);
+        navigation
+    }
+
+    /// Loads the aircraft of the plan from the database, with the saved loading and fuel
+    pub fn aircraft(&self) -> Result<Option<Aircraft>, PlanError> {
+        let Some(registration) = &self.aircraft else {
+            return Ok(None);
+        };
+        let mut aircraft = Aircraft::import(registration)?;
+        for (station, weight) in &self.loading {
+            aircraft.loading.load(station.clone(), *weight)?;
+        }
+        if let Some(fuel) = &self.fuel {
+            aircraft.load_fuel(fuel.on_board)?;
+            if fuel.consumption > 0.0 {
+                aircraft.consomatation = fuel.consumption;
+            }
+        }
+        Ok(Some(aircraft))
+    }
+
+    /// Reads a plan document, migrating it from an older version if needed
+    pub fn from_json(text: &str) -> Result<PlanDocument, PlanError> {
+        let mut document: Value = serde_json::from_str(text)?;
+        migrate(&mut document, &MIGRATIONS)?;
+        Ok(serde_json::from_value(document)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, PlanError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Reads a plan file, see `from_json`
+    pub fn load(path: &str) -> Result<PlanDocument, PlanError> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Writes the plan to a file in the current version, updating its save time
+    pub fn save(&mut self, path: &str) -> Result<(), PlanError> {
+        self.version = PLAN_VERSION;
+        self.saved = Utc::now();
+        fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+}
+
+/// Runs the `migrations` a document still needs, bringing it to the version following the last of them
+fn migrate(document: &mut Value, migrations: &[fn(&mut Value)]) -> Result<(), PlanError> {
+    let latest = migrations.len() as u64 + 1;
+    let version = document
+        .get("version")
+        .and_then(Value::as_u64)
+        .filter(|version| *version > 0)
+        .ok_or(PlanError::missing_version)?;
+    if version > latest {
+        return Err(PlanError::newer_version(version, latest));
+    }
+    for migration in &migrations[version as usize - 1..] {
+        migration(document);
+    }
+    document["version"] = Value::from(latest);
+    Ok(())
+}
+
+impl RecentPlan {
+    /// Creates the `recent_plans` table if it does not exist yet
+    pub fn create_table(db_path: &str) -> Result<(), rusqlite::Error> {
+        Connection::open(db_path)?.execute_batch(CREATE_RECENT_PLANS)
+    }
+
+    /// Records that the plan at `path` was just opened or saved
+    pub fn touch(db_path: &str, path: &str, name: &str) -> Result<(), rusqlite::Error> {
+        let conn = Connection::open(db_path)?;
+        conn.execute(
+            "INSERT INTO recent_plans (path, name, opened) VALUES (?1, ?2, ?3) ON CONFLICT(path) DO UPDATE SET name = ?2, opened = ?3",
+            params![path, name, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// The `limit` plans opened or saved last, most recent first
+    pub fn list(db_path: &str, limit: usize) -> Result<Vec<RecentPlan>, rusqlite::Error> {
+        let conn = Connection::open(db_path)?;
+        let mut stmt = conn.prepare("SELECT path, name, opened FROM recent_plans ORDER BY opened DESC LIMIT ?1")?;
+        let plans = stmt.query_map([limit as i64], |row| {
+            Ok(RecentPlan {
+                path: row.get(0)?,
+                name: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                opened: row.get(2)?,
+            })
+        })?;
+        plans.collect()
+    }
+
+    /// Forgets a plan, e.g. when its file was moved or deleted
+    pub fn remove(db_path: &str, path: &str) -> Result<(), rusqlite::Error> {
+        let conn = Connection::open(db_path)?;
+        conn.execute("DELETE FROM recent_plans WHERE path = ?1", [path])?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERSION_1: &str = r#"{
+        "version": 1,
+        "name": "Beauvais",
+        "saved": "2026-10-19T08:00:00Z",
+        "departure": { "name": "LFPN", "latitude": 48.7516, "longitude": 2.1063 },
+        "legs": [
+            { "to": { "name": "LFOB", "latitude": 49.4544, "longitude": 2.1128 }, "altitude": 2500.0, "wind": { "speed": 10.0, "direction": 270.0 } }
+        ],
+        "off_block": null,
+        "aircraft": null,
+        "fuel": null
+    }"#;
+
+    /// A version 2 that would have renamed `name` to `title`
+    fn rename_name(document: &mut Value) {
+        if let Some(name) = document.as_object_mut().and_then(|object| object.remove("name")) {
+            document["title"] = name;
+        }
+    }
+
+    #[test]
+    fn reads_version_1() {
+        let plan = PlanDocument::from_json(VERSION_1).unwrap();
+        assert_eq!(plan.version, PLAN_VERSION);
+        assert_eq!(plan.name, "Beauvais");
+        assert_eq!(plan.legs.len(), 1);
+        assert_eq!(plan.legs[0].leg_type, LegType::great_circle);
+        assert_eq!(plan.navigation().branches.len(), 1);
+    }
+
+    #[test]
+    fn migrates_older_versions() {
+        let mut document: Value = serde_json::from_str(VERSION_1).unwrap();
+        migrate(&mut document, &[rename_name]).unwrap();
+        assert_eq!(document["version"], 2);
+        assert_eq!(document["title"], "Beauvais");
+        assert!(document.get("name").is_none());
+
+        // Already in the latest version, nothing to run
+        migrate(&mut document, &[rename_name]).unwrap();
+        assert_eq!(document["title"], "Beauvais");
+    }
+
+    #[test]
+    fn rejects_newer_and_missing_versions() {
+        let newer = VERSION_1.replace("\"version\": 1", "\"version\": 2");
+        assert!(matches!(PlanDocument::from_json(&newer), Err(PlanError::newer_version(2, PLAN_VERSION))));
+        let zero = VERSION_1.replace("\"version\": 1", "\"version\": 0");
+        assert!(matches!(PlanDocument::from_json(&zero), Err(PlanError::missing_version)));
+        let missing = VERSION_1.replace("\"version\": 1,", "");
+        assert!(matches!(PlanDocument::from_json(&missing), Err(PlanError::missing_version)));
+    }
+}
@@ -0,0 +1,337 @@
+use chrono::Duration;
+
+use super::aircraft::{Aircraft, PhasePerformance};
+use super::flightplan::{NavBranch, Navigation};
+use super::geographics::{intermediate_point, NavPoint, KM_TO_NM};
+
+/// Names of the pseudo-waypoints inserted in the profile
+pub const TOP_OF_CLIMB: &str = "TOC";
+pub const TOP_OF_DESCENT: &str = "TOD";
+pub const BOTTOM_OF_DESCENT: &str = "BOD";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlightPhase {
+    climb,
+    cruise,
+    descent,
+}
+
+/// Part of a leg flown in a single phase
+#[derive(Debug, Clone)]
+pub struct ProfileSegment {
+    pub phase: FlightPhase,
+    pub from: NavPoint,
+    pub to: NavPoint,
+    pub distance: f64, // km
+    pub start_altitude: f64,
+    pub end_altitude: f64,
+    pub time: Duration,
+    pub fuel: f64, // L
+}
+
+/// Vertical profile of a navigation, split in climb, cruise and descent segments
+#[derive(Debug, Clone)]
+pub struct VerticalProfile {
+    pub segments: Vec<ProfileSegment>,
+    pub pseudo_waypoints: Vec<NavPoint>,
+}
+
+struct Leg<'a> {
+    branch: &'a NavBranch,
+    from: &'a NavPoint,
+    to: &'a NavPoint,
+    start: f64,
+    length: f64,
+}
+
+struct ProfilePoint {
+    distance: f64,
+    altitude: f64,
+    name: String,
+    pseudo: bool,
+}
+
+/// Altitude change per kilometer flown, in ft/km
+fn gradient(branch: &NavBranch, performance: &PhasePerformance) -> Option<f64> {
+    if performance.rate <= 0.0 {
+        return None;
+    }
+    let ground_speed = branch.ground_speed(performance.speed)?;
+    Some(performance.rate * 60.0 * KM_TO_NM / ground_speed)
+}
+
+fn push_point(points: &mut Vec<ProfilePoint>, point: ProfilePoint) {
+    // A pseudo-waypoint falling on a waypoint takes its name
+    if let Some(last) = points.last_mut() {
+        if (last.distance - point.distance).abs() < 1e-6 {
+            if last.pseudo && !point.pseudo {
+                last.name = point.name;
+                last.pseudo = false;
+            }
+            return;
+        }
+    }
+    points.push(point);
+}
+
+impl VerticalProfile {
+    /// Computes the climb, cruise and descent profile of a navigation.
+    ///
+    /// The aircraft climbs from `departure_elevation` at the start of the first leg, and
+    /// changes level at the start of any leg whose `altitude` differs from the current one.
+    /// The final descent is planned to reach `destination_elevation` at the end of the last leg.
+    /// On short legs, the top of climb and top of descent merge where the two paths meet.
+    ///
+    /// Returns `None` if a leg is missing its points or distance, if the wind is too strong,
+    /// or if the aircraft has no climb or descent performance.
+    pub fn compute(navigation: &Navigation, aircraft: &Aircraft, departure_elevation: f64, destination_elevation: f64) -> Option<VerticalProfile> {
+        let mut legs = Vec::new();
+        let mut start = 0.0;
+        for branch in &navigation.branches {
+            let length = branch.distance?;
+            legs.push(Leg {
+                branch,
+                from: branch.from.as_ref()?,
+                to: branch.to.as_ref()?,
+                start,
+                length,
+            });
+            start += length;
+        }
+        let total = start;
+        let first = legs.first()?;
+        let climb = &aircraft.performance.climb;
+        let descent = &aircraft.performance.descent;
+
+        // Forward pass: climb and level changes at the start of the legs
+        let mut points = vec![ProfilePoint {
+            distance: 0.0,
+            altitude: departure_elevation,
+            name: first.from.name.clone(),
+            pseudo: false,
+        }];
+        let mut altitude = departure_elevation;
+        let mut target = departure_elevation;
+        for leg in &legs {
+            if let Some(leg_altitude) = leg.branch.altitude {
+                target = leg_altitude;
+            }
+            if altitude != target {
+                let (rate, name) = if target > altitude {
+                    (gradient(leg.branch, climb)?, TOP_OF_CLIMB)
+                } else {
+                    (gradient(leg.branch, descent)?, BOTTOM_OF_DESCENT)
+                };
+                let needed = (target - altitude).abs() / rate;
+                if needed < leg.length {
+                    altitude = target;
+                    push_point(&mut points, ProfilePoint {
+                        distance: leg.start + needed,
+                        altitude,
+                        name: name.to_string(),
+                        pseudo: true,
+                    });
+                } else {
+                    altitude += (target - altitude).signum() * rate * leg.length;
+                }
+            }
+            push_point(&mut points, ProfilePoint {
+                distance: leg.start + leg.length,
+                altitude,
+                name: leg.to.name.clone(),
+                pseudo: false,
+            });
+        }
+
+        // Backward pass: descent from the destination until it meets the forward profile
+        let leg_at = |distance: f64| legs.iter().rev().find(|l| l.start <= distance).unwrap_or(first);
+        let mut descent_points = Vec::new();
+        let mut descent_altitude = destination_elevation;
+        let mut top_of_descent = (0.0, descent_altitude);
+        let mut kept = 1;
+        for k in (1..points.len()).rev() {
+            let (before, after) = (&points[k - 1], &points[k]);
+            let rate = gradient(leg_at((before.distance + after.distance) / 2.0).branch, descent)?;
+            let length = after.distance - before.distance;
+            let altitude_before = descent_altitude + rate * length;
+            if altitude_before >= before.altitude {
+                let slope_difference = rate * length - (before.altitude - after.altitude);
+                let ratio = if slope_difference > 0.0 {
+                    ((after.altitude - descent_altitude) / slope_difference).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                top_of_descent = (after.distance - ratio * length, descent_altitude + rate * length * ratio);
+                kept = k;
+                break;
+            }
+            descent_altitude = altitude_before;
+            descent_points.push(ProfilePoint {
+                distance: before.distance,
+                altitude: descent_altitude,
+                name: before.name.clone(),
+                pseudo: before.pseudo,
+            });
+            top_of_descent = (before.distance, descent_altitude);
+            kept = k;
+        }
+
+        let last = legs.last()?;
+        let mut profile: Vec<ProfilePoint> = points.drain(..kept).collect();
+        push_point(&mut profile, ProfilePoint {
+            distance: top_of_descent.0,
+            altitude: top_of_descent.1,
+            name: TOP_OF_DESCENT.to_string(),
+            pseudo: true,
+        });
+        for point in descent_points.into_iter().rev() {
+            push_point(&mut profile, point);
+        }
+        push_point(&mut profile, ProfilePoint {
+            distance: total,
+            altitude: destination_elevation,
+            name: last.to.name.clone(),
+            pseudo: false,
+        });
+
+        // Segments between consecutive points
+        let locate = |leg: &Leg, point: &ProfilePoint| {
+            let fraction = if leg.length > 0.0 { (point.distance - leg.start) / leg.length } else { 0.0 };
+            intermediate_point(leg.from, leg.to, fraction, point.name.clone())
+        };
+        let mut segments = Vec::new();
+        let mut pseudo_waypoints = Vec::new();
+        for pair in profile.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            let leg = leg_at((a.distance + b.distance) / 2.0);
+            let (phase, speed, fuel_flow) = if b.altitude - a.altitude > 0.5 {
+                (FlightPhase::climb, climb.speed, climb.fuel_flow)
+            } else if b.altitude - a.altitude < -0.5 {
+                (FlightPhase::descent, descent.speed, descent.fuel_flow)
+            } else {
+                (FlightPhase::cruise, aircraft.cruise_speed, aircraft.consomatation)
+            };
+            let ground_speed = leg.branch.ground_speed(speed)?;
+            let distance = b.distance - a.distance;
+            let hours = distance * KM_TO_NM / ground_speed;
+            let to = locate(leg, b);
+            if b.pseudo {
+                pseudo_waypoints.push(to.clone());
+            }
+            segments.push(ProfileSegment {
+                phase,
+                from: locate(leg, a),
+                to,
+                distance,
+                start_altitude: a.altitude,
+                end_altitude: b.altitude,
+                time: Duration::seconds((hours * 3600.0).round() as i64),
+                fuel: hours * fuel_flow,
+            });
+        }
+
+        Some(VerticalProfile { segments, pseudo_waypoints })
+    }
+
+    pub fn phase_time(&self, phase: FlightPhase) -> Duration {
+        self.segments.iter().filter(|s| s.phase == phase).map(|s| s.time).sum()
+    }
+
+    pub fn phase_fuel(&self, phase: FlightPhase) -> f64 {
+        self.segments.iter().filter(|s| s.phase == phase).map(|s| s.fuel).sum()
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.segments.iter().map(|s| s.time).sum()
+    }
+
+    pub fn total_fuel(&self) -> f64 {
+        self.segments.iter().map(|s| s.fuel).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navigation::aircraft::{BalanceCat, BalanceChart, BalanceSheet, FlightPlanData, Performance};
+    use crate::navigation::flightplan::Wind;
+    use crate::navigation::fuel::Fuel;
+    use crate::navigation::geographics::EarthModel;
+    use crate::navigation::route::resolve_waypoint;
+
+    /// DR400 climbing at 600 ft/min and 70 kt, descending at 500 ft/min and 100 kt
+    fn aircraft() -> Aircraft {
+        let chart = BalanceChart { catN: BalanceCat::new(), catU: BalanceCat::new(), catA: BalanceCat::new() };
+        let performance = Performance {
+            climb: PhasePerformance { rate: 600.0, speed: 70.0, fuel_flow: 30.0 },
+            descent: PhasePerformance { rate: 500.0, speed: 100.0, fuel_flow: 18.0 },
+        };
+        Aircraft::new("F-TEST".to_string(), "DR400".to_string(), 120, 100.0, Fuel::AVGAS100LL, 25.0, 1, 110, 588.0, chart, BalanceSheet::new(), performance, None, FlightPlanData::new())
+    }
+
+    /// Navigation through airports of the test database, leg `i` flown at `altitudes[i]`
+    fn navigation(route: &[&str], altitudes: &[f64]) -> Navigation {
+        let points: Vec<NavPoint> = route.iter().map(|name| resolve_waypoint("data/airports.db", name, None).unwrap().unwrap()).collect();
+        let calm = Wind { speed: 0.0, direction: 0.0 };
+        let branches = points
+            .windows(2)
+            .zip(altitudes)
+            .map(|(leg, altitude)| NavBranch::between(leg[0].clone(), leg[1].clone(), calm, Some(*altitude), EarthModel::spherical))
+            .collect();
+        Navigation::new(points[0].clone(), points[points.len() - 1].clone(), branches)
+    }
+
+    fn names(profile: &VerticalProfile) -> Vec<String> {
+        profile.pseudo_waypoints.iter().map(|p| p.name.clone()).collect()
+    }
+
+    #[test]
+    fn climb_cruise_and_descent() {
+        let navigation = navigation(&["LFPN", "LFOB"], &[2500.0]);
+        let profile = VerticalProfile::compute(&navigation, &aircraft(), 538.0, 359.0).unwrap();
+        assert_eq!(names(&profile), vec![TOP_OF_CLIMB, TOP_OF_DESCENT]);
+        let phases: Vec<FlightPhase> = profile.segments.iter().map(|s| s.phase).collect();
+        assert_eq!(phases, vec![FlightPhase::climb, FlightPhase::cruise, FlightPhase::descent]);
+
+        // 600 ft/min at 70 kt is 277.7 ft/km, 500 ft/min at 100 kt 162.0 ft/km
+        let climb = &profile.segments[0];
+        assert!((climb.distance - (2500.0 - 538.0) / 277.68).abs() < 0.01);
+        let descent = &profile.segments[2];
+        assert!((descent.distance - (2500.0 - 359.0) / 161.99).abs() < 0.01);
+        assert!((descent.end_altitude - 359.0).abs() < 1e-6);
+        let total: f64 = profile.segments.iter().map(|s| s.distance).sum();
+        assert!((total - navigation.branches[0].distance.unwrap()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn bottom_of_descent_on_a_lower_leg() {
+        let navigation = navigation(&["LFPN", "LFPT", "LFOB"], &[3500.0, 2000.0]);
+        let profile = VerticalProfile::compute(&navigation, &aircraft(), 538.0, 359.0).unwrap();
+        assert_eq!(names(&profile), vec![TOP_OF_CLIMB, BOTTOM_OF_DESCENT, TOP_OF_DESCENT]);
+
+        // Level change at LFPT, 1500 ft at 162.0 ft/km
+        let bod = profile.segments.iter().find(|s| s.to.name == BOTTOM_OF_DESCENT).unwrap();
+        assert_eq!(bod.from.name, "LFPT");
+        assert_eq!(bod.phase, FlightPhase::descent);
+        assert!((bod.distance - 1500.0 / 161.99).abs() < 0.01);
+        assert!((bod.end_altitude - 2000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn short_hop_never_reaches_cruise() {
+        // Saint-Cyr to Toussus: climb and descent paths meet before 2500 ft
+        let navigation = navigation(&["LFPZ", "LFPN"], &[2500.0]);
+        let length = navigation.branches[0].distance.unwrap();
+        let profile = VerticalProfile::compute(&navigation, &aircraft(), 373.0, 538.0).unwrap();
+        assert_eq!(names(&profile), vec![TOP_OF_DESCENT]);
+        let phases: Vec<FlightPhase> = profile.segments.iter().map(|s| s.phase).collect();
+        assert_eq!(phases, vec![FlightPhase::climb, FlightPhase::descent]);
+
+        let (climb, descent) = (&profile.segments[0], &profile.segments[1]);
+        assert!(climb.end_altitude < 2500.0);
+        assert!((climb.end_altitude - descent.start_altitude).abs() < 1e-6);
+        assert!((climb.end_altitude - (373.0 + 277.68 * climb.distance)).abs() < 1.0);
+        assert!((descent.start_altitude - (538.0 + 161.99 * descent.distance)).abs() < 1.0);
+        assert!((climb.distance + descent.distance - length).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,310 @@
+use std::path::Path;
+
+use super::airport::Airport;
+use super::coordinates;
+use super::error::RouteError;
+use super::flightplan::{NavBranch, Navigation, Wind};
+use super::garmin;
+use super::geographics::{along_track_distance, calculate_distance_and_course, cross_track_distance, EarthModel, NavPoint};
+use super::gpx;
+use super::kml;
+use super::navaid::Navaid;
+use super::spatial;
+use super::user_waypoint::{UserWaypoint, UserWaypointType};
+use super::waypoint::{RadialDistance, WaypointKind};
+
+/// Splits an ICAO radial/distance point such as `PTS245012` into its reference, bearing and distance
+fn split_radial_distance(name: &str) -> Option<(&str, f64, f64)> {
+    if name.len() < 8 || !name.is_ascii() {
+        return None;
+    }
+    let (reference, digits) = name.split_at(name.len() - 6);
+    if !(2..=5).contains(&reference.len()) || !reference.chars().all(|c| c.is_ascii_alphabetic()) || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((reference, digits[..3].parse().ok()?, digits[3..].parse().ok()?))
+}
+
+/// Resolves a name typed in a route to a navigation point, trying in order:
+/// an airport ICAO code, a user waypoint, a navaid, coordinates (see `coordinates::parse`)
+/// and a radial/distance from one of these (`PTS245012`).
+/// User waypoints and navaids sharing a name are told apart by their distance to `previous`.
+pub fn resolve_waypoint(db_path: &str, name: &str, previous: Option<&NavPoint>) -> Result<Option<NavPoint>, rusqlite::Error> {
+    let name = name.trim().to_uppercase();
+    if let Some(airport) = Airport::from_db(db_path, &name)?.pop() {
+        return Ok(Some(airport.to_nav_point()));
+    }
+
+    let user_waypoint = match previous {
+        Some(previous) => UserWaypoint::nearest(db_path, &name, previous)?,
+        None => UserWaypoint::from_database(db_path, &name)?.into_iter().next(),
+    };
+    if let Some(user_waypoint) = user_waypoint {
+        return Ok(Some(user_waypoint.to_nav_point()));
+    }
+
+    let navaid = match previous {
+        Some(previous) => Navaid::nearest(db_path, &name, previous)?,
+        None => Navaid::from_database(db_path, &name)?.into_iter().next(),
+    };
+    if let Some(navaid) = navaid {
+        return Ok(Some(navaid.to_nav_point()));
+    }
+
+    if let Ok((latitude, longitude)) = coordinates::parse(&name) {
+        return Ok(Some(NavPoint::new(name, latitude, longitude)));
+    }
+
+    if let Some((reference, bearing, distance)) = split_radial_distance(&name) {
+        // Radials are magnetic when the variation published for the navaid is known,
+        // otherwise the bearing is read as true and flagged so by the radial
+        if let Some(reference) = resolve_waypoint(db_path, reference, previous)? {
+            let variation = match &reference.kind {
+                WaypointKind::navaid(ident) => Navaid::nearest(db_path, ident, &reference)?.and_then(|navaid| navaid.magnetic_variation),
+                _ => None,
+            };
+            let radial = RadialDistance::new(reference, bearing, variation, distance);
+            return Ok(Some(radial.resolve(name, EarthModel::spherical)));
+        }
+    }
+    Ok(None)
+}
+
+/// Resolves a route typed as names separated by spaces, e.g. "LFPN NORD PTS LFPG"
+pub fn resolve_route(db_path: &str, route: &str) -> Result<Vec<NavPoint>, RouteError> {
+    let mut points: Vec<NavPoint> = Vec::new();
+    for name in route.split_whitespace() {
+        let point = resolve_waypoint(db_path, name, points.last())?.ok_or_else(|| RouteError::unknown_waypoint(name.to_string()))?;
+        points.push(point);
+    }
+    Ok(points)
+}
+
+/// A point of a route read from a file, with the planned altitude of the leg leaving it
+#[derive(Debug, Clone)]
+pub struct RoutePoint {
+    pub point: NavPoint,
+    pub altitude: Option<f64>, // ft
+}
+
+/// How the points of an imported route are matched to the database
+#[derive(Debug, Clone)]
+pub struct SnapConfig {
+    pub radius: f64,          // km, points this close to an airport, navaid or user waypoint are replaced by it
+    pub track_tolerance: f64, // km, GPS tracks are simplified to keep the turns deviating more than this
+    pub save_user_waypoints: bool, // store the points left unmatched as user waypoints, off by default
+}
+
+impl SnapConfig {
+    pub fn new() -> SnapConfig {
+        SnapConfig {
+            radius: 2.0,
+            track_tolerance: 1.0,
+            save_user_waypoints: false,
+        }
+    }
+}
+
+fn distance(from: &NavPoint, to: &NavPoint) -> f64 {
+    calculate_distance_and_course(from, to).0.unwrap_or(f64::INFINITY)
+}
+
+/// Keeps the points of a GPS track needed to stay within `tolerance` km of it (Douglas-Peucker)
+pub fn simplify_track(points: &[RoutePoint], tolerance: f64) -> Vec<RoutePoint> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let (first, last) = (&points[0].point, &points[points.len() - 1].point);
+    let (index, deviation) = points[1..points.len() - 1]
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let along = along_track_distance(first, last, &p.point).unwrap_or(0.0);
+            let deviation = if along <= 0.0 || distance(first, last) < 1e-6 {
+                distance(first, &p.point)
+            } else if along >= distance(first, last) {
+                distance(last, &p.point)
+            } else {
+                cross_track_distance(first, last, &p.point).unwrap_or(0.0).abs()
+            };
+            (i + 1, deviation)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((0, 0.0));
+    if deviation <= tolerance {
+        return vec![points[0].clone(), points[points.len() - 1].clone()];
+    }
+    let mut simplified = simplify_track(&points[..=index], tolerance);
+    simplified.pop();
+    simplified.extend(simplify_track(&points[index..], tolerance));
+    simplified
+}
+
+fn is_same_point(a: &NavPoint, b: &NavPoint) -> bool {
+    a.name == b.name && a.latitude == b.latitude && a.longitude == b.longitude
+}
+
+fn is_taken(user_waypoints: &[NavPoint], name: &str) -> bool {
+    user_waypoints.iter().any(|w| w.name == name)
+}
+
+/// `name`, or `name` followed by a number when a user waypoint already has it
+fn unused_name(user_waypoints: &[NavPoint], name: String) -> String {
+    if !is_taken(user_waypoints, &name) {
+        return name;
+    }
+    (2..).map(|n| format!("{}-{}", name, n)).find(|name| !is_taken(user_waypoints, name)).unwrap_or(name)
+}
+
+/// Replaces each point by the nearest airport, navaid or user waypoint within `config.radius`,
+/// consecutive points snapped to the same one being merged.
+/// Points left unmatched keep their position and, if `config.save_user_waypoints` is set,
+/// are stored as user waypoints with a note naming `source` and a name no other user waypoint has.
+pub fn snap_route(db_path: &str, points: Vec<RoutePoint>, config: &SnapConfig, source: &str) -> Result<Vec<RoutePoint>, rusqlite::Error> {
+    let mut snapped = Vec::with_capacity(points.len());
+    // Loaded once for the whole route; waypoints saved below are added so that later points snap to them
+    let mut user_waypoints: Vec<NavPoint> = UserWaypoint::list(db_path)?.iter().map(UserWaypoint::to_nav_point).collect();
+    for (index, route_point) in points.into_iter().enumerate() {
+        let position = &route_point.point;
+        let mut candidates: Vec<NavPoint> = spatial::airports_within_radius(db_path, position, config.radius)?
+            .into_iter()
+            .map(|(airport, _)| airport.to_nav_point())
+            .collect();
+        candidates.extend(Navaid::within_radius(db_path, position, config.radius)?.iter().map(Navaid::to_nav_point));
+        candidates.extend(user_waypoints.iter().filter(|w| distance(position, w) <= config.radius).cloned());
+        let nearest = candidates.into_iter().min_by(|a, b| distance(position, a).total_cmp(&distance(position, b)));
+
+        let point = match nearest {
+            Some(point) => point,
+            None => {
+                let name = if route_point.point.name.trim().is_empty() {
+                    (index..).map(|n| format!("WP{:02}", n)).find(|name| !is_taken(&user_waypoints, name)).unwrap_or_default()
+                } else {
+                    unused_name(&user_waypoints, route_point.point.name.trim().to_uppercase())
+                };
+                let mut waypoint = UserWaypoint::new(name, position.latitude, position.longitude, UserWaypointType::other, None, format!("Imported from {}", source));
+                if config.save_user_waypoints {
+                    waypoint.insert(db_path)?;
+                    user_waypoints.push(waypoint.to_nav_point());
+                }
+                let mut point = waypoint.to_nav_point();
+                if !config.save_user_waypoints {
+                    point.kind = WaypointKind::coordinates;
+                }
+                point
+            }
+        };
+        if let Some(previous) = snapped.last_mut().filter(|p: &&mut RoutePoint| is_same_point(&p.point, &point)) {
+            // The leg leaving the merged point is the one of the later point
+            previous.altitude = route_point.altitude.or(previous.altitude);
+            continue;
+        }
+        snapped.push(RoutePoint {
+            point,
+            altitude: route_point.altitude,
+        });
+    }
+    Ok(snapped)
+}
+
+/// Builds a navigation with one leg between each pair of consecutive points, all with `wind`.
+/// Returns `None` with less than two points.
+pub fn navigation_from_route(points: &[RoutePoint], wind: Wind, earth_model: EarthModel) -> Option<Navigation> {
+    if points.len() < 2 {
+        return None;
+    }
+    let branches = points
+        .windows(2)
+        .map(|leg| NavBranch::between(leg[0].point.clone(), leg[1].point.clone(), wind, leg[0].altitude, earth_model))
+        .collect();
+    Some(Navigation::new(points[0].point.clone(), points[points.len() - 1].point.clone(), branches))
+}
+
+/// Imports a GPX route or track, or a KML line or placemarks, as a navigation, matching its points
+/// to the database as described in `snap_route`. The format is chosen from the file extension.
+/// Garmin `.fpl` plans name their waypoints and are imported by `garmin::import_garmin_fpl` without snapping.
+pub fn import_route(db_path: &str, path: &str, config: &SnapConfig, wind: Wind) -> Result<Navigation, RouteError> {
+    let extension = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    let (points, is_track) = match extension.as_str() {
+        "gpx" => gpx::read_gpx(path)?,
+        "kml" => (kml::read_kml(path)?, false),
+        "fpl" => return garmin::import_garmin_fpl(db_path, path, wind),
+        _ => return Err(RouteError::unknown_format(path.to_string())),
+    };
+    let points = if is_track { simplify_track(&points, config.track_tolerance) } else { points };
+    let source = Path::new(path).file_name().and_then(|f| f.to_str()).unwrap_or(path);
+    let points = snap_route(db_path, points, config, source)?;
+    navigation_from_route(&points, wind, EarthModel::spherical).ok_or_else(|| RouteError::empty_route(path.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn radial_from_an_airport_has_no_variation() {
+        let point = resolve_waypoint("data/airports.db", "LFPG245012", None).unwrap().unwrap();
+        let WaypointKind::radial_distance(radial) = point.kind else {
+            panic!("not a radial: {:?}", point.kind);
+        };
+        assert_eq!(radial.magnetic_variation, None);
+        assert_eq!(radial.true_bearing(), 245.0);
+    }
+
+    #[test]
+    fn snaps_to_airports_and_keeps_unmatched_points() {
+        let points = vec![
+            RoutePoint { point: NavPoint::new("A".to_string(), 48.7516, 2.1063), altitude: Some(2500.0) },
+            RoutePoint { point: NavPoint::new(String::new(), 45.0, -30.0), altitude: None },
+        ];
+        let snapped = snap_route("data/airports.db", points, &SnapConfig::new(), "test").unwrap();
+        assert!(matches!(&snapped[0].point.kind, WaypointKind::airport(code) if code == "LFPN"));
+        assert_eq!(snapped[0].altitude, Some(2500.0));
+        assert_eq!(snapped[1].point.name, "WP01");
+        assert!(matches!(snapped[1].point.kind, WaypointKind::coordinates));
+    }
+
+    #[test]
+    fn merges_points_snapped_to_the_same_waypoint() {
+        let points = vec![
+            RoutePoint { point: NavPoint::new(String::new(), 48.7516, 2.1063), altitude: Some(2500.0) },
+            RoutePoint { point: NavPoint::new(String::new(), 48.7522, 2.1058), altitude: Some(3500.0) },
+            RoutePoint { point: NavPoint::new(String::new(), 45.0, -30.0), altitude: None },
+        ];
+        let snapped = snap_route("data/airports.db", points, &SnapConfig::new(), "test").unwrap();
+        assert_eq!(snapped.len(), 2);
+        assert!(matches!(&snapped[0].point.kind, WaypointKind::airport(code) if code == "LFPN"));
+        assert_eq!(snapped[0].altitude, Some(3500.0));
+    }
+
+    #[test]
+    fn saved_waypoints_get_unused_names() {
+        let db_path = std::env::temp_dir().join("flight_planner_route_snap.db");
+        let db_path = db_path.to_str().unwrap().to_string();
+        std::fs::copy("data/airports.db", &db_path).unwrap();
+        UserWaypoint::create_table(&db_path).unwrap();
+        let mut config = SnapConfig::new();
+        config.save_user_waypoints = true;
+
+        let route = |latitude: f64| {
+            vec![
+                RoutePoint { point: NavPoint::new(String::new(), latitude, -30.0), altitude: None },
+                RoutePoint { point: NavPoint::new("Farm".to_string(), latitude, -29.0), altitude: None },
+            ]
+        };
+        snap_route(&db_path, route(45.0), &config, "first").unwrap();
+        // Importing the same points again snaps to the saved waypoints
+        let snapped = snap_route(&db_path, route(45.0), &config, "first").unwrap();
+        assert_eq!(snapped[0].point.name, "WP00");
+        assert_eq!(snapped[1].point.name, "FARM");
+        // Other points do not reuse their names
+        let snapped = snap_route(&db_path, route(46.0), &config, "second").unwrap();
+        assert_eq!(snapped[0].point.name, "WP01");
+        assert_eq!(snapped[1].point.name, "FARM-2");
+
+        let mut names: Vec<String> = UserWaypoint::list(&db_path).unwrap().into_iter().map(|w| w.name).collect();
+        names.sort();
+        assert_eq!(names, ["FARM", "FARM-2", "WP00", "WP01"]);
+        std::fs::remove_file(db_path).unwrap();
+    }
+}
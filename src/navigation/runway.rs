@@ -0,0 +1,313 @@
+use rusqlite::types::Value;
+use rusqlite::Connection;
+
+use super::database::parse_database;
+use super::error::RunwayError;
+use super::flightplan::Wind;
+
+/// Columns of the `runways` table read by `Runway::runway_mapper`
+pub const RUNWAY_COLUMNS: &str = "length_ft, width_ft, surface, lighted, closed,
+    le_ident, le_heading_degT, le_elevation_ft, le_displaced_threshold_ft,
+    he_ident, he_heading_degT, he_elevation_ft, he_displaced_threshold_ft";
+
+/// Columns of the `frequencies` table read by `Frequency::frequency_mapper`
+pub const FREQUENCY_COLUMNS: &str = "type, description, frequency_mhz";
+
+/// Reads a number stored by the importers, which keep OurAirports empty fields as ''
+pub fn optional_number(row: &rusqlite::Row, index: usize) -> rusqlite::Result<Option<f64>> {
+    Ok(match row.get::<_, Value>(index)? {
+        Value::Integer(value) => Some(value as f64),
+        Value::Real(value) => Some(value),
+        Value::Text(value) => value.trim().parse().ok(),
+        _ => None,
+    })
+}
+
+/// One end of a runway, as used for takeoff or landing
+#[derive(Debug, Clone)]
+pub struct RunwayEnd {
+    pub ident: String,                      // "07", "25L", ...
+    pub heading: Option<f64>,               // degrees true
+    pub elevation: Option<f64>,             // ft
+    pub displaced_threshold: Option<f64>,   // ft
+}
+
+impl RunwayEnd {
+    /// Heading in degrees true. When it is unknown, the runway number gives the magnetic heading,
+    /// converted with `magnetic_variation` (degrees, east positive); `None` without a variation.
+    pub fn true_heading(&self, magnetic_variation: Option<f64>) -> Option<f64> {
+        self.heading.or_else(|| {
+            let number: String = self.ident.chars().take_while(char::is_ascii_digit).collect();
+            let magnetic = number.parse::<f64>().ok().filter(|n| (1.0..=36.0).contains(n))? * 10.0;
+            Some((magnetic + magnetic_variation?).rem_euclid(360.0))
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Runway {
+    pub length: Option<f64>, // ft
+    pub width: Option<f64>,  // ft
+    pub surface: String,
+    pub lighted: bool,
+    pub closed: bool,
+    pub low_end: RunwayEnd,
+    pub high_end: RunwayEnd,
+}
+
+impl Runway {
+    pub fn runway_mapper(row: &rusqlite::Row) -> rusqlite::Result<Runway> {
+        let end = |offset: usize| -> rusqlite::Result<RunwayEnd> {
+            Ok(RunwayEnd {
+                ident: row.get::<_, Option<String>>(offset)?.unwrap_or_default(),
+                heading: optional_number(row, offset + 1)?,
+                elevation: optional_number(row, offset + 2)?,
+                displaced_threshold: optional_number(row, offset + 3)?,
+            })
+        };
+        Ok(Runway {
+            length: optional_number(row, 0)?,
+            width: optional_number(row, 1)?,
+            surface: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            lighted: optional_number(row, 3)? == Some(1.0),
+            closed: optional_number(row, 4)? == Some(1.0),
+            low_end: end(5)?,
+            high_end: end(9)?,
+        })
+    }
+
+    /// Runway designation, e.g. "07/25"
+    pub fn ident(&self) -> String {
+        if self.high_end.ident.is_empty() {
+            self.low_end.ident.clone()
+        } else {
+            format!("{}/{}", self.low_end.ident, self.high_end.ident)
+        }
+    }
+
+    /// Both ends of the runway
+    pub fn ends(&self) -> [&RunwayEnd; 2] {
+        [&self.low_end, &self.high_end]
+    }
+
+    /// Whether the surface is paved, from the OurAirports surface codes (ASP, CON, BIT, ...)
+    pub fn is_paved(&self) -> bool {
+        let surface = self.surface.to_uppercase();
+        ["ASP", "CON", "BIT", "PEM", "TAR", "PAV"].iter().any(|code| surface.starts_with(code))
+    }
+
+    /// Runways of an airport, by ICAO code
+    pub fn from_database(database: &str, airport: &str) -> Result<Vec<Runway>, rusqlite::Error> {
+        parse_database(database, "runways", "airport_ident", airport, RUNWAY_COLUMNS, Self::runway_mapper)
+    }
+
+    /// Runways of an airport, by ICAO code, on an open connection
+    pub fn from_connection(conn: &Connection, airport: &str) -> Result<Vec<Runway>, rusqlite::Error> {
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM runways WHERE airport_ident = ?1", RUNWAY_COLUMNS))?;
+        let runways = stmt.query_map([airport], Self::runway_mapper)?.collect();
+        runways
+    }
+}
+
+/// A radio frequency of an airport (TWR, AFIS, ATIS, A/A, ...)
+#[derive(Debug, Clone)]
+pub struct Frequency {
+    pub frequency_type: String,
+    pub description: String,
+    pub mhz: f64,
+}
+
+impl Frequency {
+    pub fn frequency_mapper(row: &rusqlite::Row) -> rusqlite::Result<Frequency> {
+        Ok(Frequency {
+            frequency_type: row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+            description: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            mhz: optional_number(row, 2)?.unwrap_or(0.0),
+        })
+    }
+
+    /// Frequencies of an airport, by ICAO code
+    pub fn from_database(database: &str, airport: &str) -> Result<Vec<Frequency>, rusqlite::Error> {
+        parse_database(database, "frequencies", "airport_ident", airport, FREQUENCY_COLUMNS, Self::frequency_mapper)
+    }
+}
+
+/// Wind components along and across a runway end, in kt
+#[derive(Debug, Clone)]
+pub struct WindComponents {
+    pub runway: String,
+    pub headwind: f64,  // negative for a tailwind
+    pub crosswind: f64, // positive from the right
+}
+
+impl WindComponents {
+    /// Wind components for a takeoff or landing on `end`, the wind direction being in degrees true.
+    /// `None` if the true heading of the runway is unknown, see `RunwayEnd::true_heading`.
+    pub fn compute(end: &RunwayEnd, wind: Wind, magnetic_variation: Option<f64>) -> Option<WindComponents> {
+        let angle = (wind.direction - end.true_heading(magnetic_variation)?).to_radians();
+        Some(WindComponents {
+            runway: end.ident.clone(),
+            headwind: wind.speed * angle.cos(),
+            crosswind: wind.speed * angle.sin(),
+        })
+    }
+
+    pub fn tailwind(&self) -> f64 {
+        (-self.headwind).max(0.0)
+    }
+}
+
+/// Wind components on every runway end of an airport and the recommended one
+#[derive(Debug)]
+pub struct RunwaySelection {
+    pub components: Vec<WindComponents>,
+    pub recommended: Option<WindComponents>,
+    pub warnings: Vec<RunwayError>,
+}
+
+/// Computes the wind components on every end of the open runways and recommends the end
+/// with the most headwind, preferring those within `max_crosswind` (kt) when it is known.
+/// Warns about a tailwind or an excess crosswind on the recommended runway.
+pub fn select_runway(airport: &str, runways: &[Runway], wind: Wind, magnetic_variation: Option<f64>, max_crosswind: Option<f64>) -> RunwaySelection {
+    let components: Vec<WindComponents> = runways
+        .iter()
+        .filter(|runway| !runway.closed)
+        .flat_map(|runway| runway.ends())
+        .filter_map(|end| WindComponents::compute(end, wind, magnetic_variation))
+        .collect();
+
+    let within_limit = |c: &WindComponents| max_crosswind.is_none_or(|max| c.crosswind.abs() <= max);
+    let recommended = components
+        .iter()
+        .max_by(|a, b| {
+            within_limit(a)
+                .cmp(&within_limit(b))
+                .then(a.headwind.total_cmp(&b.headwind))
+                .then(b.crosswind.abs().total_cmp(&a.crosswind.abs()))
+        })
+        .cloned();
+
+    let mut warnings = Vec::new();
+    match &recommended {
+        None => warnings.push(RunwayError::no_runway(airport.to_string())),
+        Some(best) => {
+            if best.tailwind() >= 1.0 {
+                warnings.push(RunwayError::tailwind(airport.to_string(), best.runway.clone(), best.tailwind()));
+            }
+            if let Some(max) = max_crosswind {
+                if best.crosswind.abs() > max {
+                    warnings.push(RunwayError::crosswind_exceeded(airport.to_string(), best.runway.clone(), best.crosswind.abs(), max));
+                }
+            }
+        }
+    }
+    RunwaySelection {
+        components,
+        recommended,
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn end(ident: &str, heading: f64) -> RunwayEnd {
+        RunwayEnd { ident: ident.to_string(), heading: Some(heading), elevation: None, displaced_threshold: None }
+    }
+
+    fn runway(low: (&str, f64), high: (&str, f64)) -> Runway {
+        Runway {
+            length: Some(3600.0),
+            width: Some(82.0),
+            surface: "ASP".to_string(),
+            lighted: true,
+            closed: false,
+            low_end: end(low.0, low.1),
+            high_end: end(high.0, high.1),
+        }
+    }
+
+    fn wind(direction: f64, speed: f64) -> Wind {
+        Wind { speed, direction }
+    }
+
+    #[test]
+    fn wind_components() {
+        let runway_07 = end("07", 70.0);
+        let head = WindComponents::compute(&runway_07, wind(70.0, 20.0), None).unwrap();
+        assert!((head.headwind - 20.0).abs() < 1e-9 && head.crosswind.abs() < 1e-9);
+
+        let tail = WindComponents::compute(&runway_07, wind(250.0, 10.0), None).unwrap();
+        assert!((tail.headwind + 10.0).abs() < 1e-9);
+        assert!((tail.tailwind() - 10.0).abs() < 1e-9);
+
+        // 60° from the right: half the wind along the runway
+        let right = WindComponents::compute(&runway_07, wind(130.0, 20.0), None).unwrap();
+        assert!((right.headwind - 10.0).abs() < 1e-9);
+        assert!((right.crosswind - 17.32).abs() < 0.01);
+        let left = WindComponents::compute(&runway_07, wind(10.0, 20.0), None).unwrap();
+        assert!((left.crosswind + 17.32).abs() < 0.01);
+        assert_eq!(head.tailwind(), 0.0);
+    }
+
+    #[test]
+    fn selects_the_runway_into_the_wind() {
+        let runways = [runway(("07", 70.0), ("25", 250.0)), runway(("02", 20.0), ("20", 200.0))];
+        let selection = select_runway("LFPN", &runways, wind(240.0, 15.0), None, Some(22.0));
+        assert_eq!(selection.components.len(), 4);
+        assert_eq!(selection.recommended.unwrap().runway, "25");
+        assert!(selection.warnings.is_empty());
+    }
+
+    #[test]
+    fn prefers_a_runway_within_the_crosswind_limit() {
+        // 25 has more headwind but 26 kt of crosswind; 20 stays within the limit
+        let runways = [runway(("07", 70.0), ("25", 250.0)), runway(("02", 20.0), ("20", 200.0))];
+        let selection = select_runway("LFPN", &runways, wind(220.0, 30.0), None, Some(22.0));
+        assert_eq!(selection.recommended.unwrap().runway, "20");
+        assert!(selection.warnings.is_empty());
+    }
+
+    #[test]
+    fn warns_about_crosswind_and_tailwind() {
+        let runways = [runway(("07", 70.0), ("25", 250.0))];
+        let selection = select_runway("LFPN", &runways, wind(340.0, 25.0), None, Some(22.0));
+        assert!(matches!(&selection.warnings[..], [RunwayError::crosswind_exceeded(_, _, crosswind, max)] if *crosswind > 24.0 && *max == 22.0));
+
+        let selection = select_runway("LFPN", &runways, wind(340.0, 25.0), None, None);
+        assert!(selection.warnings.is_empty());
+
+        // One-way runway with the wind from behind
+        let mut one_way = runway(("07", 70.0), ("", 250.0));
+        one_way.high_end.heading = None;
+        let selection = select_runway("LFPN", &[one_way], wind(230.0, 10.0), None, None);
+        assert!(matches!(&selection.warnings[..], [RunwayError::tailwind(_, runway, _)] if runway == "07"));
+    }
+
+    #[test]
+    fn runway_number_is_magnetic() {
+        let mut runway_07 = end("07", 70.0);
+        runway_07.heading = None;
+        assert_eq!(runway_07.true_heading(None), None);
+        assert_eq!(runway_07.true_heading(Some(-3.0)), Some(67.0));
+        assert!(WindComponents::compute(&runway_07, wind(70.0, 20.0), None).is_none());
+        let components = WindComponents::compute(&runway_07, wind(67.0, 20.0), Some(-3.0)).unwrap();
+        assert!(components.crosswind.abs() < 1e-9);
+
+        // A known true heading is used as is
+        assert_eq!(end("36", 2.0).true_heading(Some(-3.0)), Some(2.0));
+        let mut runway_36 = end("36", 2.0);
+        runway_36.heading = None;
+        assert_eq!(runway_36.true_heading(Some(4.0)), Some(4.0));
+    }
+
+    #[test]
+    fn closed_runways_are_not_proposed() {
+        let mut closed = runway(("07", 70.0), ("25", 250.0));
+        closed.closed = true;
+        let selection = select_runway("LFPN", &[closed], wind(250.0, 10.0), None, None);
+        assert!(selection.recommended.is_none());
+        assert!(matches!(&selection.warnings[..], [RunwayError::no_runway(_)]));
+    }
+}
@@ -0,0 +1,248 @@
+use rusqlite::Connection;
+
+use super::airport::{Airport, AirportType, AIRPORT_COLUMNS};
+
+/// Restricts an airport search
+#[derive(Debug, Clone, Default)]
+pub struct AirportFilter {
+    pub country: Option<String>,   // ISO 3166-1 code, e.g. "FR"
+    pub types: Vec<AirportType>,   // empty for all types
+    pub limit: usize,              // 0 for no limit
+}
+
+/// Lowercases and removes accents, so that "Saint-Cyr-l'École" matches "saint cyr l ecole"
+pub fn normalize(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    for c in text.chars().flat_map(char::to_lowercase) {
+        match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ą' => normalized.push('a'),
+            'ç' | 'ć' | 'č' => normalized.push('c'),
+            'ď' | 'đ' => normalized.push('d'),
+            'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ę' | 'ě' => normalized.push('e'),
+            'ì' | 'í' | 'î' | 'ï' | 'ī' => normalized.push('i'),
+            'ł' | 'ľ' => normalized.push('l'),
+            'ñ' | 'ń' | 'ň' => normalized.push('n'),
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' => normalized.push('o'),
+            'ř' => normalized.push('r'),
+            'ś' | 'š' | 'ș' | 'ş' => normalized.push('s'),
+            'ť' | 'ț' | 'ţ' => normalized.push('t'),
+            'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' => normalized.push('u'),
+            'ý' | 'ÿ' => normalized.push('y'),
+            'ź' | 'ż' | 'ž' => normalized.push('z'),
+            'æ' => normalized.push_str("ae"),
+            'œ' => normalized.push_str("oe"),
+            'ß' => normalized.push_str("ss"),
+            c if c.is_alphanumeric() => normalized.push(c),
+            _ => normalized.push(' '),
+        }
+    }
+    normalized
+}
+
+/// Text the SQL prefilter of `search_airports` looks into: the normalized codes, name, municipality and keywords
+fn search_text(airport: &Airport) -> String {
+    let fields = [
+        &airport.oaci_code,
+        &airport.iata_code,
+        &airport.gps_code,
+        &airport.local_code,
+        &airport.name,
+        &airport.municipality,
+        &airport.keywords,
+    ];
+    let text = fields.iter().map(|field| normalize(field)).collect::<Vec<_>>().join(" ");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Adds the `search_text` column to the airports table if needed and fills it for the airports without it
+pub fn index_airports(db_path: &str) -> Result<usize, rusqlite::Error> {
+    let mut conn = Connection::open(db_path)?;
+    let indexed: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('airports') WHERE name = 'search_text'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !indexed {
+        conn.execute_batch("ALTER TABLE airports ADD COLUMN search_text TEXT")?;
+    }
+    let airports: Vec<Airport> = conn
+        .prepare(&format!("SELECT {} FROM airports WHERE search_text IS NULL", AIRPORT_COLUMNS))?
+        .query_map([], Airport::airport_mapper)?
+        .collect::<Result<_, _>>()?;
+    let transaction = conn.transaction()?;
+    {
+        let mut update = transaction.prepare("UPDATE airports SET search_text = ?1 WHERE ident = ?2")?;
+        for airport in &airports {
+            update.execute([search_text(airport), airport.oaci_code.clone()])?;
+        }
+    }
+    transaction.commit()?;
+    Ok(airports.len())
+}
+
+/// Edit distance between two words
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Score of one query word against a text field: prefix of a word, inside a word, or a close spelling
+fn word_score(query: &str, field: &str, weight: u32) -> u32 {
+    let mut best = 0;
+    for word in field.split_whitespace() {
+        let score = if word == query {
+            weight
+        } else if word.starts_with(query) {
+            weight * 3 / 4
+        } else if query.len() >= 3 && word.contains(query) {
+            weight / 2
+        } else {
+            let allowed = match query.chars().count() {
+                0..=3 => 0,
+                4..=6 => 1,
+                _ => 2,
+            };
+            // Compare with the start of the word too, for words still being typed
+            let prefix: String = word.chars().take(query.chars().count()).collect();
+            let distance = levenshtein(query, word).min(levenshtein(query, &prefix) + 1);
+            if distance <= allowed {
+                weight / 3 - (distance as u32) * weight / 12
+            } else {
+                0
+            }
+        };
+        best = best.max(score);
+    }
+    best
+}
+
+/// Relevance of an airport for a normalized query, 0 if some word of the query matches nothing
+fn score(airport: &Airport, query: &str) -> u32 {
+    let codes = [&airport.oaci_code, &airport.iata_code, &airport.local_code, &airport.gps_code];
+    let name = normalize(&airport.name);
+    let municipality = normalize(&airport.municipality);
+    let keywords = normalize(&airport.keywords);
+
+    let mut total = 0;
+    for word in query.split_whitespace() {
+        let code_score = codes
+            .iter()
+            .map(|code| {
+                let code = code.to_lowercase();
+                if code == word {
+                    120
+                } else if !code.is_empty() && code.starts_with(word) {
+                    90
+                } else {
+                    0
+                }
+            })
+            .max()
+            .unwrap_or(0);
+        let best = code_score
+            .max(word_score(word, &name, 100))
+            .max(word_score(word, &municipality, 80))
+            .max(word_score(word, &keywords, 60));
+        if best == 0 {
+            return 0;
+        }
+        total += best;
+    }
+    // Bigger airports first among equally relevant ones
+    total * 4
+        + match airport.airport_type {
+            AirportType::large_airport => 3,
+            AirportType::medium_airport => 2,
+            AirportType::small_airport => 1,
+            _ => 0,
+        }
+}
+
+/// Searches airports by ICAO, IATA, GPS or local code, partial name, municipality or keyword.
+/// Matching ignores case and accents and tolerates small typos; best matches come first.
+pub fn search_airports(db_path: &str, query: &str, filter: &AirportFilter) -> Result<Vec<Airport>, rusqlite::Error> {
+    // "St" and "Ste" are the usual abbreviations of "Saint" and "Sainte" in French names
+    let normalized = normalize(query);
+    let query = normalized
+        .split_whitespace()
+        .map(|word| match word {
+            "st" => "saint",
+            "ste" => "sainte",
+            word => word,
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Airports containing every word of the query, as typed, are enough most of the time;
+    // all of them are scored only when none does, to tolerate typos
+    let words: Vec<&str> = query.split_whitespace().collect();
+    let ranked = rank_airports(db_path, &query, filter, &words)?;
+    let mut ranked = if ranked.is_empty() { rank_airports(db_path, &query, filter, &[])? } else { ranked };
+    ranked.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    if filter.limit > 0 {
+        ranked.truncate(filter.limit);
+    }
+    Ok(ranked.into_iter().map(|(_, airport)| airport).collect())
+}
+
+/// Scores the airports of the filter whose `search_text` contains each of `words`, leaving out those not matching `query`
+fn rank_airports(db_path: &str, query: &str, filter: &AirportFilter, words: &[&str]) -> Result<Vec<(u32, Airport)>, rusqlite::Error> {
+    let conn = Connection::open(db_path)?;
+    let mut sql = format!("SELECT {} FROM airports WHERE 1 = 1", AIRPORT_COLUMNS);
+    let mut params: Vec<String> = Vec::new();
+    for word in words {
+        params.push(format!("%{}%", word));
+        sql.push_str(&format!(" AND search_text LIKE ?{}", params.len()));
+    }
+    if let Some(country) = &filter.country {
+        params.push(country.to_uppercase());
+        sql.push_str(&format!(" AND iso_country = ?{}", params.len()));
+    }
+    if !filter.types.is_empty() {
+        let mut placeholders = Vec::new();
+        for airport_type in &filter.types {
+            params.push(airport_type.db_type().to_string());
+            placeholders.push(format!("?{}", params.len()));
+        }
+        sql.push_str(&format!(" AND type IN ({})", placeholders.join(", ")));
+    }
+    let mut stmt = conn.prepare(&sql)?;
+    let airports = stmt.query_map(rusqlite::params_from_iter(params), Airport::airport_mapper)?;
+
+    let mut ranked = Vec::new();
+    for airport in airports {
+        let airport = airport?;
+        let score = score(&airport, query);
+        if score > 0 {
+            ranked.push((score, airport));
+        }
+    }
+    Ok(ranked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_toussus() {
+        let filter = AirportFilter { limit: 10, ..AirportFilter::default() };
+        let airports = search_airports("data/airports.db", "toussus", &filter).unwrap();
+        assert_eq!(airports[0].oaci_code, "LFPN");
+        // A typo finds nothing in the prefilter and falls back to scoring every airport
+        let airports = search_airports("data/airports.db", "tousus", &filter).unwrap();
+        assert_eq!(airports[0].oaci_code, "LFPN");
+    }
+}
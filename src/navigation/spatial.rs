@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use rusqlite::Connection;
+
+use super::airport::{Airport, AIRPORT_COLUMNS};
+use super::flightplan::Navigation;
+use super::geographics::{along_track_distance, calculate_distance_and_course, cross_track_distance, NavPoint};
+
+/// Kilometers per degree of latitude
+const KM_PER_DEGREE: f64 = 111.19;
+
+/// Creates the `airports_rtree` SQLite R*Tree index on the airport positions, and the triggers
+/// keeping it in sync with the `airports` table. Safe to call on an already indexed database.
+pub fn create_index(db_path: &str) -> Result<(), rusqlite::Error> {
+    let conn = Connection::open(db_path)?;
+    conn.execute_batch(
+        "BEGIN;
+        CREATE VIRTUAL TABLE IF NOT EXISTS airports_rtree USING rtree(id, min_lat, max_lat, min_lon, max_lon);
+        DELETE FROM airports_rtree;
+        INSERT INTO airports_rtree
+            SELECT CAST(id AS INTEGER), latitude_deg, latitude_deg, longitude_deg, longitude_deg FROM airports;
+        CREATE TRIGGER IF NOT EXISTS airports_rtree_insert AFTER INSERT ON airports BEGIN
+            INSERT OR REPLACE INTO airports_rtree
+                VALUES (CAST(NEW.id AS INTEGER), NEW.latitude_deg, NEW.latitude_deg, NEW.longitude_deg, NEW.longitude_deg);
+        END;
+        CREATE TRIGGER IF NOT EXISTS airports_rtree_update AFTER UPDATE OF id, latitude_deg, longitude_deg ON airports BEGIN
+            DELETE FROM airports_rtree WHERE id = CAST(OLD.id AS INTEGER);
+            INSERT OR REPLACE INTO airports_rtree
+                VALUES (CAST(NEW.id AS INTEGER), NEW.latitude_deg, NEW.latitude_deg, NEW.longitude_deg, NEW.longitude_deg);
+        END;
+        CREATE TRIGGER IF NOT EXISTS airports_rtree_delete AFTER DELETE ON airports BEGIN
+            DELETE FROM airports_rtree WHERE id = CAST(OLD.id AS INTEGER);
+        END;
+        COMMIT;",
+    )
+}
+
+/// Creates the `airports_rtree` index unless the database already has it, see `create_index`.
+/// Called at startup and before importing airports, whose triggers then keep the index in sync.
+pub fn ensure_index(db_path: &str) -> Result<(), rusqlite::Error> {
+    let exists = Connection::open(db_path)?
+        .prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'airports_rtree'")?
+        .exists([])?;
+    if exists {
+        return Ok(());
+    }
+    create_index(db_path)
+}
+
+/// Airports inside a latitude/longitude box in degrees, through the R*Tree index.
+/// Boxes crossing the antimeridian must first be split, see `boxes_around`.
+pub fn airports_in_box(conn: &Connection, min_latitude: f64, max_latitude: f64, min_longitude: f64, max_longitude: f64) -> Result<Vec<Airport>, rusqlite::Error> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {} FROM airports JOIN airports_rtree ON airports_rtree.id = CAST(airports.id AS INTEGER)
+        WHERE airports_rtree.max_lat >= ?1 AND airports_rtree.min_lat <= ?2
+        AND airports_rtree.max_lon >= ?3 AND airports_rtree.min_lon <= ?4",
+        AIRPORT_COLUMNS
+    ))?;
+    let airports = stmt.query_map([min_latitude, max_latitude, min_longitude, max_longitude], Airport::airport_mapper)?;
+    airports.collect()
+}
+
+/// Box around a point, `radius` in km
+fn box_around(latitude: f64, longitude: f64, radius: f64) -> (f64, f64, f64, f64) {
+    let delta_lat = radius / KM_PER_DEGREE;
+    let delta_lon = radius / (KM_PER_DEGREE * latitude.to_radians().cos().max(0.01));
+    (latitude - delta_lat, latitude + delta_lat, longitude - delta_lon, longitude + delta_lon)
+}
+
+/// Boxes around a point, `radius` in km, as `(south, north, west, east)` in degrees.
+/// A box crossing the antimeridian is split in two, one on each side.
+pub(crate) fn boxes_around(latitude: f64, longitude: f64, radius: f64) -> Vec<(f64, f64, f64, f64)> {
+    let (south, north, west, east) = box_around(latitude, longitude, radius);
+    if east - west >= 360.0 {
+        vec![(south, north, -180.0, 180.0)]
+    } else if west < -180.0 {
+        vec![(south, north, west + 360.0, 180.0), (south, north, -180.0, east)]
+    } else if east > 180.0 {
+        vec![(south, north, west, 180.0), (south, north, -180.0, east - 360.0)]
+    } else {
+        vec![(south, north, west, east)]
+    }
+}
+
+fn distance_to(point: &NavPoint, airport: &Airport) -> f64 {
+    let (distance, _) = calculate_distance_and_course(point, &NavPoint::new(String::new(), airport.latitude, airport.longitude));
+    distance.unwrap_or(f64::INFINITY)
+}
+
+/// All airports within `radius` km of a point, nearest first, with their distance in km
+pub fn airports_within_radius(db_path: &str, point: &NavPoint, radius: f64) -> Result<Vec<(Airport, f64)>, rusqlite::Error> {
+    let conn = Connection::open(db_path)?;
+    within_radius(&conn, point, radius)
+}
+
+fn within_radius(conn: &Connection, point: &NavPoint, radius: f64) -> Result<Vec<(Airport, f64)>, rusqlite::Error> {
+    let mut airports: Vec<(Airport, f64)> = Vec::new();
+    for (south, north, west, east) in boxes_around(point.latitude, point.longitude, radius) {
+        for airport in airports_in_box(conn, south, north, west, east)? {
+            let distance = distance_to(point, &airport);
+            if distance <= radius {
+                airports.push((airport, distance));
+            }
+        }
+    }
+    airports.sort_by(|a, b| a.1.total_cmp(&b.1));
+    Ok(airports)
+}
+
+/// The `count` airports nearest to a point, with their distance in km.
+/// The search radius starts at 20 km and doubles until enough airports are found.
+pub fn nearest_airports(db_path: &str, point: &NavPoint, count: usize) -> Result<Vec<(Airport, f64)>, rusqlite::Error> {
+    let conn = Connection::open(db_path)?;
+    let mut radius = 20.0;
+    loop {
+        let mut airports = within_radius(&conn, point, radius)?;
+        if airports.len() >= count || radius > 20000.0 {
+            airports.truncate(count);
+            return Ok(airports);
+        }
+        radius *= 2.0;
+    }
+}
+
+/// All airports within `width` km each side of the route, with their distance to the route in km,
+/// sorted by distance along the route
+pub fn airports_along_route(db_path: &str, navigation: &Navigation, width: f64) -> Result<Vec<(Airport, f64)>, rusqlite::Error> {
+    let conn = Connection::open(db_path)?;
+    let mut found: HashMap<String, (Airport, f64, f64)> = HashMap::new();
+    let mut route_start = 0.0;
+    for branch in &navigation.branches {
+        let (Some(from), Some(to), Some(length)) = (&branch.from, &branch.to, branch.distance) else {
+            continue;
+        };
+        let (south, _, west, _) = box_around(from.latitude.min(to.latitude), from.longitude.min(to.longitude), width);
+        let (_, north, _, east) = box_around(from.latitude.max(to.latitude), from.longitude.max(to.longitude), width);
+        for airport in airports_in_box(&conn, south, north, west, east)? {
+            let position = NavPoint::new(String::new(), airport.latitude, airport.longitude);
+            let along = along_track_distance(from, to, &position).unwrap_or(0.0);
+            let distance = if (0.0..=length).contains(&along) {
+                cross_track_distance(from, to, &position).unwrap_or(f64::INFINITY).abs()
+            } else {
+                distance_to(from, &airport).min(distance_to(to, &airport))
+            };
+            if distance > width {
+                continue;
+            }
+            let along = route_start + along.clamp(0.0, length);
+            match found.get(&airport.oaci_code) {
+                Some((_, known, _)) if *known <= distance => {}
+                _ => {
+                    found.insert(airport.oaci_code.clone(), (airport, distance, along));
+                }
+            }
+        }
+        route_start += length;
+    }
+    let mut airports: Vec<(Airport, f64, f64)> = found.into_values().collect();
+    airports.sort_by(|a, b| a.2.total_cmp(&b.2));
+    Ok(airports.into_iter().map(|(airport, distance, _)| (airport, distance)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_is_created_when_missing_and_kept_in_sync() {
+        let db_path = std::env::temp_dir().join("flight_planner_spatial_index.db");
+        let db_path = db_path.to_str().unwrap();
+        std::fs::copy("data/airports.db", db_path).unwrap();
+        Connection::open(db_path)
+            .unwrap()
+            .execute_batch(
+                "DROP TRIGGER IF EXISTS airports_rtree_insert;
+                DROP TRIGGER IF EXISTS airports_rtree_update;
+                DROP TRIGGER IF EXISTS airports_rtree_delete;
+                DROP TABLE airports_rtree;",
+            )
+            .unwrap();
+        assert!(Airport::in_area(db_path, 48.7, 48.8, 2.0, 2.2).is_err());
+
+        ensure_index(db_path).unwrap();
+        let airports = Airport::in_area(db_path, 48.7, 48.8, 2.0, 2.2).unwrap();
+        assert!(airports.iter().any(|a| a.oaci_code == "LFPN"));
+        // Already indexed: nothing to do
+        ensure_index(db_path).unwrap();
+
+        let conn = Connection::open(db_path).unwrap();
+        conn.execute("UPDATE airports SET latitude_deg = 45.0, longitude_deg = 5.0 WHERE ident = 'LFPN'", []).unwrap();
+        assert!(!airports_in_box(&conn, 48.7, 48.8, 2.0, 2.2).unwrap().iter().any(|a| a.oaci_code == "LFPN"));
+        assert!(airports_in_box(&conn, 44.9, 45.1, 4.9, 5.1).unwrap().iter().any(|a| a.oaci_code == "LFPN"));
+        std::fs::remove_file(db_path).unwrap();
+    }
+
+    #[test]
+    fn nearest_airports_are_sorted_by_distance() {
+        let toussus = NavPoint::new("LFPN".to_string(), 48.7519, 2.1061);
+        let airports = nearest_airports("data/airports.db", &toussus, 3).unwrap();
+        assert_eq!(airports.len(), 3);
+        assert_eq!(airports[0].0.oaci_code, "LFPN");
+        assert!(airports.windows(2).all(|w| w[0].1 <= w[1].1));
+    }
+}
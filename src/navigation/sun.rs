@@ -0,0 +1,136 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+/// Sun elevation at sunrise and sunset, accounting for refraction and the solar disc
+const SUNRISE_ELEVATION: f64 = -0.833;
+/// Sun elevation at the start and end of civil twilight
+const CIVIL_TWILIGHT_ELEVATION: f64 = -6.0;
+/// Obliquity of the ecliptic, in degrees
+const EARTH_OBLIQUITY: f64 = 23.4397;
+/// Julian day of 2000-01-01 12:00 UTC
+const J2000: f64 = 2451545.0;
+/// Julian day of the unix epoch
+const UNIX_EPOCH_JD: f64 = 2440587.5;
+
+/// Margin around sunrise and sunset defining legal day for VFR day flights (30 minutes in most of Europe)
+pub fn vfr_day_margin() -> Duration {
+    Duration::minutes(30)
+}
+
+/// Whether the sun rises and sets on a given day
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Daylight {
+    /// The sun rises and sets
+    normal,
+    /// Polar day: the sun stays above the horizon
+    always_up,
+    /// Polar night: the sun stays below the horizon
+    always_down,
+}
+
+/// Sun events of a day at a location, in UTC.
+/// An event is `None` when it does not happen that day (polar day or night), see `daylight`.
+#[derive(Debug, Clone)]
+pub struct SunTimes {
+    pub daylight: Daylight,
+    pub civil_dawn: Option<DateTime<Utc>>,
+    pub sunrise: Option<DateTime<Utc>>,
+    pub sunset: Option<DateTime<Utc>>,
+    pub civil_dusk: Option<DateTime<Utc>>,
+}
+
+impl SunTimes {
+    /// Computes the sun events for a location in degrees (longitude east positive) on a given date.
+    ///
+    /// Uses the NOAA simplified solar position algorithm, accurate to about a minute at mid latitudes.
+    /// Fully offline.
+    pub fn compute(latitude: f64, longitude: f64, date: NaiveDate) -> SunTimes {
+        let days = (date - NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()).num_days() as f64;
+        let mean_solar_noon = days - longitude / 360.0;
+
+        let anomaly = (357.5291 + 0.98560028 * mean_solar_noon).rem_euclid(360.0).to_radians();
+        let center = 1.9148 * anomaly.sin() + 0.02 * (2.0 * anomaly).sin() + 0.0003 * (3.0 * anomaly).sin();
+        let ecliptic_longitude = (anomaly.to_degrees() + center + 180.0 + 102.9372).rem_euclid(360.0).to_radians();
+        let transit = J2000 + mean_solar_noon + 0.0053 * anomaly.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+        let declination = (ecliptic_longitude.sin() * EARTH_OBLIQUITY.to_radians().sin()).asin();
+
+        // Cosine of the hour angle at which the sun crosses `elevation`, below -1 when it stays above all day
+        // and above 1 when it stays below
+        let cos_hour_angle = |elevation: f64| -> f64 {
+            let lat = latitude.to_radians();
+            (elevation.to_radians().sin() - lat.sin() * declination.sin()) / (lat.cos() * declination.cos())
+        };
+        let event = |elevation: f64, rising: bool| -> Option<DateTime<Utc>> {
+            let cos_hour_angle = cos_hour_angle(elevation);
+            if !(-1.0..=1.0).contains(&cos_hour_angle) {
+                return None;
+            }
+            let offset = cos_hour_angle.acos().to_degrees() / 360.0;
+            let julian_day = if rising { transit - offset } else { transit + offset };
+            DateTime::from_timestamp(((julian_day - UNIX_EPOCH_JD) * 86400.0).round() as i64, 0)
+        };
+
+        let sunrise_hour_angle = cos_hour_angle(SUNRISE_ELEVATION);
+        SunTimes {
+            daylight: if sunrise_hour_angle < -1.0 {
+                Daylight::always_up
+            } else if sunrise_hour_angle > 1.0 {
+                Daylight::always_down
+            } else {
+                Daylight::normal
+            },
+            civil_dawn: event(CIVIL_TWILIGHT_ELEVATION, true),
+            sunrise: event(SUNRISE_ELEVATION, true),
+            sunset: event(SUNRISE_ELEVATION, false),
+            civil_dusk: event(CIVIL_TWILIGHT_ELEVATION, false),
+        }
+    }
+
+    /// Start of legal day, 30 minutes before sunrise
+    pub fn day_start(&self) -> Option<DateTime<Utc>> {
+        self.sunrise.map(|t| t - vfr_day_margin())
+    }
+
+    /// End of legal day, 30 minutes after sunset
+    pub fn day_end(&self) -> Option<DateTime<Utc>> {
+        self.sunset.map(|t| t + vfr_day_margin())
+    }
+
+    /// Whether `time` falls within legal day, all day long during polar day
+    pub fn is_day(&self, time: DateTime<Utc>) -> bool {
+        match (self.daylight, self.day_start(), self.day_end()) {
+            (Daylight::always_up, _, _) => true,
+            (Daylight::normal, Some(start), Some(end)) => start <= time && time <= end,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polar_day_is_day() {
+        // Tromsø, midnight sun
+        let sun = SunTimes::compute(69.68, 18.94, NaiveDate::from_ymd_opt(2026, 6, 21).unwrap());
+        assert_eq!(sun.daylight, Daylight::always_up);
+        assert!(sun.sunrise.is_none() && sun.sunset.is_none());
+        assert!(sun.is_day(DateTime::parse_from_rfc3339("2026-06-21T23:30:00Z").unwrap().into()));
+    }
+
+    #[test]
+    fn polar_night_is_not_day() {
+        let sun = SunTimes::compute(69.68, 18.94, NaiveDate::from_ymd_opt(2026, 12, 21).unwrap());
+        assert_eq!(sun.daylight, Daylight::always_down);
+        assert!(!sun.is_day(DateTime::parse_from_rfc3339("2026-12-21T11:00:00Z").unwrap().into()));
+    }
+
+    #[test]
+    fn mid_latitude_day() {
+        // Toussus-le-Noble, sunrise around 03:47 UTC, legal day from 03:17 UTC
+        let sun = SunTimes::compute(48.75, 2.11, NaiveDate::from_ymd_opt(2026, 6, 21).unwrap());
+        assert_eq!(sun.daylight, Daylight::normal);
+        assert!(sun.is_day(DateTime::parse_from_rfc3339("2026-06-21T03:30:00Z").unwrap().into()));
+        assert!(!sun.is_day(DateTime::parse_from_rfc3339("2026-06-21T02:45:00Z").unwrap().into()));
+    }
+}
@@ -0,0 +1,437 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::error::TerrainError;
+use super::flightplan::{NavBranch, Navigation};
+use super::geographics::{NavPoint, KM_TO_NM};
+
+const FEET_PER_METER: f64 = 3.28084;
+/// Mean Earth radius of the flat projection of the corridors, km
+const EARTH_RADIUS: f64 = 6371.0;
+/// Longest segment of a leg checked on a flat projection, km
+const SEGMENT_LENGTH: f64 = 20.0;
+/// Value of a missing sample in an HGT tile
+const HGT_VOID: i16 = -32768;
+
+/// Settings of the terrain clearance check
+#[derive(Debug, Clone)]
+pub struct TerrainConfig {
+    pub tiles_dir: PathBuf,      // directory of the SRTM .hgt tiles (N48E002.hgt, ...)
+    pub obstacle_file: PathBuf,  // CSV file with name,latitude,longitude,elevation_ft
+    pub corridor_width: f64,     // NM each side of the track
+    pub margin: f64,             // ft above the highest terrain or obstacle
+}
+
+impl TerrainConfig {
+    pub fn new(data_dir: &str) -> TerrainConfig {
+        let data_dir = Path::new(data_dir);
+        TerrainConfig {
+            tiles_dir: data_dir.join("terrain"),
+            obstacle_file: data_dir.join("obstacles.csv"),
+            corridor_width: 5.0,
+            margin: 1000.0,
+        }
+    }
+}
+
+/// An obstacle, with the elevation of its top in ft AMSL
+#[derive(Debug, Clone, Deserialize)]
+pub struct Obstacle {
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    #[serde(rename = "elevation_ft")]
+    pub elevation: f64,
+}
+
+/// Highest terrain and obstacle around a leg, and the resulting minimum safe altitude
+#[derive(Debug, Clone)]
+pub struct LegClearance {
+    pub leg: String,
+    pub highest_terrain: Option<f64>, // ft
+    pub highest_obstacle: Option<Obstacle>,
+    pub minimum_safe_altitude: f64,   // ft
+    pub planned_altitude: Option<f64>, // ft
+    pub missing_terrain: bool,        // some samples fell outside the available tiles
+}
+
+impl LegClearance {
+    /// Whether the leg cannot be flown at its planned altitude: below the minimum safe altitude, or
+    /// over terrain missing from the tiles, where the minimum safe altitude cannot be trusted
+    pub fn is_below_minimum(&self) -> bool {
+        self.missing_terrain || self.planned_altitude.is_some_and(|a| a < self.minimum_safe_altitude)
+    }
+}
+
+/// Corridor around a leg, on a flat projection centred on its start, distances in km
+struct Corridor {
+    latitude: f64,
+    longitude: f64,
+    scale: f64, // km per degree of longitude, over degrees of latitude
+    end: (f64, f64),
+    width: f64,
+}
+
+impl Corridor {
+    fn new(from: &NavPoint, to: &NavPoint, width: f64) -> Corridor {
+        let scale = ((from.latitude + to.latitude) / 2.0).to_radians().cos();
+        let mut corridor = Corridor {
+            latitude: from.latitude,
+            longitude: from.longitude,
+            scale,
+            end: (0.0, 0.0),
+            width,
+        };
+        corridor.end = corridor.project(to.latitude, to.longitude);
+        corridor
+    }
+
+    fn project(&self, latitude: f64, longitude: f64) -> (f64, f64) {
+        (
+            (longitude - self.longitude).to_radians() * self.scale * EARTH_RADIUS,
+            (latitude - self.latitude).to_radians() * EARTH_RADIUS,
+        )
+    }
+
+    /// Distance from a point to the leg
+    fn distance(&self, latitude: f64, longitude: f64) -> f64 {
+        let (px, py) = self.project(latitude, longitude);
+        let (bx, by) = self.end;
+        let length = bx * bx + by * by;
+        let t = if length > 0.0 { ((px * bx + py * by) / length).clamp(0.0, 1.0) } else { 0.0 };
+        ((px - t * bx).powi(2) + (py - t * by).powi(2)).sqrt()
+    }
+
+    /// Southern and northern latitudes of the corridor
+    fn latitudes(&self) -> (f64, f64) {
+        let margin = (self.width / EARTH_RADIUS).to_degrees();
+        let end = self.latitude + (self.end.1 / EARTH_RADIUS).to_degrees();
+        (self.latitude.min(end) - margin, self.latitude.max(end) + margin)
+    }
+
+    /// Western and eastern longitudes of the corridor at `latitude`, `None` outside of it.
+    /// The corridor is the union of the discs around both ends and the band along the leg,
+    /// each crossing the parallel on a single interval.
+    fn longitudes(&self, latitude: f64) -> Option<(f64, f64)> {
+        let y = (latitude - self.latitude).to_radians() * EARTH_RADIUS;
+        let (bx, by) = self.end;
+        let mut xs: Vec<f64> = Vec::new();
+        for (cx, cy) in [(0.0, 0.0), (bx, by)] {
+            let half = self.width * self.width - (y - cy) * (y - cy);
+            if half >= 0.0 {
+                xs.extend([cx - half.sqrt(), cx + half.sqrt()]);
+            }
+        }
+        let length = (bx * bx + by * by).sqrt();
+        if length > 0.0 {
+            let (nx, ny) = (-by / length * self.width, bx / length * self.width);
+            let corners = [(nx, ny), (bx + nx, by + ny), (bx - nx, by - ny), (-nx, -ny)];
+            for k in 0..4 {
+                let ((px, py), (qx, qy)) = (corners[k], corners[(k + 1) % 4]);
+                if py != qy && (py.min(qy)..=py.max(qy)).contains(&y) {
+                    xs.push(px + (y - py) * (qx - px) / (qy - py));
+                }
+            }
+        }
+        let west = xs.iter().copied().reduce(f64::min)?;
+        let east = xs.iter().copied().reduce(f64::max)?;
+        let to_longitude = |x: f64| self.longitude + (x / (self.scale * EARTH_RADIUS)).to_degrees();
+        Some((to_longitude(west), to_longitude(east)))
+    }
+}
+
+struct Tile {
+    size: usize,
+    samples: Vec<i16>,
+}
+
+/// Offline terrain and obstacle database
+pub struct Terrain {
+    config: TerrainConfig,
+    tiles: HashMap<(i32, i32), Option<Tile>>,
+    obstacles: Vec<Obstacle>,
+}
+
+impl Terrain {
+    /// Opens the terrain database. Tiles are loaded on first use; a missing obstacle file
+    /// means no obstacle.
+    pub fn open(config: TerrainConfig) -> Result<Terrain, TerrainError> {
+        let mut obstacles = Vec::new();
+        if config.obstacle_file.exists() {
+            let mut reader = csv::Reader::from_path(&config.obstacle_file)?;
+            for obstacle in reader.deserialize() {
+                obstacles.push(obstacle?);
+            }
+        }
+        Ok(Terrain {
+            config,
+            tiles: HashMap::new(),
+            obstacles,
+        })
+    }
+
+    fn tile_name(latitude: i32, longitude: i32) -> String {
+        format!(
+            "{}{:02}{}{:03}.hgt",
+            if latitude >= 0 { 'N' } else { 'S' },
+            latitude.abs(),
+            if longitude >= 0 { 'E' } else { 'W' },
+            longitude.abs()
+        )
+    }
+
+    fn load_tile(&self, latitude: i32, longitude: i32) -> Result<Option<Tile>, TerrainError> {
+        let name = Self::tile_name(latitude, longitude);
+        let path = self.config.tiles_dir.join(&name);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let bytes = fs::read(path)?;
+        let size = ((bytes.len() / 2) as f64).sqrt() as usize;
+        if size < 2 || size * size * 2 != bytes.len() {
+            return Err(TerrainError::wrong_tile_size(name));
+        }
+        let samples = bytes.chunks_exact(2).map(|b| i16::from_be_bytes([b[0], b[1]])).collect();
+        Ok(Some(Tile { size, samples }))
+    }
+
+    /// Tile covering the square of `latitude`, `longitude` (its south-west corner), loaded on first use
+    fn tile(&mut self, latitude: i32, longitude: i32) -> Result<Option<&Tile>, TerrainError> {
+        if !self.tiles.contains_key(&(latitude, longitude)) {
+            let tile = self.load_tile(latitude, longitude)?;
+            self.tiles.insert((latitude, longitude), tile);
+        }
+        Ok(self.tiles[&(latitude, longitude)].as_ref())
+    }
+
+    /// Terrain elevation in ft at a point, `None` if no tile covers it or the sample is void
+    pub fn elevation(&mut self, latitude: f64, longitude: f64) -> Result<Option<f64>, TerrainError> {
+        let key = (latitude.floor() as i32, longitude.floor() as i32);
+        let Some(tile) = self.tile(key.0, key.1)? else {
+            return Ok(None);
+        };
+        let last = (tile.size - 1) as f64;
+        let row = ((key.0 as f64 + 1.0 - latitude) * last).round() as usize;
+        let col = ((longitude - key.1 as f64) * last).round() as usize;
+        let sample = tile.samples[row.min(tile.size - 1) * tile.size + col.min(tile.size - 1)];
+        if sample == HGT_VOID {
+            return Ok(None);
+        }
+        Ok(Some(sample as f64 * FEET_PER_METER))
+    }
+
+    /// Highest terrain sample in ft within a corridor, and whether some of it is missing.
+    /// Every sample of the tiles within the corridor is read once, row by row, so that no peak
+    /// between samples is missed and the work only grows with the area of the corridor.
+    fn highest_terrain(&mut self, corridor: &Corridor) -> Result<(Option<f64>, bool), TerrainError> {
+        let (south, north) = corridor.latitudes();
+
+        let mut highest_terrain: Option<f64> = None;
+        let mut missing_terrain = false;
+        for tile_latitude in south.floor() as i32..=north.floor() as i32 {
+            // Parallels of the corridor within the tile, 0.01° apart to find the tiles it crosses
+            let (band_south, band_north) = (south.max(tile_latitude as f64), north.min(tile_latitude as f64 + 1.0));
+            let steps = ((band_north - band_south) * 100.0).ceil().max(1.0) as usize;
+            let band: Vec<(f64, f64)> = (0..=steps)
+                .filter_map(|k| corridor.longitudes(band_south + (band_north - band_south) * k as f64 / steps as f64))
+                .collect();
+            let Some(west) = band.iter().map(|b| b.0).reduce(f64::min) else {
+                continue;
+            };
+            let east = band.iter().map(|b| b.1).fold(west, f64::max);
+
+            for tile_longitude in west.floor() as i32..=east.floor() as i32 {
+                let Some(tile) = self.tile(tile_latitude, tile_longitude)? else {
+                    missing_terrain = true;
+                    continue;
+                };
+                let last = (tile.size - 1) as f64;
+                let first_row = ((tile_latitude as f64 + 1.0 - band_north) * last).floor().max(0.0) as usize;
+                let last_row = (((tile_latitude as f64 + 1.0 - band_south) * last).ceil() as usize).min(tile.size - 1);
+                for row in first_row..=last_row {
+                    let latitude = tile_latitude as f64 + 1.0 - row as f64 / last;
+                    let Some((row_west, row_east)) = corridor.longitudes(latitude) else {
+                        continue;
+                    };
+                    let first_col = ((row_west - tile_longitude as f64) * last).ceil().max(0.0) as usize;
+                    let last_col = ((row_east - tile_longitude as f64) * last).floor().min(last);
+                    if last_col < 0.0 {
+                        continue;
+                    }
+                    for col in first_col..=last_col as usize {
+                        match tile.samples[row * tile.size + col] {
+                            HGT_VOID => missing_terrain = true,
+                            sample => {
+                                let elevation = sample as f64 * FEET_PER_METER;
+                                highest_terrain = Some(highest_terrain.map_or(elevation, |h| h.max(elevation)));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok((highest_terrain, missing_terrain))
+    }
+
+    /// Computes the highest terrain and obstacle within the corridor around a leg, following its
+    /// great circle or rhumb line in segments of `SEGMENT_LENGTH`.
+    /// Returns `None` if the leg has no `from` or `to` point.
+    pub fn leg_clearance(&mut self, branch: &NavBranch) -> Result<Option<LegClearance>, TerrainError> {
+        if branch.from.is_none() || branch.to.is_none() {
+            return Ok(None);
+        }
+        let width = self.config.corridor_width / KM_TO_NM;
+        let track = branch.track(SEGMENT_LENGTH);
+        let corridors: Vec<Corridor> = track.windows(2).map(|s| Corridor::new(&s[0], &s[1], width)).collect();
+
+        let mut highest_terrain: Option<f64> = None;
+        let mut missing_terrain = false;
+        for corridor in &corridors {
+            let (highest, missing) = self.highest_terrain(corridor)?;
+            highest_terrain = match (highest_terrain, highest) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                (a, b) => a.or(b),
+            };
+            missing_terrain |= missing;
+        }
+
+        let highest_obstacle = self
+            .obstacles
+            .iter()
+            .filter(|o| corridors.iter().any(|c| c.distance(o.latitude, o.longitude) <= width))
+            .max_by(|a, b| a.elevation.total_cmp(&b.elevation))
+            .cloned();
+
+        let highest = highest_terrain
+            .unwrap_or(0.0)
+            .max(highest_obstacle.as_ref().map_or(0.0, |o| o.elevation));
+        let minimum_safe_altitude = ((highest + self.config.margin) / 100.0).ceil() * 100.0;
+
+        Ok(Some(LegClearance {
+            leg: branch.name(),
+            highest_terrain,
+            highest_obstacle,
+            minimum_safe_altitude,
+            planned_altitude: branch.altitude,
+            missing_terrain,
+        }))
+    }
+
+    /// Computes the clearance of every leg of a navigation.
+    /// A leg without altitude is checked at the altitude of the previous leg.
+    pub fn check_navigation(&mut self, navigation: &Navigation) -> Result<Vec<LegClearance>, TerrainError> {
+        let mut clearances = Vec::new();
+        let mut altitude = None;
+        for branch in &navigation.branches {
+            altitude = branch.altitude.or(altitude);
+            if let Some(mut clearance) = self.leg_clearance(branch)? {
+                clearance.planned_altitude = altitude;
+                clearances.push(clearance);
+            }
+        }
+        Ok(clearances)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navigation::flightplan::Wind;
+    use crate::navigation::geographics::EarthModel;
+
+    const SIZE: usize = 121;
+
+    /// Writes a 1°x1° tile N45E006 at 100 m, with a 2000 m peak within 5 NM north of the 45.2°N
+    /// parallel and a 3000 m peak 33 km north of it, and returns its configuration
+    fn terrain(test: &str, obstacles: &str) -> TerrainConfig {
+        let dir = std::env::temp_dir().join(format!("terrain_{}", test));
+        let tiles_dir = dir.join("terrain");
+        fs::create_dir_all(&tiles_dir).unwrap();
+        let mut samples = vec![100i16; SIZE * SIZE];
+        samples[90 * SIZE + 60] = 2000; // 45.25°N 6.5°E
+        samples[60 * SIZE + 60] = 3000; // 45.5°N 6.5°E
+        let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_be_bytes()).collect();
+        fs::write(tiles_dir.join("N45E006.hgt"), bytes).unwrap();
+        let obstacle_file = dir.join("obstacles.csv");
+        fs::write(&obstacle_file, format!("name,latitude,longitude,elevation_ft\n{}", obstacles)).unwrap();
+        TerrainConfig {
+            tiles_dir,
+            obstacle_file,
+            corridor_width: 5.0,
+            margin: 1000.0,
+        }
+    }
+
+    fn leg(from: (f64, f64), to: (f64, f64), altitude: Option<f64>) -> NavBranch {
+        NavBranch::between(
+            NavPoint::new("A".to_string(), from.0, from.1),
+            NavPoint::new("B".to_string(), to.0, to.1),
+            Wind { speed: 0.0, direction: 0.0 },
+            altitude,
+            EarthModel::spherical,
+        )
+    }
+
+    #[test]
+    fn highest_terrain_within_the_corridor() {
+        let config = terrain("corridor", "");
+        let dir = config.tiles_dir.parent().unwrap().to_path_buf();
+        let mut terrain = Terrain::open(config).unwrap();
+        let clearance = terrain.leg_clearance(&leg((45.2, 6.2), (45.2, 6.8), Some(7500.0))).unwrap().unwrap();
+        // The 3000 m peak is outside of the corridor
+        assert!((clearance.highest_terrain.unwrap() - 2000.0 * FEET_PER_METER).abs() < 0.01);
+        assert_eq!(clearance.minimum_safe_altitude, 7600.0);
+        assert!(!clearance.missing_terrain);
+        assert!(clearance.is_below_minimum());
+
+        // A narrower corridor misses the first peak too
+        terrain.config.corridor_width = 2.0;
+        let clearance = terrain.leg_clearance(&leg((45.2, 6.2), (45.2, 6.8), Some(7500.0))).unwrap().unwrap();
+        assert!((clearance.highest_terrain.unwrap() - 100.0 * FEET_PER_METER).abs() < 0.01);
+        assert!(!clearance.is_below_minimum());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn obstacle_within_the_corridor() {
+        let config = terrain("obstacle", "Mast,45.21,6.3,8000\nTower,45.6,6.3,9000\n");
+        let dir = config.tiles_dir.parent().unwrap().to_path_buf();
+        let mut terrain = Terrain::open(config).unwrap();
+        let clearance = terrain.leg_clearance(&leg((45.2, 6.2), (45.2, 6.8), Some(8500.0))).unwrap().unwrap();
+        assert_eq!(clearance.highest_obstacle.as_ref().unwrap().name, "Mast");
+        assert_eq!(clearance.minimum_safe_altitude, 9000.0);
+        assert!(clearance.is_below_minimum());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_tile_fails_closed() {
+        let config = terrain("missing", "");
+        let dir = config.tiles_dir.parent().unwrap().to_path_buf();
+        let mut terrain = Terrain::open(config).unwrap();
+        // The leg ends over N45E007, which is not available
+        let clearance = terrain.leg_clearance(&leg((45.2, 6.2), (45.2, 7.2), Some(12000.0))).unwrap().unwrap();
+        assert!(clearance.missing_terrain);
+        assert!(clearance.is_below_minimum());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn legs_keep_the_previous_altitude() {
+        let config = terrain("navigation", "");
+        let dir = config.tiles_dir.parent().unwrap().to_path_buf();
+        let mut terrain = Terrain::open(config).unwrap();
+        let branches = vec![leg((45.8, 6.2), (45.8, 6.5), Some(7500.0)), leg((45.8, 6.5), (45.2, 6.5), None)];
+        let navigation = Navigation::new(NavPoint::new("A".to_string(), 45.8, 6.2), NavPoint::new("B".to_string(), 45.2, 6.5), branches);
+        let clearances = terrain.check_navigation(&navigation).unwrap();
+        assert!(!clearances[0].is_below_minimum());
+        // The second leg crosses the 3000 m peak at the altitude of the first one
+        assert_eq!(clearances[1].planned_altitude, Some(7500.0));
+        assert!(clearances[1].is_below_minimum());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,107 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+
+/// Returns the time zone used at a location, from the ISO 3166-1 country code
+/// stored in `airports.iso_country`.
+///
+/// The lookup is fully offline. Countries spanning several zones, or missing from the
+/// table, fall back to the nautical time zone of the longitude (15° per hour).
+pub fn from_country(iso_country: &str, longitude: f64) -> Tz {
+    let zone = match iso_country.to_uppercase().as_str() {
+        "FR" | "MC" => "Europe/Paris",
+        "BE" => "Europe/Brussels",
+        "LU" => "Europe/Luxembourg",
+        "NL" => "Europe/Amsterdam",
+        "DE" => "Europe/Berlin",
+        "CH" => "Europe/Zurich",
+        "LI" => "Europe/Vaduz",
+        "AT" => "Europe/Vienna",
+        "IT" | "SM" | "VA" => "Europe/Rome",
+        "ES" if longitude > -10.0 => "Europe/Madrid",
+        "ES" => "Atlantic/Canary",
+        "PT" if longitude > -20.0 => "Europe/Lisbon",
+        "PT" => "Atlantic/Azores",
+        "AD" => "Europe/Andorra",
+        "GB" | "GG" | "JE" | "IM" => "Europe/London",
+        "IE" => "Europe/Dublin",
+        "DK" => "Europe/Copenhagen",
+        "NO" => "Europe/Oslo",
+        "SE" => "Europe/Stockholm",
+        "FI" => "Europe/Helsinki",
+        "IS" => "Atlantic/Reykjavik",
+        "PL" => "Europe/Warsaw",
+        "CZ" => "Europe/Prague",
+        "SK" => "Europe/Bratislava",
+        "HU" => "Europe/Budapest",
+        "SI" => "Europe/Ljubljana",
+        "HR" => "Europe/Zagreb",
+        "GR" => "Europe/Athens",
+        "MT" => "Europe/Malta",
+        "CY" => "Asia/Nicosia",
+        "RO" => "Europe/Bucharest",
+        "BG" => "Europe/Sofia",
+        "EE" => "Europe/Tallinn",
+        "LV" => "Europe/Riga",
+        "LT" => "Europe/Vilnius",
+        "MA" => "Africa/Casablanca",
+        "DZ" => "Africa/Algiers",
+        "TN" => "Africa/Tunis",
+        "GP" => "America/Guadeloupe",
+        "MQ" => "America/Martinique",
+        "GF" => "America/Cayenne",
+        "PM" => "America/Miquelon",
+        "RE" => "Indian/Reunion",
+        "YT" => "Indian/Mayotte",
+        "NC" => "Pacific/Noumea",
+        "WF" => "Pacific/Wallis",
+        _ => return nautical(longitude),
+    };
+    zone.parse().unwrap_or_else(|_| nautical(longitude))
+}
+
+/// Nautical time zone of a longitude, as an `Etc/GMT±n` zone.
+/// Note that the sign of `Etc` zones is inverted: `Etc/GMT-1` is UTC+1.
+pub fn nautical(longitude: f64) -> Tz {
+    let offset = (longitude / 15.0).round().clamp(-12.0, 12.0) as i32;
+    let name = if offset == 0 {
+        "Etc/GMT".to_string()
+    } else {
+        format!("Etc/GMT{:+}", -offset)
+    };
+    name.parse().unwrap_or(Tz::UTC)
+}
+
+/// Formats a UTC instant the way it is written on a nav log, with the local time alongside,
+/// e.g. `14:05Z (16:05 LT)`.
+pub fn format_utc_and_local(time: DateTime<Utc>, zone: Tz) -> String {
+    format!("{} ({} LT)", time.format("%H:%MZ"), time.with_timezone(&zone).format("%H:%M"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn country_table_and_split_countries() {
+        assert_eq!(from_country("FR", 2.3), chrono_tz::Europe::Paris);
+        assert_eq!(from_country("gp", -61.5), chrono_tz::America::Guadeloupe);
+        assert_eq!(from_country("ES", -3.7), chrono_tz::Europe::Madrid);
+        assert_eq!(from_country("ES", -15.4), chrono_tz::Atlantic::Canary);
+        assert_eq!(from_country("PT", -9.1), chrono_tz::Europe::Lisbon);
+        assert_eq!(from_country("PT", -25.7), chrono_tz::Atlantic::Azores);
+    }
+
+    #[test]
+    fn unknown_countries_use_the_nautical_zone() {
+        assert_eq!(from_country("US", -74.0), chrono_tz::Etc::GMTPlus5);
+        assert_eq!(from_country("", 0.0), chrono_tz::Etc::GMT);
+        assert_eq!(nautical(37.6), chrono_tz::Etc::GMTMinus3);
+        assert_eq!(nautical(180.0), chrono_tz::Etc::GMTMinus12);
+    }
+
+    #[test]
+    fn formats_utc_with_local_time() {
+        let time: DateTime<Utc> = DateTime::parse_from_rfc3339("2024-01-15T14:05:00Z").unwrap().into();
+        assert_eq!(format_utc_and_local(time, chrono_tz::Europe::Paris), "14:05Z (15:05 LT)");
+    }
+}
@@ -0,0 +1,280 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use super::database::parse_database;
+use super::error::ImportError;
+use super::geographics::{calculate_distance_and_course, NavPoint};
+use super::waypoint::WaypointKind;
+
+/// Columns of the `user_waypoints` table read by `UserWaypoint::user_waypoint_mapper`
+pub const USER_WAYPOINT_COLUMNS: &str = "id, name, latitude, longitude, type, airport, notes";
+
+const CREATE_USER_WAYPOINTS: &str = r#"CREATE TABLE IF NOT EXISTS "user_waypoints" (
+	"id"	INTEGER,
+	"name"	TEXT NOT NULL,
+	"latitude"	NUMERIC NOT NULL,
+	"longitude"	NUMERIC NOT NULL,
+	"type"	TEXT,
+	"airport"	TEXT,
+	"notes"	TEXT,
+	PRIMARY KEY("id" AUTOINCREMENT)
+);
+CREATE INDEX IF NOT EXISTS "user_waypoints_name" ON "user_waypoints" ("name");"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum UserWaypointType {
+    /// VFR reporting point published on the approach charts, e.g. "NORD" or "SIERRA"
+    reporting_point,
+    /// Landmark picked by the pilot: a castle, a lake, a motorway junction...
+    landmark,
+    #[default]
+    other,
+}
+
+impl UserWaypointType {
+    pub fn from_db_type(waypoint_type: &str) -> UserWaypointType {
+        match waypoint_type {
+            "reporting_point" => UserWaypointType::reporting_point,
+            "landmark" => UserWaypointType::landmark,
+            _ => UserWaypointType::other,
+        }
+    }
+
+    pub fn db_type(&self) -> &'static str {
+        match self {
+            UserWaypointType::reporting_point => "reporting_point",
+            UserWaypointType::landmark => "landmark",
+            UserWaypointType::other => "other",
+        }
+    }
+}
+
+/// A waypoint defined by the user, stored in the `user_waypoints` table.
+/// Also the layout of the CSV import and export files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserWaypoint {
+    #[serde(skip)]
+    pub id: Option<i64>, // None until saved
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    #[serde(rename = "type", default)]
+    pub waypoint_type: UserWaypointType,
+    #[serde(default)]
+    pub airport: Option<String>, // ICAO code of the airport the point belongs to
+    #[serde(default)]
+    pub notes: String,
+}
+
+impl UserWaypoint {
+    pub fn new(name: String, latitude: f64, longitude: f64, waypoint_type: UserWaypointType, airport: Option<String>, notes: String) -> UserWaypoint {
+        UserWaypoint {
+            id: None,
+            name: name.to_uppercase(),
+            latitude,
+            longitude,
+            waypoint_type,
+            airport,
+            notes,
+        }
+    }
+
+    /// Creates the `user_waypoints` table if it does not exist yet
+    pub fn create_table(db_path: &str) -> Result<(), rusqlite::Error> {
+        Connection::open(db_path)?.execute_batch(CREATE_USER_WAYPOINTS)
+    }
+
+    pub fn user_waypoint_mapper(row: &rusqlite::Row) -> rusqlite::Result<UserWaypoint> {
+        Ok(UserWaypoint {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            latitude: row.get(2)?,
+            longitude: row.get(3)?,
+            waypoint_type: UserWaypointType::from_db_type(&row.get::<_, Option<String>>(4)?.unwrap_or_default()),
+            airport: row.get::<_, Option<String>>(5)?.filter(|airport| !airport.is_empty()),
+            notes: row.get::<_, Option<String>>(6)?.unwrap_or_default(),
+        })
+    }
+
+    /// All user waypoints with this name; the same name may be used around several airports
+    pub fn from_database(db_path: &str, name: &str) -> Result<Vec<UserWaypoint>, rusqlite::Error> {
+        parse_database(db_path, "user_waypoints", "name", &name.to_uppercase(), USER_WAYPOINT_COLUMNS, Self::user_waypoint_mapper)
+    }
+
+    /// All user waypoints, sorted by airport then name
+    pub fn list(db_path: &str) -> Result<Vec<UserWaypoint>, rusqlite::Error> {
+        let conn = Connection::open(db_path)?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM user_waypoints ORDER BY airport, name", USER_WAYPOINT_COLUMNS))?;
+        let waypoints = stmt.query_map([], Self::user_waypoint_mapper)?;
+        waypoints.collect()
+    }
+
+    /// The user waypoint with this name nearest to `previous`, usually the previous waypoint of the route
+    pub fn nearest(db_path: &str, name: &str, previous: &NavPoint) -> Result<Option<UserWaypoint>, rusqlite::Error> {
+        let distance = |waypoint: &UserWaypoint| {
+            calculate_distance_and_course(previous, &waypoint.to_nav_point()).0.unwrap_or(f64::INFINITY)
+        };
+        Ok(Self::from_database(db_path, name)?
+            .into_iter()
+            .min_by(|a, b| distance(a).total_cmp(&distance(b))))
+    }
+
+    /// Saves a new waypoint and sets its id
+    pub fn insert(&mut self, db_path: &str) -> Result<i64, rusqlite::Error> {
+        let conn = Connection::open(db_path)?;
+        conn.execute(
+            "INSERT INTO user_waypoints (name, latitude, longitude, type, airport, notes) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![self.name, self.latitude, self.longitude, self.waypoint_type.db_type(), self.airport, self.notes],
+        )?;
+        let id = conn.last_insert_rowid();
+        self.id = Some(id);
+        Ok(id)
+    }
+
+    /// Saves the changes of a waypoint already in the database
+    pub fn update(&self, db_path: &str) -> Result<(), rusqlite::Error> {
+        let id = self.id.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+        let conn = Connection::open(db_path)?;
+        let updated = conn.execute(
+            "UPDATE user_waypoints SET name = ?1, latitude = ?2, longitude = ?3, type = ?4, airport = ?5, notes = ?6 WHERE id = ?7",
+            params![self.name, self.latitude, self.longitude, self.waypoint_type.db_type(), self.airport, self.notes, id],
+        )?;
+        if updated == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        Ok(())
+    }
+
+    pub fn delete(db_path: &str, id: i64) -> Result<(), rusqlite::Error> {
+        let conn = Connection::open(db_path)?;
+        conn.execute("DELETE FROM user_waypoints WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    /// Adds the waypoints of a CSV file with the columns name, latitude, longitude, type, airport, notes.
+    /// A waypoint with the same name and airport as one of the database replaces it, so that
+    /// importing a file again does not duplicate its waypoints.
+    /// Returns the number of waypoints added or replaced.
+    pub fn import_csv(db_path: &str, csv_path: &str) -> Result<usize, ImportError> {
+        let mut reader = csv::Reader::from_path(csv_path)?;
+        let mut conn = Connection::open(db_path)?;
+        let transaction = conn.transaction()?;
+        let mut count = 0;
+        {
+            let mut existing = transaction.prepare("SELECT id FROM user_waypoints WHERE name = ?1 AND IFNULL(airport, '') = IFNULL(?2, '')")?;
+            for waypoint in reader.deserialize() {
+                let waypoint: UserWaypoint = waypoint?;
+                let waypoint = UserWaypoint::new(waypoint.name, waypoint.latitude, waypoint.longitude, waypoint.waypoint_type, waypoint.airport, waypoint.notes);
+                let id: Option<i64> = existing.query_row(params![waypoint.name, waypoint.airport], |row| row.get(0)).optional()?;
+                transaction.execute(
+                    "INSERT OR REPLACE INTO user_waypoints (id, name, latitude, longitude, type, airport, notes) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![id, waypoint.name, waypoint.latitude, waypoint.longitude, waypoint.waypoint_type.db_type(), waypoint.airport, waypoint.notes],
+                )?;
+                count += 1;
+            }
+        }
+        transaction.commit()?;
+        Ok(count)
+    }
+
+    /// Writes all user waypoints to a CSV file readable by `import_csv`.
+    /// Returns the number of waypoints written.
+    pub fn export_csv(db_path: &str, csv_path: &str) -> Result<usize, ImportError> {
+        let waypoints = Self::list(db_path)?;
+        let mut writer = csv::Writer::from_path(csv_path)?;
+        for waypoint in &waypoints {
+            writer.serialize(waypoint)?;
+        }
+        writer.flush().map_err(csv::Error::from)?;
+        Ok(waypoints.len())
+    }
+
+    /// Navigation point at the waypoint, named after it
+    pub fn to_nav_point(&self) -> NavPoint {
+        let mut point = NavPoint::new(self.name.clone(), self.latitude, self.longitude);
+        point.kind = WaypointKind::user_waypoint(self.name.clone());
+        point
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database(name: &str) -> String {
+        let db_path = std::env::temp_dir().join(format!("flight_planner_user_waypoints_{}.db", name));
+        let db_path = db_path.to_str().unwrap().to_string();
+        std::fs::copy("data/airports.db", &db_path).unwrap();
+        Connection::open(&db_path).unwrap().execute("DROP TABLE IF EXISTS user_waypoints", []).unwrap();
+        UserWaypoint::create_table(&db_path).unwrap();
+        db_path
+    }
+
+    #[test]
+    fn insert_update_and_delete() {
+        let db_path = database("crud");
+        let mut waypoint = UserWaypoint::new("nord".to_string(), 48.80, 2.10, UserWaypointType::reporting_point, Some("LFPN".to_string()), String::new());
+        let id = waypoint.insert(&db_path).unwrap();
+        assert_eq!(waypoint.id, Some(id));
+
+        let saved = UserWaypoint::from_database(&db_path, "Nord").unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].name, "NORD");
+        assert_eq!(saved[0].airport.as_deref(), Some("LFPN"));
+        assert_eq!(saved[0].waypoint_type, UserWaypointType::reporting_point);
+
+        waypoint.latitude = 48.81;
+        waypoint.notes = "Chateau d'eau".to_string();
+        waypoint.update(&db_path).unwrap();
+        let saved = UserWaypoint::list(&db_path).unwrap();
+        assert_eq!(saved[0].latitude, 48.81);
+        assert_eq!(saved[0].notes, "Chateau d'eau");
+
+        UserWaypoint::delete(&db_path, id).unwrap();
+        assert!(UserWaypoint::list(&db_path).unwrap().is_empty());
+        assert!(waypoint.update(&db_path).is_err());
+        std::fs::remove_file(db_path).unwrap();
+    }
+
+    #[test]
+    fn nearest_of_the_same_name() {
+        let db_path = database("nearest");
+        UserWaypoint::new("NORD".to_string(), 48.80, 2.10, UserWaypointType::reporting_point, Some("LFPN".to_string()), String::new()).insert(&db_path).unwrap();
+        UserWaypoint::new("NORD".to_string(), 43.70, 7.20, UserWaypointType::reporting_point, Some("LFMN".to_string()), String::new()).insert(&db_path).unwrap();
+        let previous = NavPoint::new("LFPN".to_string(), 48.75, 2.11);
+        let nearest = UserWaypoint::nearest(&db_path, "nord", &previous).unwrap().unwrap();
+        assert_eq!(nearest.airport.as_deref(), Some("LFPN"));
+        std::fs::remove_file(db_path).unwrap();
+    }
+
+    #[test]
+    fn csv_round_trip_does_not_duplicate() {
+        let db_path = database("csv");
+        let csv_path = std::env::temp_dir().join("flight_planner_user_waypoints.csv");
+        let csv_path = csv_path.to_str().unwrap();
+        std::fs::write(
+            csv_path,
+            "name,latitude,longitude,type,airport,notes\n\
+             nord,48.80,2.10,reporting_point,LFPN,\n\
+             NORD,43.70,7.20,reporting_point,LFMN,\n\
+             chateau,48.60,1.90,landmark,,\"Tour, ruines\"\n",
+        )
+        .unwrap();
+        assert_eq!(UserWaypoint::import_csv(&db_path, csv_path).unwrap(), 3);
+        assert_eq!(UserWaypoint::export_csv(&db_path, csv_path).unwrap(), 3);
+
+        // Importing the exported file again replaces the waypoints instead of adding them
+        UserWaypoint::delete(&db_path, UserWaypoint::from_database(&db_path, "CHATEAU").unwrap()[0].id.unwrap()).unwrap();
+        UserWaypoint::new("CHATEAU".to_string(), 0.0, 0.0, UserWaypointType::other, None, String::new()).insert(&db_path).unwrap();
+        assert_eq!(UserWaypoint::import_csv(&db_path, csv_path).unwrap(), 3);
+        let waypoints = UserWaypoint::list(&db_path).unwrap();
+        assert_eq!(waypoints.len(), 3);
+        let chateau = waypoints.iter().find(|w| w.name == "CHATEAU").unwrap();
+        assert_eq!((chateau.latitude, chateau.longitude), (48.60, 1.90));
+        assert_eq!(chateau.waypoint_type, UserWaypointType::landmark);
+        assert_eq!(chateau.airport, None);
+        assert_eq!(chateau.notes, "Tour, ruines");
+        std::fs::remove_file(db_path).unwrap();
+        std::fs::remove_file(csv_path).unwrap();
+    }
+}
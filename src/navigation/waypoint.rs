@@ -0,0 +1,85 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::geographics::{distance_and_course, project_point, EarthModel, NavPoint, KM_TO_NM};
+
+/// What a `NavPoint` was defined from
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub enum WaypointKind {
+    /// Plain coordinates
+    #[default]
+    coordinates,
+    /// Airport, by ICAO code
+    airport(String),
+    /// Radio navaid (VOR, NDB, DME, ...), by ident
+    navaid(String),
+    /// User waypoint or VFR reporting point, by name
+    user_waypoint(String),
+    /// Bearing and distance from a reference point
+    radial_distance(RadialDistance),
+}
+
+/// Position defined by a bearing and a distance from a reference, e.g. "radial 245 / 12 NM from PTS"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RadialDistance {
+    pub reference: Box<NavPoint>,
+    pub bearing: f64,                    // degrees from the reference
+    pub magnetic_variation: Option<f64>, // Some when `bearing` is magnetic, degrees east positive
+    pub distance: f64,                   // NM
+}
+
+impl RadialDistance {
+    pub fn new(reference: NavPoint, bearing: f64, magnetic_variation: Option<f64>, distance: f64) -> RadialDistance {
+        RadialDistance {
+            reference: Box::new(reference),
+            bearing,
+            magnetic_variation,
+            distance,
+        }
+    }
+
+    /// Bearing from the reference in degrees true
+    pub fn true_bearing(&self) -> f64 {
+        (self.bearing + self.magnetic_variation.unwrap_or(0.0)).rem_euclid(360.0)
+    }
+
+    /// Resolves the definition to coordinates by solving the direct geodesic problem
+    pub fn resolve(&self, name: String, model: EarthModel) -> NavPoint {
+        let mut point = project_point(&self.reference, self.true_bearing(), self.distance / KM_TO_NM, model, name);
+        point.kind = WaypointKind::radial_distance(self.clone());
+        point
+    }
+
+    /// Describes `position` as a bearing and distance from the nearest of `references`, each given with
+    /// its magnetic variation. The bearing is magnetic when the variation of the reference is known.
+    pub fn describe(position: &NavPoint, references: &[(NavPoint, Option<f64>)], model: EarthModel) -> Option<RadialDistance> {
+        references
+            .iter()
+            .filter_map(|(reference, magnetic_variation)| match distance_and_course(reference, position, model) {
+                (Some(distance), Some(course)) => Some((reference, *magnetic_variation, distance, course)),
+                _ => None,
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(reference, magnetic_variation, distance, course)| {
+                RadialDistance::new(
+                    reference.clone(),
+                    (course - magnetic_variation.unwrap_or(0.0)).rem_euclid(360.0),
+                    magnetic_variation,
+                    distance * KM_TO_NM,
+                )
+            })
+    }
+
+    /// ICAO flight plan form: reference, bearing on 3 digits and distance on 3 digits, e.g. `PTS245012`
+    pub fn icao(&self) -> String {
+        format!("{}{:03.0}{:03.0}", self.reference.name, self.bearing.round().rem_euclid(360.0), self.distance.round())
+    }
+}
+
+impl fmt::Display for RadialDistance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let north = if self.magnetic_variation.is_some() { "M" } else { "T" };
+        write!(f, "{} {:03.0}°{}/{:.1} NM", self.reference.name, self.bearing.round().rem_euclid(360.0), north, self.distance)
+    }
+}